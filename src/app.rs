@@ -1,9 +1,25 @@
+mod adjustments;
 pub mod canvas;
+mod filters;
+mod histogram;
+mod history;
+mod keymap;
+mod layers;
+mod palette;
+mod preview;
+mod project;
+mod quantize;
+mod resize;
+mod selection;
+mod settings;
+mod transform;
+mod viewport;
+use std::path::{Path, PathBuf};
+
 use canvas::Canvas;
 use egui::Color32;
-use image::Pixel;
 
-#[derive(Default)]
+#[derive(Default, PartialEq, Clone, Copy, serde::Serialize, serde::Deserialize)]
 enum Instrument {
     #[default]
     Pencil,
@@ -13,9 +29,35 @@ enum Instrument {
     SharpLine,
     SmoothLine,
     Triangle,
+    TexturedTriangle,
+    Polygon,
+    Circle,
+    Ellipse,
+    Curve,
+    Rectangle,
+    Eyedropper,
+    Select,
 }
 
 impl Instrument {
+    const ALL: [Instrument; 15] = [
+        Self::Pencil,
+        Self::Bucket,
+        Self::ImageBucket,
+        Self::Border,
+        Self::SharpLine,
+        Self::SmoothLine,
+        Self::Triangle,
+        Self::TexturedTriangle,
+        Self::Polygon,
+        Self::Circle,
+        Self::Ellipse,
+        Self::Curve,
+        Self::Rectangle,
+        Self::Eyedropper,
+        Self::Select,
+    ];
+
     fn get_name(&self) -> String {
         match self {
             Self::Pencil => String::from("карандаш"),
@@ -25,6 +67,14 @@ impl Instrument {
             Self::SharpLine => String::from("линия"),
             Self::SmoothLine => String::from("размытая линия"),
             Self::Triangle => String::from("треугольник"),
+            Self::TexturedTriangle => String::from("текстурированный треугольник"),
+            Self::Polygon => String::from("многоугольник"),
+            Self::Circle => String::from("окружность"),
+            Self::Ellipse => String::from("эллипс"),
+            Self::Curve => String::from("кривая"),
+            Self::Rectangle => String::from("прямоугольник"),
+            Self::Eyedropper => String::from("пипетка"),
+            Self::Select => String::from("прямоугольное выделение"),
         }
     }
 }
@@ -32,26 +82,103 @@ impl Instrument {
 #[derive(Default)]
 pub struct ColorsApp {
     // обработка холста
-    canvas: Canvas,
+    layers: layers::LayerStack,
+    composite: Canvas,
     needs_redraw: bool,
     texture_handle: Option<egui::TextureHandle>,
+    history: history::History,
 
     // рисование на холсте
     cur_color: egui::Color32,
     points: Vec<egui::Pos2>,
     colors: Vec<egui::Color32>,
     cur_instrument: Instrument,
+    modifiers: egui::Modifiers,
+    /// Enter нажат, когда ввод с клавиатуры не ждёт ни одно поле
+    enter_pressed: bool,
     loaded_image: Option<egui::ColorImage>,
+    loaded_texture: Option<canvas::Texture>,
     connectivity: canvas::Connectivity,
 
+    // цвета и палитра
+    secondary_color: egui::Color32,
+    recent_colors: Vec<egui::Color32>,
+    palette: palette::Palette,
+    eyedropper_size: usize,
+
+    // наложение текстуры
+    texture_sampler: canvas::TextureSampler,
+    texture_uvs: [egui::Pos2; 3],
+
+    // заливка многоугольника
+    contours: Vec<Vec<egui::Pos2>>,
+    contour_colors: Vec<Vec<egui::Color32>>,
+    fill_rule: canvas::FillRule,
+    gouraud_polygon: bool,
+    triangulate_polygon: bool,
+
+    // окружности и эллипсы
+    conic_filled: bool,
+    conic_smooth: bool,
+    ellipse_angle: f32,
+
+    // кривые
+    curve_kind: canvas::CurveKind,
+    curve_method: canvas::CurveMethod,
+    curve_smooth: bool,
+    dragged_point: Option<usize>,
+
+    // линии
+    polyline_mode: bool,
+
+    // прямоугольники
+    rect_corner_radius: f32,
+    rect_stroke: bool,
+    rect_stroke_width: f32,
+    rect_filled: bool,
+    rect_fill: canvas::Fill,
+
+    // предпросмотр незавершённых построений
+    ghost_texture: Option<(egui::TextureHandle, canvas::PixelRect)>,
+    ghost_state: Option<preview::GhostState>,
+
+    // файлы и настройки
+    canvas_path: Option<PathBuf>,
+    bucket_image_path: Option<PathBuf>,
+    reopen_last: bool,
+
+    // горячие клавиши
+    keymap: keymap::Keymap,
+    show_shortcuts: bool,
+
     // создание нового холста
     show_new_canvas_popup: bool,
     new_canvas_width: usize,
     new_canvas_height: usize,
+    new_canvas_transparent: bool,
 
     // отоображение холста
+    viewport: viewport::Viewport,
+    checker_texture: Option<egui::TextureHandle>,
+    show_pixel_grid: bool,
+    hovered_pixel: Option<[usize; 2]>,
     display_canvas_width: f32,
     display_canvas_height: f32,
+
+    // Выделение и окна меню Image, Adjustments и Filters
+    selection: Option<canvas::PixelRect>,
+    selection_anchor: Option<[usize; 2]>,
+    resize_dialog: Option<resize::ResizeDialog>,
+    transform_dialog: Option<transform::TransformDialog>,
+    filter_dialog: Option<filters::FilterDialog>,
+    adjustment_dialog: Option<adjustments::AdjustmentDialog>,
+    quantize_dialog: Option<quantize::QuantizeDialog>,
+
+    // панель слоёв: номер и имя слоя, пока его имя редактируют
+    layer_name: (usize, String),
+
+    // гистограмма активного слоя
+    histogram_panel: histogram::HistogramPanel,
 }
 
 // =============== Инициализация приложения ===============
@@ -61,14 +188,39 @@ impl ColorsApp {
     const INIT_CANVAS_WIDTH: usize = 160;
     const INIT_CANVAS_HEIGHT: usize = 90;
 
+    // Радиус (в пикселях экрана) маркеров контрольных точек
+    const HANDLE_RADIUS: f32 = 5.0;
+
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         cc.egui_ctx.set_theme(egui::Theme::Light);
-        let mut tmp = Self::default();
-        tmp.canvas = Canvas::new(ColorsApp::INIT_CANVAS_WIDTH, ColorsApp::INIT_CANVAS_HEIGHT);
-        tmp.needs_redraw = true;
-        tmp.show_new_canvas_popup = false;
-        tmp.cur_color = egui::Color32::BLACK;
-        tmp
+        let mut app = Self {
+            layers: layers::LayerStack::new(Canvas::new(
+                ColorsApp::INIT_CANVAS_WIDTH,
+                ColorsApp::INIT_CANVAS_HEIGHT,
+            )),
+            needs_redraw: true,
+            show_new_canvas_popup: false,
+            cur_color: egui::Color32::BLACK,
+            secondary_color: egui::Color32::WHITE,
+            eyedropper_size: 1,
+            keymap: keymap::Keymap::load(),
+            rect_stroke: true,
+            rect_stroke_width: 1.0,
+            texture_uvs: [
+                egui::Pos2::new(0.0, 0.0),
+                egui::Pos2::new(1.0, 0.0),
+                egui::Pos2::new(0.0, 1.0),
+            ],
+            ..Default::default()
+        };
+
+        // восстановить настройки прошлого запуска
+        if let Some(storage) = cc.storage
+            && let Some(settings) = eframe::get_value(storage, eframe::APP_KEY)
+        {
+            app.apply_settings(settings);
+        }
+        app
     }
 }
 
@@ -106,16 +258,15 @@ impl ColorsApp {
     fn handle_bucket(&mut self, canvas_rect: egui::Rect, response: &egui::Response) {
         if response.clicked()
             && let Some(pointer_pos) = response.hover_pos()
+            && let Some(pos) = self.coord_screen_to_canvas(pointer_pos, canvas_rect)
         {
-            if let Some(pos) = self.coord_screen_to_canvas(pointer_pos, canvas_rect) {
-                let color = self.cur_color;
-                let connect = self.connectivity;
-                self.canvas_mut(&response.ctx)
-                    .fill_with_color(pos, color, connect);
+            let color = self.cur_color;
+            let connect = self.connectivity;
+            self.canvas_mut(&response.ctx)
+                .fill_with_color(pos, color, connect);
 
-                #[cfg(debug_assertions)]
-                println!("заливка {:#?} в {:#?}", self.cur_color, pos);
-            }
+            #[cfg(debug_assertions)]
+            println!("заливка {:#?} в {:#?}", self.cur_color, pos);
         }
     }
 
@@ -123,17 +274,15 @@ impl ColorsApp {
     fn handle_image_bucket(&mut self, canvas_rect: egui::Rect, response: &egui::Response) {
         if response.clicked()
             && let Some(pointer_pos) = response.hover_pos()
+            && let Some(pos) = self.coord_screen_to_canvas(pointer_pos, canvas_rect)
+            && let Some(img) = self.loaded_image.clone()
         {
-            if let Some(pos) = self.coord_screen_to_canvas(pointer_pos, canvas_rect)
-                && let Some(img) = self.loaded_image.clone()
-            {
-                let connect = self.connectivity;
-                self.canvas_mut(&response.ctx)
-                    .fill_with_img(pos, &img, connect);
+            let connect = self.connectivity;
+            self.canvas_mut(&response.ctx)
+                .fill_with_img(pos, &img, connect);
 
-                #[cfg(debug_assertions)]
-                println!("заливка картинкой в {:#?}", pos);
-            }
+            #[cfg(debug_assertions)]
+            println!("заливка картинкой в {:#?}", pos);
         }
     }
 
@@ -141,65 +290,87 @@ impl ColorsApp {
     fn handle_border(&mut self, canvas_rect: egui::Rect, response: &egui::Response) {
         if response.clicked()
             && let Some(pointer_pos) = response.hover_pos()
+            && let Some(pos) = self.coord_screen_to_canvas(pointer_pos, canvas_rect)
         {
-            if let Some(pos) = self.coord_screen_to_canvas(pointer_pos, canvas_rect) {
-                let boudary = self.canvas_mut(&response.ctx).trace_boundary(pos);
-                let color = self.cur_color;
-                self.canvas_mut(&response.ctx)
-                    .draw_boundary(&boudary, color);
+            let boudary = self.canvas_mut(&response.ctx).trace_boundary(pos);
+            let color = self.cur_color;
+            self.canvas_mut(&response.ctx)
+                .draw_boundary(&boudary, color);
 
-                #[cfg(debug_assertions)]
-                println!("выделение границы в {:#?}", pos);
-            }
+            #[cfg(debug_assertions)]
+            println!("выделение границы в {:#?}", pos);
         }
     }
 
     /// Обрабатывает рисование линии
     fn handle_sharp_line(&mut self, canvas_rect: egui::Rect, response: &egui::Response) {
+        if self.polyline_mode {
+            self.handle_polyline(canvas_rect, response);
+            return;
+        }
+
         if response.clicked()
             && let Some(pointer_pos) = response.hover_pos()
+            && let Some(pos) = self.coord_screen_to_canvas(pointer_pos, canvas_rect)
         {
-            if let Some(pos) = self.coord_screen_to_canvas(pointer_pos, canvas_rect) {
-                if self.points.len() < 1 {
-                    self.points.push(pos);
-
-                    #[cfg(debug_assertions)]
-                    println!("поставлена точка линии в {:#?}", pos);
-                    return;
-                }
-
-                let prev_pos = self.points.pop().unwrap();
-                let color = self.cur_color;
-                self.canvas_mut(&response.ctx)
-                    .draw_sharp_line(prev_pos, pos, color);
+            if self.points.is_empty() {
+                self.points.push(pos);
 
                 #[cfg(debug_assertions)]
-                println!("нарисована линия цвета {:#?}", self.cur_color);
+                println!("поставлена точка линии в {:#?}", pos);
+                return;
             }
+
+            self.commit_pending(&response.ctx, Some(pos));
+
+            #[cfg(debug_assertions)]
+            println!("нарисована линия цвета {:#?}", self.cur_color);
         }
     }
 
     /// Обрабатывает рисование размытой линии
     fn handle_smooth_line(&mut self, canvas_rect: egui::Rect, response: &egui::Response) {
+        if self.polyline_mode {
+            self.handle_polyline(canvas_rect, response);
+            return;
+        }
+
         if response.clicked()
             && let Some(pointer_pos) = response.hover_pos()
+            && let Some(pos) = self.coord_screen_to_canvas(pointer_pos, canvas_rect)
         {
-            if let Some(pos) = self.coord_screen_to_canvas(pointer_pos, canvas_rect) {
-                if self.points.len() < 1 {
-                    self.points.push(pos);
+            if self.points.is_empty() {
+                self.points.push(pos);
 
-                    #[cfg(debug_assertions)]
-                    println!("поставлена точка линии в {:#?}", pos);
-                    return;
-                }
-                let prev_pos = self.points.pop().unwrap();
-                let color = self.cur_color;
-                self.canvas_mut(&response.ctx)
-                    .draw_smooth_line_simple(prev_pos, pos, color);
+                #[cfg(debug_assertions)]
+                println!("поставлена точка линии в {:#?}", pos);
+                return;
+            }
+            self.commit_pending(&response.ctx, Some(pos));
 
+            #[cfg(debug_assertions)]
+            println!("нарисована линия цвета {:#?}", self.cur_color);
+        }
+    }
+
+    /// Обрабатывает рисование ломаной. Каждый щелчок продолжает ломаную,
+    /// двойной щелчок или Enter рисует её на холсте.
+    fn handle_polyline(&mut self, canvas_rect: egui::Rect, response: &egui::Response) {
+        if response.double_clicked() || self.enter_pressed {
+            // первый щелчок двойного щелчка уже поставил вершину
+            let count = self.points.len();
+            if self.commit_pending(&response.ctx, None) {
                 #[cfg(debug_assertions)]
-                println!("нарисована линия цвета {:#?}", self.cur_color);
+                println!("нарисована ломаная из {} вершин", count);
             }
+        } else if response.clicked()
+            && let Some(pointer_pos) = response.hover_pos()
+            && let Some(pos) = self.coord_screen_to_canvas(pointer_pos, canvas_rect)
+        {
+            self.points.push(pos);
+
+            #[cfg(debug_assertions)]
+            println!("поставлена вершина ломаной в {:#?}", pos);
         }
     }
 
@@ -207,62 +378,176 @@ impl ColorsApp {
     fn handle_triangle(&mut self, canvas_rect: egui::Rect, response: &egui::Response) {
         if response.clicked()
             && let Some(pointer_pos) = response.hover_pos()
+            && let Some(pos) = self.coord_screen_to_canvas(pointer_pos, canvas_rect)
         {
-            if let Some(pos) = self.coord_screen_to_canvas(pointer_pos, canvas_rect) {
-                if self.points.len() < 2 {
-                    self.points.push(pos);
-                    self.colors.push(self.cur_color);
+            if self.points.len() < 2 {
+                self.points.push(pos);
+                self.colors.push(self.cur_color);
 
-                    #[cfg(debug_assertions)]
-                    println!(
-                        "поставлена точка треугольника {:#?} в {:#?}",
-                        self.cur_color, pos
-                    );
-                    return;
-                }
-                let pos1 = self.points.pop().unwrap();
-                let color1 = self.colors.pop().unwrap();
+                #[cfg(debug_assertions)]
+                println!(
+                    "поставлена точка треугольника {:#?} в {:#?}",
+                    self.cur_color, pos
+                );
+                return;
+            }
+            self.commit_pending(&response.ctx, Some(pos));
+
+            #[cfg(debug_assertions)]
+            println!("нарисован треугольник");
+        }
+    }
+
+    /// Обрабатывает рисование треугольника с наложенной текстурой
+    fn handle_textured_triangle(&mut self, canvas_rect: egui::Rect, response: &egui::Response) {
+        // без загруженной картинки треугольник не нарисовать, точки не ставятся
+        if self.loaded_texture.is_none() {
+            self.cancel_pending();
+            return;
+        }
 
-                let pos2 = self.points.pop().unwrap();
-                let color2 = self.colors.pop().unwrap();
+        if response.clicked()
+            && let Some(pointer_pos) = response.hover_pos()
+            && let Some(pos) = self.coord_screen_to_canvas(pointer_pos, canvas_rect)
+        {
+            if self.points.len() < 2 {
+                self.points.push(pos);
 
-                let color = self.cur_color;
-                self.canvas_mut(&response.ctx)
-                    .draw_gradient_triangle(pos1, pos2, pos, color1, color2, color);
+                #[cfg(debug_assertions)]
+                println!(
+                    "поставлена точка текстурированного треугольника в {:#?}",
+                    pos
+                );
+                return;
+            }
 
+            if self.commit_pending(&response.ctx, Some(pos)) {
                 #[cfg(debug_assertions)]
-                println!("нарисован треугольник");
+                println!("нарисован текстурированный треугольник");
             }
         }
     }
+
+    /// Обрабатывает заливку многоугольника. Каждый щелчок добавляет вершину,
+    /// двойной щелчок замыкает контур и заливает многоугольник, а двойной щелчок
+    /// с Shift замыкает контур и начинает следующий.
+    fn handle_polygon(&mut self, canvas_rect: egui::Rect, response: &egui::Response) {
+        if response.double_clicked() {
+            // первый щелчок двойного щелчка уже поставил вершину
+            if self.points.len() >= 3 {
+                self.contours.push(std::mem::take(&mut self.points));
+                self.contour_colors.push(std::mem::take(&mut self.colors));
+
+                #[cfg(debug_assertions)]
+                println!("замкнут контур многоугольника");
+            }
+
+            if self.modifiers.shift {
+                return;
+            }
+
+            let count = self.contours.len();
+            if self.commit_pending(&response.ctx, None) {
+                #[cfg(debug_assertions)]
+                println!("залит многоугольник из {} контуров", count);
+            }
+        } else if response.clicked()
+            && let Some(pointer_pos) = response.hover_pos()
+            && let Some(pos) = self.coord_screen_to_canvas(pointer_pos, canvas_rect)
+        {
+            self.points.push(pos);
+            self.colors.push(self.cur_color);
+
+            #[cfg(debug_assertions)]
+            println!(
+                "поставлена вершина многоугольника {:#?} в {:#?}",
+                self.cur_color, pos
+            );
+        }
+    }
+
+    /// Обрабатывает рисование фигур перетаскиванием (окружности, эллипсы, прямоугольники):
+    /// начало перетаскивания ставит первую точку, отпускание кнопки рисует фигуру.
+    fn handle_drag_shape(&mut self, canvas_rect: egui::Rect, response: &egui::Response) {
+        let Some(pos) = response
+            .interact_pointer_pos()
+            .and_then(|pointer_pos| self.coord_screen_to_canvas(pointer_pos, canvas_rect))
+        else {
+            return;
+        };
+
+        if response.drag_started() {
+            self.points.clear();
+            self.points.push(pos);
+
+            #[cfg(debug_assertions)]
+            println!(
+                "начато рисование {} в {:#?}",
+                self.cur_instrument.get_name(),
+                pos
+            );
+        } else if response.drag_stopped() && self.commit_pending(&response.ctx, Some(pos)) {
+            #[cfg(debug_assertions)]
+            println!("нарисован {} до {:#?}", self.cur_instrument.get_name(), pos);
+        }
+    }
+
+    /// Обрабатывает рисование кривой. Щелчки ставят контрольные точки, которые
+    /// можно перетаскивать, Enter рисует кривую на холсте.
+    fn handle_curve(&mut self, canvas_rect: egui::Rect, response: &egui::Response) {
+        let pointer_pos = response.interact_pointer_pos();
+
+        if response.drag_started()
+            && let Some(pointer_pos) = pointer_pos
+        {
+            // захватить контрольную точку под курсором
+            self.dragged_point = self.points.iter().position(|&p| {
+                self.coord_canvas_to_screen(p, canvas_rect)
+                    .distance(pointer_pos)
+                    <= ColorsApp::HANDLE_RADIUS * 2.0
+            });
+        } else if response.drag_stopped() {
+            self.dragged_point = None;
+        } else if response.clicked()
+            && let Some(pointer_pos) = pointer_pos
+            && let Some(pos) = self.coord_screen_to_canvas(pointer_pos, canvas_rect)
+            && self
+                .curve_kind
+                .control_points()
+                .is_none_or(|count| self.points.len() < count)
+        {
+            self.points.push(pos);
+
+            #[cfg(debug_assertions)]
+            println!("поставлена контрольная точка кривой в {:#?}", pos);
+        }
+
+        if response.dragged()
+            && let Some(index) = self.dragged_point
+            && let Some(pointer_pos) = pointer_pos
+            && let Some(pos) = self.coord_screen_to_canvas(pointer_pos, canvas_rect)
+        {
+            self.points[index] = pos;
+        }
+
+        if self.enter_pressed && self.commit_pending(&response.ctx, None) {
+            #[cfg(debug_assertions)]
+            println!("нарисована кривая: {}", self.curve_kind.get_name());
+        }
+    }
 }
 
 // =============== Обработка UI ===============
 
 impl ColorsApp {
-    /// Выделяет место под текущий холст и выводит его на весь текущий размер экрана.
+    /// Выделяет под холст всю свободную область экрана.
+    /// Положение и масштаб холста внутри неё задаёт viewport.
     fn allocate_canvas(&self, ui: &mut egui::Ui) -> (egui::Response, egui::Painter) {
         let available_size = ui.available_size();
-        let canvas_size = self.canvas.size();
-
-        // Это было только для целочисленного увеличения
-        // let width_ratio = (available_size.x / canvas_size[0] as f32).floor().max(1.0);
-        // let height_ratio = (available_size.y / canvas_size[1] as f32).floor().max(1.0);
-        // let canvas_to_display_ratio = width_ratio.min(height_ratio);
 
-        // let display_width = canvas_size[0] as f32 * canvas_to_display_ratio;
-        // let display_height = canvas_size[1] as f32 * canvas_to_display_ratio;
-
-        let canvas_aspect_ratio = canvas_size[0] as f32 / canvas_size[1] as f32;
-
-        let display_width = available_size.x.min(available_size.y * canvas_aspect_ratio);
-        let display_height = display_width / canvas_aspect_ratio;
-
-        let (canvas_response, painter) = ui.allocate_painter(
-            egui::Vec2::new(display_width, display_height),
-            egui::Sense::click_and_drag(),
-        );
-        return (canvas_response, painter);
+        let (canvas_response, painter) =
+            ui.allocate_painter(available_size, egui::Sense::click_and_drag());
+        (canvas_response, painter)
     }
 
     /// Отображает PopUp с созданием холста нового размера.
@@ -292,14 +577,25 @@ impl ColorsApp {
                     ui.add(egui::DragValue::new(&mut self.new_canvas_height));
                 });
 
+                ui.checkbox(&mut self.new_canvas_transparent, "Transparent");
+
                 ui.horizontal(|ui| {
                     if ui.button("Cancel").clicked() {
                         self.show_new_canvas_popup = false;
                     }
 
                     if ui.button("Create").clicked() {
-                        self.canvas = Canvas::new(self.new_canvas_width, self.new_canvas_height);
-                        self.needs_redraw = true;
+                        let background = if self.new_canvas_transparent {
+                            Color32::TRANSPARENT
+                        } else {
+                            Color32::WHITE
+                        };
+                        let canvas = Canvas::new_filled(
+                            self.new_canvas_width,
+                            self.new_canvas_height,
+                            background,
+                        );
+                        self.replace_canvas(ctx, canvas);
 
                         self.show_new_canvas_popup = false;
                     }
@@ -307,17 +603,236 @@ impl ColorsApp {
             });
         });
     }
+
+    /// Отображает настройки наложения текстуры.
+    fn show_texture_settings(&mut self, ui: &mut egui::Ui) {
+        if self.loaded_texture.is_none() {
+            ui.label("нет картинки (File -> Load Bucket Image)");
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("выборка:");
+            egui::ComboBox::from_id_salt("sampling_combo_box")
+                .selected_text(self.texture_sampler.filter.get_name())
+                .show_ui(ui, |ui| {
+                    for filter in [
+                        canvas::Sampling::Nearest,
+                        canvas::Sampling::Bilinear,
+                        canvas::Sampling::Mipmap,
+                    ] {
+                        ui.selectable_value(
+                            &mut self.texture_sampler.filter,
+                            filter,
+                            filter.get_name(),
+                        );
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("адресация:");
+            egui::ComboBox::from_id_salt("address_mode_combo_box")
+                .selected_text(self.texture_sampler.address.get_name())
+                .show_ui(ui, |ui| {
+                    for address in [
+                        canvas::AddressMode::Wrap,
+                        canvas::AddressMode::Clamp,
+                        canvas::AddressMode::Mirror,
+                    ] {
+                        ui.selectable_value(
+                            &mut self.texture_sampler.address,
+                            address,
+                            address.get_name(),
+                        );
+                    }
+                });
+        });
+
+        ui.label("UV вершин:");
+        for (i, uv) in self.texture_uvs.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(format!("{}:", i + 1));
+                ui.add(egui::DragValue::new(&mut uv.x).speed(0.01).prefix("u "));
+                ui.add(egui::DragValue::new(&mut uv.y).speed(0.01).prefix("v "));
+            });
+        }
+    }
+
+    /// Отображает настройки заливки многоугольника.
+    fn show_polygon_settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("правило:");
+            egui::ComboBox::from_id_salt("fill_rule_combo_box")
+                .selected_text(self.fill_rule.get_name())
+                .show_ui(ui, |ui| {
+                    for rule in [canvas::FillRule::EvenOdd, canvas::FillRule::NonZero] {
+                        ui.selectable_value(&mut self.fill_rule, rule, rule.get_name());
+                    }
+                });
+        });
+
+        ui.checkbox(&mut self.gouraud_polygon, "цвета вершин (Гуро)");
+        ui.checkbox(&mut self.triangulate_polygon, "через триангуляцию")
+            .on_hover_text("первый контур внешний, остальные - дыры");
+        ui.label("двойной щелчок - залить,\nShift + двойной щелчок - новый контур");
+    }
+
+    /// Отображает настройки рисования окружностей и эллипсов.
+    fn show_conic_settings(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.conic_filled, "заливка");
+        ui.checkbox(&mut self.conic_smooth, "сглаживание (Ву)");
+        if self.cur_instrument == Instrument::Ellipse {
+            ui.horizontal(|ui| {
+                ui.label("поворот:");
+                ui.add(egui::Slider::new(&mut self.ellipse_angle, -180.0..=180.0).suffix("°"));
+            });
+        }
+    }
+
+    /// Отображает настройки рисования кривых.
+    fn show_curve_settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("вид:");
+            egui::ComboBox::from_id_salt("curve_kind_combo_box")
+                .selected_text(self.curve_kind.get_name())
+                .show_ui(ui, |ui| {
+                    for kind in [
+                        canvas::CurveKind::Quadratic,
+                        canvas::CurveKind::Cubic,
+                        canvas::CurveKind::CatmullRom,
+                    ] {
+                        ui.selectable_value(&mut self.curve_kind, kind, kind.get_name());
+                    }
+                });
+        });
+        // у кривых Безье лишние контрольные точки отбрасываются
+        if let Some(count) = self.curve_kind.control_points() {
+            self.points.truncate(count);
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("разбиение:");
+            egui::ComboBox::from_id_salt("curve_method_combo_box")
+                .selected_text(self.curve_method.get_name())
+                .show_ui(ui, |ui| {
+                    for method in [
+                        canvas::CurveMethod::Subdivision,
+                        canvas::CurveMethod::ForwardDifferencing,
+                    ] {
+                        ui.selectable_value(&mut self.curve_method, method, method.get_name());
+                    }
+                });
+        });
+
+        ui.checkbox(&mut self.curve_smooth, "сглаживание (Ву)");
+        ui.label("Enter - нарисовать, Escape - отменить");
+    }
+
+    /// Отображает настройки рисования прямоугольников.
+    fn show_rect_settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("скругление:");
+            ui.add(egui::DragValue::new(&mut self.rect_corner_radius).range(0.0..=f32::MAX));
+        });
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.rect_stroke, "контур");
+            ui.add(
+                egui::DragValue::new(&mut self.rect_stroke_width)
+                    .range(1.0..=f32::MAX)
+                    .suffix(" px"),
+            );
+        });
+
+        ui.checkbox(&mut self.rect_filled, "заливка");
+        if !self.rect_filled {
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            let is_gradient = matches!(self.rect_fill, canvas::Fill::Gradient { .. });
+            if ui.radio(!is_gradient, "цвет").clicked() && is_gradient {
+                self.rect_fill = canvas::Fill::Solid(egui::Color32::WHITE);
+            }
+            if ui.radio(is_gradient, "градиент").clicked() && !is_gradient {
+                self.rect_fill = canvas::Fill::Gradient {
+                    start: self.cur_color,
+                    end: egui::Color32::WHITE,
+                    direction: canvas::GradientDirection::Horizontal,
+                };
+            }
+        });
+
+        match &mut self.rect_fill {
+            canvas::Fill::Solid(color) => {
+                ui.horizontal(|ui| {
+                    ui.label("цвет заливки:");
+                    ui.color_edit_button_srgba(color);
+                });
+            }
+            canvas::Fill::Gradient {
+                start,
+                end,
+                direction,
+            } => {
+                ui.horizontal(|ui| {
+                    ui.label("от");
+                    ui.color_edit_button_srgba(start);
+                    ui.label("до");
+                    ui.color_edit_button_srgba(end);
+                });
+                egui::ComboBox::from_id_salt("gradient_direction_combo_box")
+                    .selected_text(direction.get_name())
+                    .show_ui(ui, |ui| {
+                        for dir in [
+                            canvas::GradientDirection::Horizontal,
+                            canvas::GradientDirection::Vertical,
+                        ] {
+                            ui.selectable_value(direction, dir, dir.get_name());
+                        }
+                    });
+            }
+        }
+    }
 }
 
 // =============== Главный цикл UI ===============
 
 impl eframe::App for ColorsApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, &self.settings());
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let image_changed = self.image_changed();
         self.update_texture(ctx);
 
+        self.modifiers = ctx.input(|i| i.modifiers);
+        // фокус поля проверяется до панелей: по Enter поле теряет фокус в этом же кадре
+        self.enter_pressed =
+            !ctx.wants_keyboard_input() && ctx.input(|i| i.key_pressed(egui::Key::Enter));
+        if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.cancel_pending();
+        }
+        self.handle_shortcuts(ctx);
+        self.update_hovered_pixel(ctx);
+
+        // действие (мазок карандашом, щелчок) заканчивается отпусканием кнопок мыши
+        if !ctx.input(|i| i.pointer.any_down()) {
+            self.history.end_action(&self.layers);
+        }
+
         if self.show_new_canvas_popup {
             self.show_popup(ctx);
         }
+        if self.show_shortcuts {
+            self.show_shortcuts_window(ctx);
+        }
+        self.show_resize_window(ctx);
+        self.show_transform_window(ctx);
+        self.show_filter_window(ctx);
+        self.show_adjustment_window(ctx, image_changed);
+        self.show_quantize_window(ctx);
 
         // --------------- Верхняя панель ---------------
         egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
@@ -337,14 +852,165 @@ impl eframe::App for ColorsApp {
                     }
 
                     // Загрузить картинку для холста
-                    if ui.button("Load Canvas").clicked() {
+                    let shortcut = self.shortcut_text(ctx, keymap::Action::Load);
+                    if ui
+                        .add(egui::Button::new("Load Canvas").shortcut_text(shortcut))
+                        .clicked()
+                    {
                         self.load_canvas(ctx);
                     }
 
+                    // Сохранить холст в файл с картинкой
+                    let shortcut = self.shortcut_text(ctx, keymap::Action::Save);
+                    if ui
+                        .add(egui::Button::new("Save Canvas").shortcut_text(shortcut))
+                        .clicked()
+                    {
+                        self.save_canvas();
+                    }
+
+                    // Проект: слои, незавершённые построения и история
+                    if ui.button("Open Project").clicked() {
+                        self.open_project(ctx);
+                        ui.close();
+                    }
+                    if ui.button("Save Project").clicked() {
+                        self.save_project();
+                        ui.close();
+                    }
+
+                    ui.checkbox(&mut self.reopen_last, "Reopen Last Files")
+                        .on_hover_text(
+                            "открывать при запуске последний холст и картинку для заливки",
+                        );
+
                     if ui.button("Quit").clicked() {
                         ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                     }
                 });
+
+                ui.menu_button("Edit", |ui| {
+                    let shortcut = self.shortcut_text(ctx, keymap::Action::Undo);
+                    if ui
+                        .add_enabled(
+                            self.history.can_undo(),
+                            egui::Button::new("Undo").shortcut_text(shortcut),
+                        )
+                        .clicked()
+                    {
+                        self.undo(ctx);
+                    }
+
+                    let shortcut = self.shortcut_text(ctx, keymap::Action::Redo);
+                    if ui
+                        .add_enabled(
+                            self.history.can_redo(),
+                            egui::Button::new("Redo").shortcut_text(shortcut),
+                        )
+                        .clicked()
+                    {
+                        self.redo(ctx);
+                    }
+
+                    ui.separator();
+                    if ui.button("Select All").clicked() {
+                        self.select_all();
+                        ui.close();
+                    }
+                    if ui
+                        .add_enabled(self.selection().is_some(), egui::Button::new("Deselect"))
+                        .clicked()
+                    {
+                        self.deselect();
+                        ui.close();
+                    }
+                });
+
+                ui.menu_button("Image", |ui| {
+                    if ui.button("Resize...").clicked() {
+                        self.open_resize_dialog(resize::ResizeKind::Image);
+                        ui.close();
+                    }
+                    if ui.button("Canvas Size...").clicked() {
+                        self.open_resize_dialog(resize::ResizeKind::Canvas);
+                        ui.close();
+                    }
+                    if ui
+                        .add_enabled(
+                            self.selection().is_some(),
+                            egui::Button::new("Crop to Selection"),
+                        )
+                        .clicked()
+                    {
+                        self.crop_to_selection(ctx);
+                        ui.close();
+                    }
+
+                    // без выделения преобразуются все слои, иначе - выделение активного слоя
+                    ui.separator();
+                    for transform in transform::Transform::ALL {
+                        if ui.button(transform.get_label()).clicked() {
+                            self.apply_transform(ctx, transform);
+                            ui.close();
+                        }
+                    }
+                    if ui.button("Transform...").clicked() {
+                        self.transform_dialog = Some(transform::TransformDialog::default());
+                        ui.close();
+                    }
+                });
+
+                // коррекция применяется к активному слою (или к выделению на нём)
+                ui.menu_button("Adjustments", |ui| {
+                    for adjustment in adjustments::Adjustment::ALL {
+                        if adjustment == adjustments::Adjustment::Equalize {
+                            ui.separator();
+                        }
+                        if !adjustment.has_options() {
+                            if ui.button(adjustment.get_label()).clicked() {
+                                self.apply_adjustment(ctx, adjustment);
+                                ui.close();
+                            }
+                        } else if ui
+                            .button(format!("{}...", adjustment.get_label()))
+                            .clicked()
+                        {
+                            self.adjustment_dialog =
+                                Some(adjustments::AdjustmentDialog::new(adjustment));
+                            ui.close();
+                        }
+                    }
+
+                    ui.separator();
+                    if ui.button("Quantize...").clicked() {
+                        self.quantize_dialog = Some(quantize::QuantizeDialog::default());
+                        ui.close();
+                    }
+                });
+
+                // фильтры применяются к активному слою (или к выделению на нём)
+                ui.menu_button("Filters", |ui| {
+                    for filter in filters::Filter::ALL {
+                        if filter == filters::Filter::EdgeDetect {
+                            ui.separator();
+                        }
+                        if ui.button(format!("{}...", filter.get_label())).clicked() {
+                            self.filter_dialog = Some(filters::FilterDialog::new(filter));
+                            ui.close();
+                        }
+                    }
+                });
+
+                ui.menu_button("Help", |ui| {
+                    let shortcut = self.shortcut_text(ctx, keymap::Action::Shortcuts);
+                    if ui
+                        .add(egui::Button::new("Shortcuts").shortcut_text(shortcut))
+                        .clicked()
+                    {
+                        self.show_shortcuts = true;
+                        ui.close();
+                    }
+                });
             });
         });
 
@@ -356,9 +1022,11 @@ impl eframe::App for ColorsApp {
                     egui::color_picker::color_picker_color32(
                         ui,
                         &mut self.cur_color,
-                        egui::color_picker::Alpha::Opaque,
+                        egui::color_picker::Alpha::OnlyBlend,
                     );
 
+                    self.show_palette_panel(ui);
+
                     ui.separator();
 
                     ui.horizontal(|ui| {
@@ -404,9 +1072,77 @@ impl eframe::App for ColorsApp {
                     if ui.button("Triangle").clicked() {
                         self.switch_instrument(Instrument::Triangle);
                     }
+                    if ui.button("Textured Triangle").clicked() {
+                        self.switch_instrument(Instrument::TexturedTriangle);
+                    }
+
+                    if ui.button("Polygon").clicked() {
+                        self.switch_instrument(Instrument::Polygon);
+                    }
+                    if ui.button("Circle").clicked() {
+                        self.switch_instrument(Instrument::Circle);
+                    }
+                    if ui.button("Ellipse").clicked() {
+                        self.switch_instrument(Instrument::Ellipse);
+                    }
+                    if ui.button("Curve").clicked() {
+                        self.switch_instrument(Instrument::Curve);
+                    }
+                    if ui.button("Rectangle").clicked() {
+                        self.switch_instrument(Instrument::Rectangle);
+                    }
+                    if ui.button("Eyedropper").clicked() {
+                        self.switch_instrument(Instrument::Eyedropper);
+                    }
+                    if ui.button("Select").clicked() {
+                        self.switch_instrument(Instrument::Select);
+                    }
+
+                    if self.cur_instrument == Instrument::TexturedTriangle {
+                        ui.separator();
+                        self.show_texture_settings(ui);
+                    }
+                    if self.cur_instrument == Instrument::Polygon {
+                        ui.separator();
+                        self.show_polygon_settings(ui);
+                    }
+                    if matches!(
+                        self.cur_instrument,
+                        Instrument::Circle | Instrument::Ellipse
+                    ) {
+                        ui.separator();
+                        self.show_conic_settings(ui);
+                    }
+                    if self.cur_instrument == Instrument::Curve {
+                        ui.separator();
+                        self.show_curve_settings(ui);
+                    }
+                    if matches!(
+                        self.cur_instrument,
+                        Instrument::SharpLine | Instrument::SmoothLine
+                    ) {
+                        ui.separator();
+                        ui.checkbox(&mut self.polyline_mode, "ломаная")
+                            .on_hover_text(
+                                "двойной щелчок или Enter - нарисовать,\nEscape - отменить",
+                            );
+                    }
+                    if self.cur_instrument == Instrument::Rectangle {
+                        ui.separator();
+                        self.show_rect_settings(ui);
+                    }
                 });
             });
 
+        // --------------- Правая панель ---------------
+        egui::SidePanel::right("layers_panel")
+            .resizable(false)
+            .show(ctx, |ui| {
+                self.show_layers_panel(ui);
+                ui.separator();
+                self.show_histogram_panel(ui, image_changed);
+            });
+
         // --------------- Нижняя панель ---------------
         egui::TopBottomPanel::bottom("bottom_panel").show(ctx, |ui| {
             ui.horizontal(|ui| {
@@ -416,8 +1152,8 @@ impl eframe::App for ColorsApp {
 
                 ui.label(format!(
                     "размер холста: {} x {}",
-                    self.canvas.size()[0],
-                    self.canvas.size()[1]
+                    self.layers.size()[0],
+                    self.layers.size()[1]
                 ));
 
                 ui.separator();
@@ -426,6 +1162,14 @@ impl eframe::App for ColorsApp {
                     "отображаемый размер: {:.1} x {:.1}",
                     self.display_canvas_width, self.display_canvas_height
                 ));
+
+                ui.separator();
+
+                self.show_zoom_controls(ui);
+
+                ui.separator();
+
+                self.show_pixel_readout(ui);
             });
         });
 
@@ -433,13 +1177,21 @@ impl eframe::App for ColorsApp {
         egui::CentralPanel::default().show(ctx, |ui| {
             // Выделить область под холст
             let (canvas_response, painter) = self.allocate_canvas(ui);
-            let canvas_rect = canvas_response.rect;
+            let panning = self.handle_viewport(&canvas_response);
+            let canvas_rect = self.viewport.canvas_rect();
 
             self.display_canvas_width = canvas_rect.width();
             self.display_canvas_height = canvas_rect.height();
 
-            // Обработать рисование
+            // Обработать рисование (кроме перемещения холста и рисования на закреплённом слое)
+            let locked = self.layers.active().locked
+                && !matches!(
+                    self.cur_instrument,
+                    Instrument::Eyedropper | Instrument::Select
+                );
+            let was_modified = self.image_changed();
             match self.cur_instrument {
+                _ if panning || locked => {}
                 Instrument::Pencil => self.handle_pencil(canvas_rect, &canvas_response),
                 Instrument::Bucket => self.handle_bucket(canvas_rect, &canvas_response),
                 Instrument::ImageBucket => self.handle_image_bucket(canvas_rect, &canvas_response),
@@ -447,10 +1199,30 @@ impl eframe::App for ColorsApp {
                 Instrument::SharpLine => self.handle_sharp_line(canvas_rect, &canvas_response),
                 Instrument::SmoothLine => self.handle_smooth_line(canvas_rect, &canvas_response),
                 Instrument::Triangle => self.handle_triangle(canvas_rect, &canvas_response),
+                Instrument::TexturedTriangle => {
+                    self.handle_textured_triangle(canvas_rect, &canvas_response)
+                }
+                Instrument::Polygon => self.handle_polygon(canvas_rect, &canvas_response),
+                Instrument::Circle | Instrument::Ellipse | Instrument::Rectangle => {
+                    self.handle_drag_shape(canvas_rect, &canvas_response)
+                }
+                Instrument::Curve => self.handle_curve(canvas_rect, &canvas_response),
+                Instrument::Eyedropper => self.handle_eyedropper(canvas_rect, &canvas_response),
+                Instrument::Select => self.handle_select(canvas_rect, &canvas_response),
             };
 
-            // Вывести текущий холст на экран
-            if let Some(texture) = &self.texture_handle {
+            // Цвет, которым только что рисовали, попадает в недавние
+            if !was_modified
+                && self.image_changed()
+                && self.cur_instrument != Instrument::ImageBucket
+            {
+                self.remember_color(self.cur_color);
+            }
+
+            // Вывести текущий холст (или предпросмотр коррекции) поверх шахматки
+            // (видна в прозрачных местах)
+            self.draw_checkerboard(ctx, &painter, canvas_rect);
+            if let Some(texture) = self.adjustment_preview().or(self.texture_handle.as_ref()) {
                 painter.image(
                     texture.id(),
                     canvas_rect,
@@ -458,6 +1230,15 @@ impl eframe::App for ColorsApp {
                     egui::Color32::WHITE,
                 );
             }
+
+            // Вывести сетку пикселей
+            self.draw_pixel_grid(&painter, canvas_rect);
+
+            // Вывести поверх холста незавершённые построения
+            let hover_pos = canvas_response.hover_pos();
+            self.update_ghost(ctx, canvas_rect, hover_pos);
+            self.draw_overlay(&painter, canvas_rect, hover_pos);
+            self.draw_selection(&painter, canvas_rect);
         });
     }
 }
@@ -466,24 +1247,50 @@ impl eframe::App for ColorsApp {
 
 impl ColorsApp {
     /// Обновить текущую GPU текстуру для отображения.
+    /// После изменений стопки слоёв (needs_redraw) текстура создаётся заново,
+    /// иначе в неё выгружается только изменённая область слоёв.
     fn update_texture(&mut self, ctx: &egui::Context) {
-        if !self.needs_redraw {
+        let dirty = self.layers.take_dirty();
+        let size_changed = self.composite.size() != self.layers.size();
+        if let Some(texture) = &mut self.texture_handle
+            && !self.needs_redraw
+            && !size_changed
+        {
+            // выгрузить в текстуру только изменённую часть
+            if let Some(rect) = dirty {
+                self.layers.composite_rect(&mut self.composite, rect);
+                self.composite.take_dirty();
+                texture.set_partial(
+                    [rect.x, rect.y],
+                    self.composite.region_image(rect),
+                    egui::TextureOptions::NEAREST,
+                );
+            }
             return;
         }
 
+        self.composite = self.layers.composite();
+        self.composite.take_dirty();
         self.texture_handle = Some(ctx.load_texture(
             "canvas",
-            self.canvas.to_color_image(),
+            self.composite.to_color_image(),
             egui::TextureOptions::NEAREST, // Linear слишком размытый для отображения мелких пикселей
         ));
         self.needs_redraw = false;
     }
 
-    /// Получить изменяемый холст и пометить его на перерисовку.
+    /// Изменено ли изображение с последнего обновления текстуры.
+    fn image_changed(&self) -> bool {
+        self.needs_redraw || self.layers.active().canvas.dirty_rect().is_some()
+    }
+
+    /// Получить изменяемый холст активного слоя. Изменённые пиксели холст отмечает сам,
+    /// и в текстуру выгружается только эта область.
+    /// Состояние до изменения сохраняется в истории (один раз за действие).
     fn canvas_mut(&mut self, ctx: &egui::Context) -> &mut Canvas {
-        self.needs_redraw = true;
+        self.history.record(&self.layers);
         ctx.request_repaint(); // Холст изменён, надо заново его нарисовать
-        &mut self.canvas
+        &mut self.layers.active_mut().canvas
     }
 
     /// Преобразует координаты экрана в координаты холста
@@ -496,7 +1303,7 @@ impl ColorsApp {
             let relative_x = (screen_pos.x - canvas_rect.left()) / canvas_rect.width();
             let relative_y = (screen_pos.y - canvas_rect.top()) / canvas_rect.height();
 
-            let canvas_size = self.canvas.size();
+            let canvas_size = self.layers.size();
             let pixel_x = relative_x * canvas_size[0] as f32;
             let pixel_y = relative_y * canvas_size[1] as f32;
             return Some(egui::Pos2 {
@@ -507,11 +1314,52 @@ impl ColorsApp {
         None
     }
 
+    /// Преобразует координаты холста в координаты экрана
+    fn coord_canvas_to_screen(
+        &self,
+        canvas_pos: egui::Pos2,
+        canvas_rect: egui::Rect,
+    ) -> egui::Pos2 {
+        let canvas_size = self.layers.size();
+        egui::Pos2 {
+            x: canvas_rect.left() + canvas_pos.x / canvas_size[0] as f32 * canvas_rect.width(),
+            y: canvas_rect.top() + canvas_pos.y / canvas_size[1] as f32 * canvas_rect.height(),
+        }
+    }
+
+    /// Заменить изображение целиком (новое или загруженное) одним слоем,
+    /// старые слои остаются в истории.
+    fn replace_canvas(&mut self, ctx: &egui::Context, canvas: Canvas) {
+        self.history.record(&self.layers);
+        self.layers = layers::LayerStack::new(canvas);
+        self.needs_redraw = true;
+        ctx.request_repaint();
+        self.cancel_pending();
+        self.viewport.fit();
+    }
+
+    /// Отменить последнее действие
+    fn undo(&mut self, ctx: &egui::Context) {
+        self.cancel_pending();
+        if self.history.undo(&mut self.layers) {
+            self.needs_redraw = true;
+            ctx.request_repaint();
+        }
+    }
+
+    /// Повторить отменённое действие
+    fn redo(&mut self, ctx: &egui::Context) {
+        self.cancel_pending();
+        if self.history.redo(&mut self.layers) {
+            self.needs_redraw = true;
+            ctx.request_repaint();
+        }
+    }
+
     /// Сменить инструмент (рисование)
     fn switch_instrument(&mut self, new_instrument: Instrument) {
         self.cur_instrument = new_instrument;
-        self.points.clear();
-        self.colors.clear();
+        self.cancel_pending();
     }
 
     /// Загрузить файл с картинкой из файловой системы для заливки
@@ -521,39 +1369,63 @@ impl ColorsApp {
             .pick_file();
 
         if let Some(path) = path {
-            if let Ok(img) = image::open(&path) {
-                let image_size = [img.width() as usize, img.height() as usize];
-                let image_buf = img.to_rgb8().into_raw();
-                self.loaded_image = Some(egui::ColorImage::from_rgb(image_size, &image_buf));
-            }
+            self.open_bucket_image(&path);
         }
     }
 
+    /// Открыть картинку для заливки по пути
+    fn open_bucket_image(&mut self, path: &Path) {
+        if let Ok(img) = image::open(path) {
+            self.set_bucket_image(&img);
+            self.bucket_image_path = Some(path.to_path_buf());
+        }
+    }
+
+    /// Установить картинку для заливки и текстурирования
+    fn set_bucket_image(&mut self, img: &image::DynamicImage) {
+        let image_size = [img.width() as usize, img.height() as usize];
+        let image_buf = img.to_rgba8().into_raw();
+        let image = egui::ColorImage::from_rgba_unmultiplied(image_size, &image_buf);
+        self.loaded_texture = Some(canvas::Texture::new(&image));
+        self.loaded_image = Some(image);
+        // предпросмотр треугольника с текстурой перестраивается с новой картинкой
+        self.ghost_state = None;
+    }
+
     /// Загрузить файл с картинкой из файловой системы для холста
     fn load_canvas(&mut self, ctx: &egui::Context) {
         let path = rfd::FileDialog::new()
             .add_filter("Images", &["png", "jpg", "jpeg", "bmp", "tga", "tiff"])
             .pick_file();
 
+        if let Some(path) = path
+            && let Some(canvas) = Self::read_canvas(&path)
+        {
+            self.replace_canvas(ctx, canvas);
+            self.canvas_path = Some(path);
+        }
+        self.update_texture(ctx);
+    }
+
+    /// Прочитать холст из файла с картинкой
+    fn read_canvas(path: &Path) -> Option<Canvas> {
+        let image = image::open(path).ok()?;
+        Some(Canvas::from(&image))
+    }
+
+    /// Сохранить холст в файл с картинкой
+    fn save_canvas(&mut self) {
+        let path = rfd::FileDialog::new()
+            .add_filter("PNG", &["png"])
+            .add_filter("Images", &["bmp", "tga", "tiff"])
+            .set_file_name("canvas.png")
+            .save_file();
+
         if let Some(path) = path {
-            if let Ok(img) = image::open(&path) {
-                let image_size = [img.width() as usize, img.height() as usize];
-                let image = img.into_rgba8();
-
-                self.canvas = Canvas::new(image_size[0], image_size[1]);
-                for i in 0..image_size[0] {
-                    for j in 0..image_size[1] {
-                        let channels = image.get_pixel(i as u32, j as u32).channels();
-                        self.canvas_mut(ctx)[(i, j)] = Color32::from_rgba_premultiplied(
-                            channels[0],
-                            channels[1],
-                            channels[2],
-                            channels[3],
-                        );
-                    }
-                }
+            match self.composite.to_rgba_image().save(&path) {
+                Ok(()) => self.canvas_path = Some(path),
+                Err(error) => eprintln!("не удалось сохранить холст {path:?}: {error}"),
             }
         }
-        self.update_texture(ctx);
     }
 }
@@ -0,0 +1,482 @@
+use egui::{Color32, Pos2, Stroke};
+
+use super::ColorsApp;
+use super::canvas::{
+    Canvas, ChannelMixer, ColorChannel, GrayscaleMethod, Levels, PixelRect, ToneCurve,
+};
+
+#[derive(PartialEq, Clone, Copy)]
+/// Цветовая коррекция из меню Adjustments.
+pub enum Adjustment {
+    BrightnessContrast,
+    Levels,
+    Curves,
+    HueSaturation,
+    Grayscale,
+    /// Негатив (без настроек)
+    Invert,
+    Posterize,
+    Threshold,
+    ChannelMixer,
+    /// Глобальное выравнивание гистограммы (без настроек)
+    Equalize,
+    /// Адаптивное выравнивание гистограммы с ограничением контраста
+    Clahe,
+}
+
+impl Adjustment {
+    pub const ALL: [Adjustment; 11] = [
+        Adjustment::BrightnessContrast,
+        Adjustment::Levels,
+        Adjustment::Curves,
+        Adjustment::HueSaturation,
+        Adjustment::Grayscale,
+        Adjustment::Invert,
+        Adjustment::Posterize,
+        Adjustment::Threshold,
+        Adjustment::ChannelMixer,
+        Adjustment::Equalize,
+        Adjustment::Clahe,
+    ];
+
+    /// Надпись пункта меню и заголовок окна.
+    pub fn get_label(&self) -> &'static str {
+        match self {
+            Adjustment::BrightnessContrast => "Brightness/Contrast",
+            Adjustment::Levels => "Levels",
+            Adjustment::Curves => "Curves",
+            Adjustment::HueSaturation => "Hue/Saturation",
+            Adjustment::Grayscale => "Grayscale",
+            Adjustment::Invert => "Invert",
+            Adjustment::Posterize => "Posterize",
+            Adjustment::Threshold => "Threshold",
+            Adjustment::ChannelMixer => "Channel Mixer",
+            Adjustment::Equalize => "Equalize",
+            Adjustment::Clahe => "CLAHE",
+        }
+    }
+
+    /// Есть ли у коррекции окно настроек (иначе она применяется сразу).
+    pub fn has_options(&self) -> bool {
+        !matches!(self, Adjustment::Invert | Adjustment::Equalize)
+    }
+}
+
+/// Параметры коррекции.
+#[derive(Clone, PartialEq)]
+struct AdjustmentSettings {
+    adjustment: Adjustment,
+    brightness: f32,
+    contrast: f32,
+    levels: Levels,
+    levels_channel: ColorChannel,
+    /// Кривые в порядке ColorChannel::ALL
+    curves: [ToneCurve; 4],
+    curve_channel: ColorChannel,
+    /// Сдвиг тона в градусах
+    hue: f32,
+    /// Изменение насыщенности в процентах
+    saturation: f32,
+    /// Изменение значения (яркости) в процентах
+    value: f32,
+    grayscale: GrayscaleMethod,
+    posterize_levels: u32,
+    threshold: u8,
+    mixer: ChannelMixer,
+    /// Число блоков CLAHE по каждой оси
+    clahe_tiles: usize,
+    /// Ограничение контраста CLAHE (в средних значениях столбца гистограммы)
+    clahe_clip_limit: f32,
+}
+
+impl AdjustmentSettings {
+    fn new(adjustment: Adjustment) -> Self {
+        Self {
+            adjustment,
+            brightness: 0.0,
+            contrast: 0.0,
+            levels: Levels::default(),
+            levels_channel: ColorChannel::default(),
+            curves: Default::default(),
+            curve_channel: ColorChannel::default(),
+            hue: 0.0,
+            saturation: 0.0,
+            value: 0.0,
+            grayscale: GrayscaleMethod::default(),
+            posterize_levels: 4,
+            threshold: 128,
+            mixer: ChannelMixer::default(),
+            clahe_tiles: 8,
+            clahe_clip_limit: 2.0,
+        }
+    }
+
+    /// Результат коррекции для холста canvas.
+    fn apply(&self, canvas: &Canvas) -> Canvas {
+        match self.adjustment {
+            Adjustment::BrightnessContrast => {
+                canvas.brightness_contrast(self.brightness, self.contrast)
+            }
+            Adjustment::Levels => canvas.levels(self.levels, self.levels_channel),
+            Adjustment::Curves => canvas.curves(&self.curves),
+            Adjustment::HueSaturation => {
+                canvas.hue_saturation_value(self.hue, self.saturation, self.value)
+            }
+            Adjustment::Grayscale => canvas.grayscale(self.grayscale),
+            Adjustment::Invert => canvas.invert(),
+            Adjustment::Posterize => canvas.posterize(self.posterize_levels),
+            Adjustment::Threshold => canvas.threshold(self.threshold),
+            Adjustment::ChannelMixer => canvas.mix_channels(&self.mixer),
+            Adjustment::Equalize => canvas.equalize(),
+            Adjustment::Clahe => canvas.clahe(self.clahe_tiles, self.clahe_clip_limit),
+        }
+    }
+}
+
+/// Состояние окна цветовой коррекции.
+pub struct AdjustmentDialog {
+    settings: AdjustmentSettings,
+    /// Показывать результат на холсте до применения
+    live_preview: bool,
+    /// Изображение с применённой коррекцией
+    preview: Option<egui::TextureHandle>,
+    /// Параметры и выделение, для которых построен предпросмотр
+    preview_state: Option<(AdjustmentSettings, Option<PixelRect>)>,
+    /// Перетаскиваемая точка кривой
+    dragged_point: Option<usize>,
+}
+
+impl AdjustmentDialog {
+    /// Сторона поля редактора кривых
+    const CURVE_EDITOR_SIZE: f32 = 256.0;
+    /// Расстояние захвата точки кривой в пикселях экрана
+    const CURVE_GRAB_RADIUS: f32 = 8.0;
+
+    pub fn new(adjustment: Adjustment) -> Self {
+        Self {
+            settings: AdjustmentSettings::new(adjustment),
+            live_preview: true,
+            preview: None,
+            preview_state: None,
+            dragged_point: None,
+        }
+    }
+
+    /// Выбор каналов коррекции.
+    fn channel_combo_box(ui: &mut egui::Ui, id: &str, channel: &mut ColorChannel) {
+        ui.horizontal(|ui| {
+            ui.label("канал:");
+            egui::ComboBox::from_id_salt(id)
+                .selected_text(channel.get_name())
+                .show_ui(ui, |ui| {
+                    for option in ColorChannel::ALL {
+                        ui.selectable_value(channel, option, option.get_name());
+                    }
+                });
+        });
+    }
+
+    /// Настройки уровней.
+    fn show_levels(&mut self, ui: &mut egui::Ui) {
+        let settings = &mut self.settings;
+        Self::channel_combo_box(ui, "levels_channel_combo_box", &mut settings.levels_channel);
+        let levels = &mut settings.levels;
+        ui.horizontal(|ui| {
+            ui.label("вход:");
+            let [black, white] = &mut levels.input;
+            ui.add(egui::DragValue::new(black).range(0.0..=254.0));
+            ui.add(egui::DragValue::new(white).range(1.0..=255.0));
+            *black = black.min(*white - 1.0);
+        });
+        ui.horizontal(|ui| {
+            ui.label("гамма:");
+            ui.add(egui::Slider::new(&mut levels.gamma, 0.1..=10.0).logarithmic(true));
+        });
+        ui.horizontal(|ui| {
+            ui.label("выход:");
+            for value in &mut levels.output {
+                ui.add(egui::DragValue::new(value).range(0.0..=255.0));
+            }
+        });
+    }
+
+    /// Редактор кривых: щелчок добавляет точку, перетаскивание двигает её,
+    /// щелчок правой кнопкой удаляет.
+    fn show_curves(&mut self, ui: &mut egui::Ui) {
+        let settings = &mut self.settings;
+        Self::channel_combo_box(ui, "curves_channel_combo_box", &mut settings.curve_channel);
+        let index = ColorChannel::ALL
+            .iter()
+            .position(|&channel| channel == settings.curve_channel)
+            .unwrap();
+        let curve = &mut settings.curves[index];
+
+        let size = egui::Vec2::splat(Self::CURVE_EDITOR_SIZE);
+        let (response, painter) = ui.allocate_painter(size, egui::Sense::click_and_drag());
+        let rect = response.rect;
+        let to_screen = |[x, y]: [f32; 2]| {
+            Pos2::new(
+                rect.left() + x / 255.0 * rect.width(),
+                rect.bottom() - y / 255.0 * rect.height(),
+            )
+        };
+        let to_curve = |pos: Pos2| {
+            [
+                (pos.x - rect.left()) / rect.width() * 255.0,
+                (rect.bottom() - pos.y) / rect.height() * 255.0,
+            ]
+        };
+        let nearest = |curve: &ToneCurve, pos: Pos2| {
+            curve
+                .points()
+                .iter()
+                .position(|&p| to_screen(p).distance(pos) <= Self::CURVE_GRAB_RADIUS)
+        };
+
+        if let Some(pos) = response.interact_pointer_pos() {
+            if response.drag_started() {
+                self.dragged_point =
+                    Some(nearest(curve, pos).unwrap_or_else(|| curve.insert(to_curve(pos))));
+            } else if response.clicked() && nearest(curve, pos).is_none() {
+                curve.insert(to_curve(pos));
+            } else if response.secondary_clicked()
+                && let Some(index) = nearest(curve, pos)
+            {
+                curve.remove(index);
+            }
+            if response.dragged()
+                && let Some(index) = self.dragged_point
+            {
+                curve.move_point(index, to_curve(pos));
+            }
+        }
+        if response.drag_stopped() {
+            self.dragged_point = None;
+        }
+
+        // сетка по четвертям, диагональ и сама кривая
+        let visuals = ui.visuals();
+        painter.rect_filled(rect, 0.0, visuals.extreme_bg_color);
+        let grid = Stroke::new(1.0, visuals.weak_text_color());
+        for i in 1..4 {
+            let t = i as f32 * 255.0 / 4.0;
+            painter.line_segment([to_screen([t, 0.0]), to_screen([t, 255.0])], grid);
+            painter.line_segment([to_screen([0.0, t]), to_screen([255.0, t])], grid);
+        }
+        painter.line_segment([to_screen([0.0, 0.0]), to_screen([255.0, 255.0])], grid);
+
+        let color = match settings.curve_channel {
+            ColorChannel::Rgb => visuals.strong_text_color(),
+            ColorChannel::Red => Color32::RED,
+            ColorChannel::Green => Color32::GREEN,
+            ColorChannel::Blue => Color32::LIGHT_BLUE,
+        };
+        let lut = curve.lut();
+        let line = (0..256)
+            .map(|x| to_screen([x as f32, lut[x] as f32]))
+            .collect();
+        painter.add(egui::Shape::line(line, Stroke::new(2.0, color)));
+        for &point in curve.points() {
+            painter.circle(
+                to_screen(point),
+                ColorsApp::HANDLE_RADIUS,
+                color,
+                Stroke::new(1.0, Color32::BLACK),
+            );
+        }
+
+        if ui.button("Reset Channel").clicked() {
+            *curve = ToneCurve::default();
+        }
+    }
+
+    /// Настройки микшера каналов (веса в процентах).
+    fn show_channel_mixer(&mut self, ui: &mut egui::Ui) {
+        let mixer = &mut self.settings.mixer;
+        let rows = if mixer.monochrome { 1 } else { 3 };
+        egui::Grid::new("channel_mixer_grid").show(ui, |ui| {
+            ui.label("");
+            for name in ["R", "G", "B", "добавка"] {
+                ui.label(name);
+            }
+            ui.end_row();
+
+            let names = ["красный", "зелёный", "синий"];
+            for (c, name) in names.iter().enumerate().take(rows) {
+                ui.label(if mixer.monochrome { "серый" } else { name });
+                for weight in &mut mixer.matrix[c] {
+                    ui.add(
+                        egui::DragValue::from_get_set(|value| {
+                            if let Some(value) = value {
+                                *weight = value as f32 / 100.0;
+                            }
+                            (*weight * 100.0) as f64
+                        })
+                        .range(-200.0..=200.0)
+                        .suffix("%"),
+                    );
+                }
+                ui.add(egui::DragValue::new(&mut mixer.offset[c]).range(-255.0..=255.0));
+                ui.end_row();
+            }
+        });
+        ui.checkbox(&mut mixer.monochrome, "монохромный");
+    }
+}
+
+// =============== Цветовая коррекция ===============
+
+impl ColorsApp {
+    /// Применить коррекцию без настроек (Adjustments -> Invert, Equalize).
+    pub(super) fn apply_adjustment(&mut self, ctx: &egui::Context, adjustment: Adjustment) {
+        let settings = AdjustmentSettings::new(adjustment);
+        self.map_active_layer(ctx, |canvas| settings.apply(canvas));
+
+        #[cfg(debug_assertions)]
+        println!("применена коррекция {}", adjustment.get_label());
+    }
+
+    /// Предпросмотр открытой коррекции, если он включён.
+    pub(super) fn adjustment_preview(&self) -> Option<&egui::TextureHandle> {
+        self.adjustment_dialog
+            .as_ref()
+            .and_then(|dialog| dialog.preview.as_ref())
+    }
+
+    /// Перестроить предпросмотр при изменении параметров, выделения или изображения.
+    fn update_adjustment_preview(
+        &self,
+        ctx: &egui::Context,
+        dialog: &mut AdjustmentDialog,
+        image_changed: bool,
+    ) {
+        if !dialog.live_preview || self.layers.active().locked {
+            dialog.preview = None;
+            dialog.preview_state = None;
+            return;
+        }
+        let state = (dialog.settings.clone(), self.selection());
+        if !image_changed && dialog.preview_state.as_ref() == Some(&state) {
+            return;
+        }
+
+        let layer = self.mapped_active_layer(|canvas| dialog.settings.apply(canvas));
+        dialog.preview = Some(ctx.load_texture(
+            "adjustment_preview",
+            self.layers.composite_with(Some(&layer)).to_color_image(),
+            egui::TextureOptions::NEAREST,
+        ));
+        dialog.preview_state = Some(state);
+    }
+
+    /// Отображает окно настройки цветовой коррекции (Adjustments -> ...).
+    /// Пока окно открыто, вместо холста показывается результат коррекции;
+    /// image_changed - изменилось ли изображение с прошлого кадра.
+    pub(super) fn show_adjustment_window(&mut self, ctx: &egui::Context, image_changed: bool) {
+        let Some(mut dialog) = self.adjustment_dialog.take() else {
+            return;
+        };
+        let locked = self.layers.active().locked;
+
+        let mut open = true;
+        let mut apply = false;
+        egui::Window::new(dialog.settings.adjustment.get_label())
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                let settings = &mut dialog.settings;
+                match settings.adjustment {
+                    Adjustment::BrightnessContrast => {
+                        ui.horizontal(|ui| {
+                            ui.label("яркость:");
+                            ui.add(egui::Slider::new(&mut settings.brightness, -255.0..=255.0));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("контраст:");
+                            ui.add(egui::Slider::new(&mut settings.contrast, -255.0..=255.0));
+                        });
+                    }
+                    Adjustment::Levels => dialog.show_levels(ui),
+                    Adjustment::Curves => dialog.show_curves(ui),
+                    Adjustment::HueSaturation => {
+                        ui.horizontal(|ui| {
+                            ui.label("тон:");
+                            ui.add(
+                                egui::Slider::new(&mut settings.hue, -180.0..=180.0).suffix("°"),
+                            );
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("насыщенность:");
+                            ui.add(
+                                egui::Slider::new(&mut settings.saturation, -100.0..=100.0)
+                                    .suffix("%"),
+                            );
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("значение:");
+                            ui.add(
+                                egui::Slider::new(&mut settings.value, -100.0..=100.0).suffix("%"),
+                            );
+                        });
+                    }
+                    Adjustment::Grayscale => {
+                        for method in GrayscaleMethod::ALL {
+                            ui.radio_value(&mut settings.grayscale, method, method.get_name());
+                        }
+                    }
+                    Adjustment::Invert => {}
+                    Adjustment::Posterize => {
+                        ui.horizontal(|ui| {
+                            ui.label("уровней:");
+                            ui.add(egui::Slider::new(&mut settings.posterize_levels, 2..=64));
+                        });
+                    }
+                    Adjustment::Threshold => {
+                        ui.horizontal(|ui| {
+                            ui.label("порог:");
+                            ui.add(egui::Slider::new(&mut settings.threshold, 0..=255));
+                        });
+                    }
+                    Adjustment::ChannelMixer => dialog.show_channel_mixer(ui),
+                    Adjustment::Equalize => {}
+                    Adjustment::Clahe => {
+                        ui.horizontal(|ui| {
+                            ui.label("блоков:");
+                            ui.add(egui::Slider::new(&mut settings.clahe_tiles, 1..=32));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("ограничение контраста:");
+                            ui.add(egui::Slider::new(
+                                &mut settings.clahe_clip_limit,
+                                0.0..=10.0,
+                            ))
+                            .on_hover_text("0 - без ограничения");
+                        });
+                    }
+                }
+
+                if locked {
+                    ui.label("активный слой закреплён");
+                }
+                ui.checkbox(&mut dialog.live_preview, "предпросмотр");
+                ui.horizontal(|ui| {
+                    if ui.button("Reset").clicked() {
+                        dialog.settings = AdjustmentSettings::new(dialog.settings.adjustment);
+                    }
+                    apply = ui.button("Apply").clicked();
+                });
+            });
+
+        if apply {
+            let settings = dialog.settings;
+            self.map_active_layer(ctx, |canvas| settings.apply(canvas));
+
+            #[cfg(debug_assertions)]
+            println!("применена коррекция {}", settings.adjustment.get_label());
+        } else if open {
+            self.update_adjustment_preview(ctx, &mut dialog, image_changed);
+            self.adjustment_dialog = Some(dialog);
+        }
+    }
+}
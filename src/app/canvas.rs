@@ -4,7 +4,39 @@ use egui::{Color32, ColorImage, Pos2, Vec2};
 
 use std::collections::VecDeque;
 
-#[derive(Default, PartialEq, Clone, Copy)]
+mod adjust;
+mod blend;
+mod conics;
+mod convolution;
+mod curves;
+mod dirty;
+mod edges;
+mod histogram;
+mod interop;
+mod polygon;
+mod quantize;
+mod rect;
+mod resample;
+mod texture;
+mod transform;
+mod triangulation;
+pub use adjust::{ChannelMixer, ColorChannel, GrayscaleMethod, Levels, ToneCurve};
+pub use blend::BlendMode;
+pub use convolution::{EdgeMode, Kernel};
+pub use curves::{CurveKind, CurveMethod, flatten_curve};
+pub use dirty::PixelRect;
+pub use edges::EdgeOperator;
+pub use histogram::Histogram;
+pub use interop::CanvasView;
+pub use polygon::FillRule;
+pub use quantize::{Dithering, FixedPalette};
+pub use rect::{Fill, GradientDirection};
+pub use resample::{Anchor, Resampling};
+pub use texture::{AddressMode, Sampling, Texture, TextureSampler};
+pub use transform::Affine;
+pub use triangulation::triangulate;
+
+#[derive(Default, PartialEq, Clone, Copy, serde::Serialize, serde::Deserialize)]
 /// Вариант связности, нужен для заливки.
 pub enum Connectivity {
     /// 4-х связная заливка
@@ -25,20 +57,31 @@ impl Connectivity {
 
 // =============== Реализация холста ===============
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Canvas {
     pixels: Vec<Color32>,
     width: usize,
     height: usize,
+    /// Область, изменённая с последней выгрузки в текстуру
+    dirty: Option<PixelRect>,
 }
 
 impl Canvas {
     pub fn new(width: usize, height: usize) -> Self {
-        Self {
-            pixels: vec![Color32::WHITE; width * height],
+        Self::new_filled(width, height, Color32::WHITE)
+    }
+
+    /// Холст, залитый цветом color (Color32::TRANSPARENT - прозрачный холст).
+    /// Новый холст целиком считается изменённым.
+    pub fn new_filled(width: usize, height: usize, color: Color32) -> Self {
+        let mut canvas = Self {
+            pixels: vec![color; width * height],
             width,
             height,
-        }
+            dirty: None,
+        };
+        canvas.mark_all_dirty();
+        canvas
     }
 
     #[inline]
@@ -47,6 +90,27 @@ impl Canvas {
         x < self.width && y < self.height && x > 0 && y > 0
     }
 
+    /// Закрасить пиксель, если он лежит в пределах холста.
+    fn put_pixel(&mut self, x: i32, y: i32, color: Color32) {
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            self[(x as usize, y as usize)] = color;
+        }
+    }
+
+    /// Закрасить пиксели строки y от x_start до x_end включительно (с обрезкой по холсту).
+    fn fill_row(&mut self, y: i32, x_start: i32, x_end: i32, color: Color32) {
+        if y < 0 || y as usize >= self.height {
+            return;
+        }
+        let start = x_start.max(0) as usize;
+        let end = (x_end + 1).clamp(0, self.width as i32) as usize;
+        if start < end {
+            let row = y as usize * self.width;
+            self.pixels[row + start..row + end].fill(color);
+            self.mark_dirty(PixelRect::new(start, y as usize, end - start, 1));
+        }
+    }
+
     /// Преобразовать холст в ColorImage для дальнейшего использования в egui.
     pub fn to_color_image(&self) -> ColorImage {
         ColorImage {
@@ -67,9 +131,48 @@ impl Canvas {
     /// Заполнить весь холст указанным цветом
     pub fn clear(&mut self, color: Color32) {
         self.pixels.fill(color);
+        self.mark_all_dirty();
+    }
+
+    /// Средний цвет квадрата size x size с центром в пикселе pos
+    /// (часть квадрата за пределами холста не учитывается).
+    pub fn sample_color(&self, pos: Pos2, size: usize) -> Color32 {
+        let (cx, cy) = (pos.x.floor() as i64, pos.y.floor() as i64);
+        let half = (size.max(1) / 2) as i64;
+        let x_range = (cx - half).max(0)..(cx + half + 1).min(self.width as i64);
+        let y_range = (cy - half).max(0)..(cy + half + 1).min(self.height as i64);
+
+        let mut sum = [0u64; 4];
+        let mut count = 0u64;
+        for y in y_range {
+            for x in x_range.clone() {
+                let color = self[(x as usize, y as usize)];
+                for (channel, value) in sum.iter_mut().zip(color.to_array()) {
+                    *channel += value as u64;
+                }
+                count += 1;
+            }
+        }
+        if count == 0 {
+            return Color32::TRANSPARENT;
+        }
+
+        let [r, g, b, a] = sum.map(|channel| ((channel + count / 2) / count) as u8);
+        Color32::from_rgba_premultiplied(r, g, b, a)
     }
 }
 
+/// Линейная интерполяция между двумя цветами, t = 0 соответствует color1.
+fn lerp_color(color1: Color32, color2: Color32, t: f32) -> Color32 {
+    let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    Color32::from_rgba_premultiplied(
+        lerp(color1.r(), color2.r()),
+        lerp(color1.g(), color2.g()),
+        lerp(color1.b(), color2.b()),
+        lerp(color1.a(), color2.a()),
+    )
+}
+
 // =============== Доступ к отдельным пикселям холста ===============
 
 impl Index<(usize, usize)> for Canvas {
@@ -88,6 +191,9 @@ impl IndexMut<(usize, usize)> for Canvas {
     fn index_mut(&mut self, index: (usize, usize)) -> &mut Self::Output {
         let (x, y) = index;
         self.check_bounds(x, y);
+        if x < self.width && y < self.height {
+            self.mark_pixel_dirty(x, y);
+        }
         &mut self.pixels[y * self.width + x]
     }
 }
@@ -182,63 +288,38 @@ impl Canvas {
 
     /// Рекурсивная заливка изображения.
     /// pos - позиция, в которой применяется заливка;
-    /// img - изображение для заливки;
+    /// img - изображение для заливки (полупрозрачные пиксели накладываются на прежний цвет);
     /// connectivity - тип заливки (4-х или 8-ми связная);
     pub fn fill_with_img(&mut self, pos: Pos2, img: &ColorImage, connectivity: Connectivity) {
         let start_x = pos.x as usize;
         let start_y = pos.y as usize;
 
-        if !self.check_bounds(start_x, start_y) {
+        if !self.check_bounds(start_x, start_y) || img.width() == 0 || img.height() == 0 {
             return;
         }
 
+        // область заливки находится заранее: после наложения прозрачных пикселей картинки
+        // цвет остаётся прежним, и залитые пиксели по цвету не отличить от незалитых
         let old_color = self[(start_x, start_y)];
-        let img_width = img.width();
-        let img_height = img.height();
-
-        let mut stack = VecDeque::new();
-        stack.push_back((start_x, start_y));
-
-        while let Some((x, y)) = stack.pop_front() {
-            if !self.check_bounds(x, y) || self[(x, y)] != old_color {
-                continue;
-            }
-
-            let (left, right) = self.find_line_bounds(x, y, old_color);
-
-            let mut im_y = (y as i32 - start_y as i32) as i32;
-            while im_y <= 0 {
-                im_y += img_height as i32;
-            }
-
-            for i in left..=right {
-                let mut im_x = i as i32 - start_x as i32;
-                while im_x <= 0 {
-                    im_x += img_width as i32;
-                }
-
-                let img_x = (im_x as usize).rem_euclid(img_width);
-                let img_y = (im_y as usize).rem_euclid(img_height);
-
-                if img_x < img_width && img_y < img_height {
-                    self[(i, y)] = img[(img_x, img_y)];
-                }
-            }
+        let marker = if old_color == Color32::TRANSPARENT {
+            Color32::WHITE
+        } else {
+            Color32::TRANSPARENT
+        };
+        let mut region = self.clone();
+        region.fill_with_color(pos, marker, connectivity);
+        let Some(rect) = self.diff_rect(&region) else {
+            return;
+        };
 
-            for i in left..=right {
-                self.check_and_push(i, y - 1, old_color, &mut stack);
-                self.check_and_push(i, y + 1, old_color, &mut stack);
-            }
-            match connectivity {
-                Connectivity::FOUR => {}
-                Connectivity::EIGHT => {
-                    if left > 0 {
-                        self.check_and_push(left - 1, y - 1, old_color, &mut stack);
-                        self.check_and_push(left - 1, y + 1, old_color, &mut stack);
-                    }
-                    self.check_and_push(right + 1, y - 1, old_color, &mut stack);
-                    self.check_and_push(right + 1, y + 1, old_color, &mut stack);
+        for y in rect.y..rect.y + rect.height {
+            let img_y = (y as i64 - start_y as i64).rem_euclid(img.height() as i64) as usize;
+            for x in rect.x..rect.x + rect.width {
+                if region[(x, y)] == self[(x, y)] {
+                    continue;
                 }
+                let img_x = (x as i64 - start_x as i64).rem_euclid(img.width() as i64) as usize;
+                self[(x, y)] = BlendMode::Normal.composite(old_color, img[(img_x, img_y)], 1.0);
             }
         }
     }
@@ -295,7 +376,7 @@ impl Canvas {
                     current_y - (-dy) as usize
                 };
 
-                if self[(next_x, next_y)] == boundary_color && self.check_bounds(next_x, next_y) {
+                if self.check_bounds(next_x, next_y) && self[(next_x, next_y)] == boundary_color {
                     current_x = next_x;
                     current_y = next_y;
                     prev_direction = direction_index;
@@ -351,7 +432,7 @@ impl Canvas {
         let mut error = dx + dy;
 
         loop {
-            self[(x0 as usize, y0 as usize)] = color;
+            self.put_pixel(x0, y0, color);
             let e2 = 2 * error;
             if e2 >= dy {
                 if x0 == x1 {
@@ -414,7 +495,7 @@ impl Canvas {
     }
 
     fn set_pixel(&mut self, x: i32, y: i32, color: Color32, intensity: f32) {
-        if x >= 0 && y >= 0 {
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
             let background = self[(x as usize, y as usize)];
 
             let bg_r = background.r() as f32;
@@ -484,46 +565,137 @@ impl Canvas {
         Color32::from_rgba_premultiplied(r, g, b, a)
     }
 
-    /// Градиентная растеризация треугольника через барицентрические координаты.
+    /// Обход пикселей холста внутри треугольника через барицентрические координаты.
     /// pos[1..3] - 3 точки треугольника;
-    /// color[1..3] - цвета соответствующих точек;
-    pub fn draw_gradient_triangle(
+    /// shade - вызывается с холстом, координатами пикселя и его барицентрическими координатами;
+    fn for_each_triangle_pixel(
         &mut self,
         pos1: Pos2,
         pos2: Pos2,
         pos3: Pos2,
-        color1: Color32,
-        color2: Color32,
-        color3: Color32,
+        mut shade: impl FnMut(&mut Self, usize, usize, (f32, f32, f32)),
     ) {
+        if self.width == 0 || self.height == 0 {
+            return;
+        }
+
         // ограничивающий прямоугольник
-        let min_x = pos1.x.min(pos2.x.min(pos3.x)).floor() as usize;
-        let min_y = pos1.y.min(pos2.y.min(pos3.y)).floor() as usize;
-        let max_x = pos1.x.max(pos2.x.max(pos3.x)).ceil() as usize;
-        let max_y = pos1.y.max(pos2.y.max(pos3.y)).ceil() as usize;
+        let min_x = pos1.x.min(pos2.x.min(pos3.x)).floor().max(0.0) as usize;
+        let min_y = pos1.y.min(pos2.y.min(pos3.y)).floor().max(0.0) as usize;
+        let max_x = (pos1.x.max(pos2.x.max(pos3.x)).ceil() as usize).min(self.width - 1);
+        let max_y = (pos1.y.max(pos2.y.max(pos3.y)).ceil() as usize).min(self.height - 1);
 
         // цикл по пикселям ограничевающего прямогольника
         for y in min_y..=max_y {
             for x in min_x..=max_x {
-                if x >= self.width || y >= self.height {
-                    continue;
-                }
-
                 let pixel_pos = Pos2::new(x as f32, y as f32);
 
                 // барицентрические координаты
-                if let Some((alpha, beta, gamma)) =
+                if let Some(coords @ (alpha, beta, gamma)) =
                     self.compute_barycentric_coords(pixel_pos, pos1, pos2, pos3)
                 {
                     // пиксель внутри треугольника
                     if alpha >= 0.0 && beta >= 0.0 && gamma >= 0.0 {
-                        // интерполяция цвета
-                        let color =
-                            self.interpolate_color(alpha, beta, gamma, color1, color2, color3);
-                        self[(x, y)] = color;
+                        shade(self, x, y, coords);
                     }
                 }
             }
         }
     }
+
+    /// Градиентная растеризация треугольника через барицентрические координаты.
+    /// pos[1..3] - 3 точки треугольника;
+    /// color[1..3] - цвета соответствующих точек;
+    pub fn draw_gradient_triangle(
+        &mut self,
+        pos1: Pos2,
+        pos2: Pos2,
+        pos3: Pos2,
+        color1: Color32,
+        color2: Color32,
+        color3: Color32,
+    ) {
+        self.for_each_triangle_pixel(pos1, pos2, pos3, |canvas, x, y, (alpha, beta, gamma)| {
+            // интерполяция цвета
+            let color = canvas.interpolate_color(alpha, beta, gamma, color1, color2, color3);
+            canvas[(x, y)] = color;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transparent_canvas() {
+        let canvas = Canvas::new_filled(3, 2, Color32::TRANSPARENT);
+        assert_eq!(canvas.size(), [3, 2]);
+        assert!(
+            canvas
+                .pixels
+                .iter()
+                .all(|&color| color == Color32::TRANSPARENT)
+        );
+        assert!(
+            Canvas::new(3, 2)
+                .pixels
+                .iter()
+                .all(|&color| color == Color32::WHITE)
+        );
+    }
+
+    #[test]
+    fn picked_color_keeps_alpha() {
+        let half = Color32::from_rgba_unmultiplied(0, 255, 0, 128);
+        let canvas = Canvas::new_filled(3, 3, half);
+        assert_eq!(canvas.sample_color(Pos2::new(1.5, 1.5), 1), half);
+        assert_eq!(canvas.sample_color(Pos2::new(1.5, 1.5), 3), half);
+
+        // прозрачные пиксели не затемняют цвет, а уменьшают непрозрачность
+        let mut canvas = Canvas::new_filled(2, 1, Color32::TRANSPARENT);
+        canvas[(1, 0)] = Color32::RED;
+        let picked = canvas.sample_color(Pos2::new(0.5, 0.5), 3);
+        let [r, g, b, a] = picked.to_srgba_unmultiplied();
+        assert_eq!([r, g, b], [255, 0, 0]);
+        assert_eq!(a, 128);
+    }
+
+    #[test]
+    fn image_fill_with_transparent_pixels_terminates() {
+        // область 6x6 в рамке чёрных пикселей
+        let mut canvas = Canvas::new(8, 8);
+        for i in 0..8 {
+            canvas[(i, 0)] = Color32::BLACK;
+            canvas[(i, 7)] = Color32::BLACK;
+            canvas[(0, i)] = Color32::BLACK;
+            canvas[(7, i)] = Color32::BLACK;
+        }
+        let half = Color32::from_rgba_unmultiplied(0, 0, 255, 128);
+        let image = ColorImage::new([2, 1], vec![Color32::TRANSPARENT, half]);
+        canvas.fill_with_img(Pos2::new(2.0, 2.0), &image, Connectivity::FOUR);
+
+        let blended = BlendMode::Normal.composite(Color32::WHITE, half, 1.0);
+        for y in 1..7 {
+            for x in 1..7 {
+                // картинка повторяется от точки заливки
+                let expected = if x % 2 == 0 { Color32::WHITE } else { blended };
+                assert_eq!(canvas[(x, y)], expected);
+            }
+        }
+        assert_eq!(canvas[(0, 3)], Color32::BLACK);
+    }
+
+    #[test]
+    fn image_fill_on_transparent_canvas() {
+        let mut canvas = Canvas::new_filled(4, 4, Color32::TRANSPARENT);
+        let image = ColorImage::new([1, 1], vec![Color32::RED]);
+        canvas.fill_with_img(Pos2::new(1.0, 1.0), &image, Connectivity::EIGHT);
+        // заливка не выходит на нулевые строку и столбец (см. check_bounds)
+        for y in 1..4 {
+            for x in 1..4 {
+                assert_eq!(canvas[(x, y)], Color32::RED);
+            }
+        }
+    }
 }
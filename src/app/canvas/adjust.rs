@@ -0,0 +1,504 @@
+use egui::Color32;
+
+use super::Canvas;
+
+/// Таблица преобразования значений канала [0, 255].
+pub type Lut = [u8; 256];
+
+/// Таблица значений функции f на [0, 255] с округлением и обрезкой.
+fn lut(f: impl Fn(f32) -> f32) -> Lut {
+    std::array::from_fn(|v| f(v as f32).round().clamp(0.0, 255.0) as u8)
+}
+
+/// Тождественная таблица.
+fn identity_lut() -> Lut {
+    std::array::from_fn(|v| v as u8)
+}
+
+#[derive(Default, PartialEq, Clone, Copy)]
+/// Каналы, к которым применяется коррекция.
+pub enum ColorChannel {
+    #[default]
+    /// Все цветовые каналы одинаково
+    Rgb,
+    Red,
+    Green,
+    Blue,
+}
+
+impl ColorChannel {
+    pub const ALL: [ColorChannel; 4] = [
+        ColorChannel::Rgb,
+        ColorChannel::Red,
+        ColorChannel::Green,
+        ColorChannel::Blue,
+    ];
+
+    pub fn get_name(&self) -> String {
+        match self {
+            ColorChannel::Rgb => String::from("RGB"),
+            ColorChannel::Red => String::from("красный"),
+            ColorChannel::Green => String::from("зелёный"),
+            ColorChannel::Blue => String::from("синий"),
+        }
+    }
+
+    /// Таблицы для каналов R, G, B: table для выбранных каналов, тождественная для остальных.
+    pub fn luts(&self, table: Lut) -> [Lut; 3] {
+        let mut luts = [identity_lut(); 3];
+        match self {
+            ColorChannel::Rgb => luts = [table; 3],
+            ColorChannel::Red => luts[0] = table,
+            ColorChannel::Green => luts[1] = table,
+            ColorChannel::Blue => luts[2] = table,
+        }
+        luts
+    }
+}
+
+/// Параметры уровней: входной диапазон [input[0], input[1]] растягивается
+/// до выходного [output[0], output[1]] с гамма-коррекцией средних тонов.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Levels {
+    pub input: [f32; 2],
+    pub gamma: f32,
+    pub output: [f32; 2],
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        Self {
+            input: [0.0, 255.0],
+            gamma: 1.0,
+            output: [0.0, 255.0],
+        }
+    }
+}
+
+impl Levels {
+    pub fn lut(&self) -> Lut {
+        let [black, white] = self.input;
+        let range = (white - black).max(1.0);
+        let gamma = self.gamma.max(0.01);
+        let [low, high] = self.output;
+        lut(|v| {
+            let t = ((v - black) / range).clamp(0.0, 1.0).powf(1.0 / gamma);
+            low + t * (high - low)
+        })
+    }
+}
+
+/// Тоновая кривая, заданная опорными точками [вход, выход] в [0, 255]
+/// и проходящая через них монотонным кубическим сплайном (Фрича-Карлсона).
+#[derive(Clone, PartialEq, Debug)]
+pub struct ToneCurve {
+    /// Точки по возрастанию входа, не меньше двух
+    points: Vec<[f32; 2]>,
+}
+
+impl Default for ToneCurve {
+    fn default() -> Self {
+        Self {
+            points: vec![[0.0, 0.0], [255.0, 255.0]],
+        }
+    }
+}
+
+impl ToneCurve {
+    /// Наименьшее расстояние между входами соседних точек
+    const MIN_GAP: f32 = 1.0;
+
+    pub fn points(&self) -> &[[f32; 2]] {
+        &self.points
+    }
+
+    /// Добавить точку (точка с тем же входом заменяется). Возвращает её номер.
+    pub fn insert(&mut self, point: [f32; 2]) -> usize {
+        let point = point.map(|c| c.clamp(0.0, 255.0));
+        if let Some(index) = self
+            .points
+            .iter()
+            .position(|p| (p[0] - point[0]).abs() < Self::MIN_GAP)
+        {
+            self.points[index] = point;
+            return index;
+        }
+        let index = self.points.partition_point(|p| p[0] < point[0]);
+        self.points.insert(index, point);
+        index
+    }
+
+    /// Передвинуть точку index, не переходя через соседние по входу.
+    pub fn move_point(&mut self, index: usize, point: [f32; 2]) {
+        let min = match index {
+            0 => 0.0,
+            _ => self.points[index - 1][0] + Self::MIN_GAP,
+        };
+        let max = match self.points.get(index + 1) {
+            Some(next) => next[0] - Self::MIN_GAP,
+            None => 255.0,
+        };
+        self.points[index] = [point[0].clamp(min, max), point[1].clamp(0.0, 255.0)];
+    }
+
+    /// Удалить точку index (последние две точки не удаляются).
+    pub fn remove(&mut self, index: usize) {
+        if self.points.len() > 2 {
+            self.points.remove(index);
+        }
+    }
+
+    /// Касательные в точках, не нарушающие монотонность между ними.
+    fn tangents(&self) -> Vec<f32> {
+        let p = &self.points;
+        let secants: Vec<f32> = p
+            .windows(2)
+            .map(|w| (w[1][1] - w[0][1]) / (w[1][0] - w[0][0]))
+            .collect();
+        let n = p.len();
+        let mut tangents: Vec<f32> = (0..n)
+            .map(|k| match k {
+                0 => secants[0],
+                k if k == n - 1 => secants[n - 2],
+                k if secants[k - 1] * secants[k] <= 0.0 => 0.0,
+                k => (secants[k - 1] + secants[k]) / 2.0,
+            })
+            .collect();
+        for (k, &secant) in secants.iter().enumerate() {
+            if secant == 0.0 {
+                tangents[k] = 0.0;
+                tangents[k + 1] = 0.0;
+                continue;
+            }
+            let (a, b) = (tangents[k] / secant, tangents[k + 1] / secant);
+            let s = a * a + b * b;
+            if s > 9.0 {
+                let t = 3.0 / s.sqrt();
+                tangents[k] = t * a * secant;
+                tangents[k + 1] = t * b * secant;
+            }
+        }
+        tangents
+    }
+
+    /// Таблица значений кривой. Вне крайних точек кривая постоянна.
+    pub fn lut(&self) -> Lut {
+        let p = &self.points;
+        let tangents = self.tangents();
+        let (first, last) = (p[0], p[p.len() - 1]);
+        lut(|x| {
+            if x <= first[0] {
+                return first[1];
+            }
+            if x >= last[0] {
+                return last[1];
+            }
+            let k = p.partition_point(|q| q[0] <= x) - 1;
+            let h = p[k + 1][0] - p[k][0];
+            let t = (x - p[k][0]) / h;
+            let (t2, t3) = (t * t, t * t * t);
+            (2.0 * t3 - 3.0 * t2 + 1.0) * p[k][1]
+                + (t3 - 2.0 * t2 + t) * h * tangents[k]
+                + (-2.0 * t3 + 3.0 * t2) * p[k + 1][1]
+                + (t3 - t2) * h * tangents[k + 1]
+        })
+    }
+}
+
+#[derive(Default, PartialEq, Clone, Copy)]
+/// Способ перевода цвета в оттенок серого.
+pub enum GrayscaleMethod {
+    #[default]
+    /// Яркость с весами BT.601 (0.299, 0.587, 0.114)
+    Luma601,
+    /// Яркость с весами BT.709 (0.2126, 0.7152, 0.0722)
+    Luma709,
+    /// Среднее каналов
+    Average,
+    /// Светлота: (max + min) / 2
+    Lightness,
+    /// Наибольший канал (значение в HSV)
+    Maximum,
+}
+
+impl GrayscaleMethod {
+    pub const ALL: [GrayscaleMethod; 5] = [
+        GrayscaleMethod::Luma601,
+        GrayscaleMethod::Luma709,
+        GrayscaleMethod::Average,
+        GrayscaleMethod::Lightness,
+        GrayscaleMethod::Maximum,
+    ];
+
+    pub fn get_name(&self) -> String {
+        match self {
+            GrayscaleMethod::Luma601 => String::from("яркость BT.601"),
+            GrayscaleMethod::Luma709 => String::from("яркость BT.709"),
+            GrayscaleMethod::Average => String::from("среднее"),
+            GrayscaleMethod::Lightness => String::from("светлота"),
+            GrayscaleMethod::Maximum => String::from("максимум"),
+        }
+    }
+
+    /// Оттенок серого для цвета [r, g, b].
+    pub fn gray(&self, [r, g, b]: [f32; 3]) -> f32 {
+        match self {
+            GrayscaleMethod::Luma601 => 0.299 * r + 0.587 * g + 0.114 * b,
+            GrayscaleMethod::Luma709 => 0.2126 * r + 0.7152 * g + 0.0722 * b,
+            GrayscaleMethod::Average => (r + g + b) / 3.0,
+            GrayscaleMethod::Lightness => (r.max(g).max(b) + r.min(g).min(b)) / 2.0,
+            GrayscaleMethod::Maximum => r.max(g).max(b),
+        }
+    }
+}
+
+/// Микшер каналов: выходной канал c равен сумме matrix[c][j] * (входной канал j)
+/// плюс offset[c]; в монохромном режиме все каналы берутся из первой строки.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ChannelMixer {
+    pub matrix: [[f32; 3]; 3],
+    pub offset: [f32; 3],
+    pub monochrome: bool,
+}
+
+impl Default for ChannelMixer {
+    fn default() -> Self {
+        Self {
+            matrix: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            offset: [0.0; 3],
+            monochrome: false,
+        }
+    }
+}
+
+/// Перевод RGB в [0, 1] в HSV: тон в градусах [0, 360), насыщенность и значение в [0, 1].
+fn rgb_to_hsv([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+    let hue = if delta <= 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    let saturation = if max > 0.0 { delta / max } else { 0.0 };
+    [hue, saturation, max]
+}
+
+/// Перевод HSV в RGB в [0, 1].
+fn hsv_to_rgb([hue, saturation, value]: [f32; 3]) -> [f32; 3] {
+    let chroma = value * saturation;
+    let h = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    let [r, g, b] = match h as u32 {
+        0 => [chroma, x, 0.0],
+        1 => [x, chroma, 0.0],
+        2 => [0.0, chroma, x],
+        3 => [0.0, x, chroma],
+        4 => [x, 0.0, chroma],
+        _ => [chroma, 0.0, x],
+    };
+    let m = value - chroma;
+    [r + m, g + m, b + m]
+}
+
+// =============== Цветовая коррекция ===============
+
+impl Canvas {
+    /// Холст, цвет каждого пикселя которого (без премультипликации, каналы в [0, 255])
+    /// заменён на map(цвет); прозрачность сохраняется.
+    fn map_rgb(&self, map: impl Fn([f32; 3]) -> [f32; 3]) -> Canvas {
+        let pixels = self
+            .pixels
+            .iter()
+            .map(|color| {
+                let [r, g, b, a] = color.to_srgba_unmultiplied();
+                let [r, g, b] =
+                    map([r, g, b].map(f32::from)).map(|c| c.round().clamp(0.0, 255.0) as u8);
+                Color32::from_rgba_unmultiplied(r, g, b, a)
+            })
+            .collect();
+        Canvas::from_pixels(self.width, self.height, pixels)
+    }
+
+    /// Применить к каналам R, G, B таблицы luts.
+    pub fn apply_luts(&self, luts: &[Lut; 3]) -> Canvas {
+        let pixels = self
+            .pixels
+            .iter()
+            .map(|color| {
+                let [r, g, b, a] = color.to_srgba_unmultiplied();
+                Color32::from_rgba_unmultiplied(
+                    luts[0][r as usize],
+                    luts[1][g as usize],
+                    luts[2][b as usize],
+                    a,
+                )
+            })
+            .collect();
+        Canvas::from_pixels(self.width, self.height, pixels)
+    }
+
+    /// Яркость и контраст: brightness добавляется к каналам,
+    /// contrast в [-255, 255] растягивает (или сжимает) тона относительно середины.
+    pub fn brightness_contrast(&self, brightness: f32, contrast: f32) -> Canvas {
+        let contrast = contrast.clamp(-255.0, 255.0);
+        let factor = 259.0 * (contrast + 255.0) / (255.0 * (259.0 - contrast));
+        let table = lut(|v| factor * (v - 128.0) + 128.0 + brightness);
+        self.apply_luts(&[table; 3])
+    }
+
+    /// Уровни для каналов channel.
+    pub fn levels(&self, levels: Levels, channel: ColorChannel) -> Canvas {
+        self.apply_luts(&channel.luts(levels.lut()))
+    }
+
+    /// Кривые: curves[0] применяется ко всем каналам, затем curves[1..4] - к R, G и B
+    /// (порядок ColorChannel::ALL).
+    pub fn curves(&self, curves: &[ToneCurve; 4]) -> Canvas {
+        let master = curves[0].lut();
+        let luts: [Lut; 3] = std::array::from_fn(|c| {
+            let channel = curves[c + 1].lut();
+            std::array::from_fn(|v| channel[master[v] as usize])
+        });
+        self.apply_luts(&luts)
+    }
+
+    /// Сдвиг тона на hue градусов, изменение насыщенности и значения
+    /// на saturation и value процентов.
+    pub fn hue_saturation_value(&self, hue: f32, saturation: f32, value: f32) -> Canvas {
+        let saturation = 1.0 + saturation / 100.0;
+        let value = 1.0 + value / 100.0;
+        self.map_rgb(|rgb| {
+            let [h, s, v] = rgb_to_hsv(rgb.map(|c| c / 255.0));
+            let hsv = [
+                h + hue,
+                (s * saturation).clamp(0.0, 1.0),
+                (v * value).clamp(0.0, 1.0),
+            ];
+            hsv_to_rgb(hsv).map(|c| c * 255.0)
+        })
+    }
+
+    /// Оттенки серого способом method.
+    pub fn grayscale(&self, method: GrayscaleMethod) -> Canvas {
+        self.map_rgb(|rgb| [method.gray(rgb); 3])
+    }
+
+    /// Негатив.
+    pub fn invert(&self) -> Canvas {
+        self.apply_luts(&[lut(|v| 255.0 - v); 3])
+    }
+
+    /// Постеризация: levels уровней в каждом канале (не меньше двух).
+    pub fn posterize(&self, levels: u32) -> Canvas {
+        let steps = levels.max(2) as f32 - 1.0;
+        self.apply_luts(&[lut(|v| (v / 255.0 * steps).round() * 255.0 / steps); 3])
+    }
+
+    /// Порог: пиксели с яркостью (BT.601) не меньше level становятся белыми, остальные - чёрными.
+    pub fn threshold(&self, level: u8) -> Canvas {
+        let level = level as f32;
+        self.map_rgb(|rgb| {
+            if GrayscaleMethod::Luma601.gray(rgb) >= level {
+                [255.0; 3]
+            } else {
+                [0.0; 3]
+            }
+        })
+    }
+
+    /// Смешивание каналов микшером mixer.
+    pub fn mix_channels(&self, mixer: &ChannelMixer) -> Canvas {
+        self.map_rgb(|rgb| {
+            let mix = |c: usize| {
+                let row = mixer.matrix[c];
+                row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2] + mixer.offset[c]
+            };
+            if mixer.monochrome {
+                [mix(0); 3]
+            } else {
+                [mix(0), mix(1), mix(2)]
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve(points: &[[f32; 2]]) -> ToneCurve {
+        let mut curve = ToneCurve::default();
+        for &point in points {
+            curve.insert(point);
+        }
+        curve
+    }
+
+    #[test]
+    fn identity_curve_gives_identity_lut() {
+        assert_eq!(ToneCurve::default().lut(), identity_lut());
+        // точки на диагонали не меняют кривую
+        assert_eq!(curve(&[[64.0, 64.0], [192.0, 192.0]]).lut(), identity_lut());
+    }
+
+    #[test]
+    fn monotone_curve_stays_monotone() {
+        // резкий подъём между пологими участками: без ограничения касательных
+        // сплайн проскакивает через соседние точки
+        let curve = curve(&[[100.0, 10.0], [110.0, 245.0], [120.0, 250.0]]);
+        for (k, &tangent) in curve.tangents().iter().enumerate() {
+            assert!(tangent >= 0.0, "касательная {k} отрицательна");
+        }
+        let lut = curve.lut();
+        assert!(lut.windows(2).all(|w| w[0] <= w[1]));
+        for point in curve.points() {
+            assert_eq!(lut[point[0] as usize], point[1] as u8);
+        }
+    }
+
+    #[test]
+    fn flat_segment_has_zero_tangents() {
+        let curve = curve(&[[100.0, 128.0], [150.0, 128.0]]);
+        let tangents = curve.tangents();
+        assert_eq!(&tangents[1..3], &[0.0, 0.0]);
+        let lut = curve.lut();
+        assert!(lut[100..=150].iter().all(|&v| v == 128));
+    }
+
+    #[test]
+    fn levels_map_input_points_to_output_points() {
+        let levels = Levels {
+            input: [40.0, 200.0],
+            gamma: 1.7,
+            output: [20.0, 230.0],
+        };
+        let lut = levels.lut();
+        assert_eq!(lut[40], 20);
+        assert_eq!(lut[200], 230);
+        // вне входного диапазона значения обрезаются
+        assert_eq!(lut[0], 20);
+        assert_eq!(lut[255], 230);
+        assert!(lut.windows(2).all(|w| w[0] <= w[1]));
+
+        assert_eq!(Levels::default().lut(), identity_lut());
+    }
+
+    #[test]
+    fn neutral_brightness_contrast_keeps_colors() {
+        let pixels = (0..=255u8)
+            .map(|v| Color32::from_rgba_unmultiplied(v, 255 - v, v / 2, 255))
+            .collect();
+        let canvas = Canvas::from_pixels(256, 1, pixels);
+        assert!(canvas.brightness_contrast(0.0, 0.0).pixels == canvas.pixels);
+
+        let brighter = canvas.brightness_contrast(10.0, 0.0);
+        assert_eq!(brighter[(0, 0)].r(), 10);
+        assert_eq!(brighter[(255, 0)].r(), 255);
+    }
+}
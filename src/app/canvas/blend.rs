@@ -0,0 +1,200 @@
+use egui::Color32;
+
+use super::{Canvas, PixelRect};
+
+#[derive(Default, PartialEq, Clone, Copy, serde::Serialize, serde::Deserialize)]
+/// Режим смешивания слоя с нижележащим изображением.
+pub enum BlendMode {
+    #[default]
+    /// Верхний слой просто перекрывает нижний
+    Normal,
+    /// Произведение цветов (затемнение)
+    Multiply,
+    /// Инверсия произведения инверсий (осветление)
+    Screen,
+    /// Multiply для тёмных участков основы, Screen для светлых
+    Overlay,
+    /// Минимум цветов
+    Darken,
+    /// Максимум цветов
+    Lighten,
+    /// Сумма цветов с насыщением
+    Add,
+    /// Модуль разности цветов
+    Difference,
+}
+
+impl BlendMode {
+    pub const ALL: [BlendMode; 8] = [
+        BlendMode::Normal,
+        BlendMode::Multiply,
+        BlendMode::Screen,
+        BlendMode::Overlay,
+        BlendMode::Darken,
+        BlendMode::Lighten,
+        BlendMode::Add,
+        BlendMode::Difference,
+    ];
+
+    pub fn get_name(&self) -> String {
+        match self {
+            BlendMode::Normal => String::from("обычный"),
+            BlendMode::Multiply => String::from("умножение"),
+            BlendMode::Screen => String::from("экран"),
+            BlendMode::Overlay => String::from("перекрытие"),
+            BlendMode::Darken => String::from("затемнение"),
+            BlendMode::Lighten => String::from("замена светлым"),
+            BlendMode::Add => String::from("сложение"),
+            BlendMode::Difference => String::from("разница"),
+        }
+    }
+
+    /// Смешивание непрозрачных цветов основы backdrop и слоя source (каналы в [0, 1]).
+    fn blend(&self, backdrop: f32, source: f32) -> f32 {
+        match self {
+            BlendMode::Normal => source,
+            BlendMode::Multiply => backdrop * source,
+            BlendMode::Screen => backdrop + source - backdrop * source,
+            BlendMode::Overlay => {
+                if backdrop <= 0.5 {
+                    2.0 * backdrop * source
+                } else {
+                    1.0 - 2.0 * (1.0 - backdrop) * (1.0 - source)
+                }
+            }
+            BlendMode::Darken => backdrop.min(source),
+            BlendMode::Lighten => backdrop.max(source),
+            BlendMode::Add => (backdrop + source).min(1.0),
+            BlendMode::Difference => (backdrop - source).abs(),
+        }
+    }
+
+    /// Наложение цвета source с непрозрачностью opacity на backdrop
+    /// по формуле композиции W3C (цвета с премультиплицированной альфой).
+    pub(super) fn composite(&self, backdrop: Color32, source: Color32, opacity: f32) -> Color32 {
+        let [cb @ .., ab] = backdrop.to_array().map(|c| c as f32 / 255.0);
+        let [cs @ .., as_] = source.to_array().map(|c| c as f32 / 255.0 * opacity);
+        if as_ <= 0.0 {
+            return backdrop;
+        }
+        if *self == BlendMode::Normal && as_ >= 1.0 {
+            return source;
+        }
+
+        let mut result = [0.0; 4];
+        for c in 0..3 {
+            // смешивание определено для цветов без премультипликации
+            let mixed = if ab > 0.0 {
+                self.blend(cb[c] / ab, cs[c] / as_)
+            } else {
+                0.0
+            };
+            result[c] = cs[c] * (1.0 - ab) + as_ * ab * mixed + cb[c] * (1.0 - as_);
+        }
+        result[3] = as_ + ab * (1.0 - as_);
+
+        let [r, g, b, a] = result.map(|c| (c * 255.0).round().clamp(0.0, 255.0) as u8);
+        Color32::from_rgba_premultiplied(r.min(a), g.min(a), b.min(a), a)
+    }
+}
+
+// =============== Смешивание слоёв ===============
+
+impl Canvas {
+    /// Наложить холст layer того же размера поверх текущего.
+    /// opacity - непрозрачность слоя в [0, 1];
+    /// mode - режим смешивания;
+    pub fn blend(&mut self, layer: &Canvas, opacity: f32, mode: BlendMode) {
+        self.blend_rect(layer, opacity, mode, self.bounds());
+    }
+
+    /// Наложить холст layer того же размера поверх текущего только в области rect.
+    pub fn blend_rect(&mut self, layer: &Canvas, opacity: f32, mode: BlendMode, rect: PixelRect) {
+        let opacity = opacity.clamp(0.0, 1.0);
+        let Some(rect) = rect.intersect(self.bounds()) else {
+            return;
+        };
+        if opacity <= 0.0 {
+            return;
+        }
+
+        for y in rect.y..rect.y + rect.height {
+            let row = y * self.width + rect.x..y * self.width + rect.x + rect.width;
+            for (backdrop, &source) in self.pixels[row.clone()].iter_mut().zip(&layer.pixels[row]) {
+                *backdrop = mode.composite(*backdrop, source, opacity);
+            }
+        }
+        self.mark_dirty(rect);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray(v: u8) -> Color32 {
+        Color32::from_gray(v)
+    }
+
+    #[test]
+    fn opaque_modes() {
+        let (dark, light) = (gray(64), gray(192));
+        assert_eq!(BlendMode::Normal.composite(dark, light, 1.0), light);
+        assert_eq!(BlendMode::Darken.composite(dark, light, 1.0), dark);
+        assert_eq!(BlendMode::Lighten.composite(dark, light, 1.0), light);
+        assert_eq!(BlendMode::Multiply.composite(gray(255), light, 1.0), light);
+        assert_eq!(BlendMode::Screen.composite(gray(0), light, 1.0), light);
+        assert_eq!(BlendMode::Add.composite(light, light, 1.0), gray(255));
+        assert_eq!(BlendMode::Difference.composite(light, light, 1.0), gray(0));
+        assert_eq!(
+            BlendMode::Multiply.composite(gray(128), gray(128), 1.0),
+            gray(64)
+        );
+    }
+
+    #[test]
+    fn transparent_source_keeps_backdrop() {
+        let backdrop = Color32::from_rgba_unmultiplied(10, 200, 30, 90);
+        for mode in BlendMode::ALL {
+            assert_eq!(
+                mode.composite(backdrop, Color32::TRANSPARENT, 1.0),
+                backdrop
+            );
+            assert_eq!(mode.composite(backdrop, Color32::RED, 0.0), backdrop);
+        }
+    }
+
+    #[test]
+    fn transparent_backdrop_shows_source() {
+        // на прозрачной основе режим смешивания не влияет на результат
+        let source = Color32::from_rgba_unmultiplied(200, 100, 50, 128);
+        for mode in BlendMode::ALL {
+            assert_eq!(mode.composite(Color32::TRANSPARENT, source, 1.0), source);
+        }
+    }
+
+    #[test]
+    fn opacity_mixes_with_backdrop() {
+        let result = BlendMode::Normal.composite(gray(0), gray(255), 0.5);
+        assert_eq!(result.a(), 255);
+        assert!((result.r() as i32 - 128).abs() <= 1);
+
+        // полупрозрачное на полупрозрачном: альфа 1 - (1 - 0.5)(1 - 0.5)
+        let half = Color32::from_rgba_unmultiplied(255, 0, 0, 128);
+        let result = BlendMode::Normal.composite(half, half, 1.0);
+        assert!((result.a() as i32 - 191).abs() <= 1);
+    }
+
+    #[test]
+    fn blend_rect_changes_only_the_rect() {
+        let mut canvas = Canvas::new_filled(4, 4, gray(0));
+        let layer = Canvas::new_filled(4, 4, gray(255));
+        canvas.blend_rect(&layer, 1.0, BlendMode::Normal, PixelRect::new(1, 1, 2, 2));
+        for y in 0..4 {
+            for x in 0..4 {
+                let inside = (1..3).contains(&x) && (1..3).contains(&y);
+                assert_eq!(canvas[(x, y)], if inside { gray(255) } else { gray(0) });
+            }
+        }
+    }
+}
@@ -0,0 +1,386 @@
+use egui::{Color32, Pos2, Vec2};
+
+use super::Canvas;
+
+/// Точки первого октанта окружности радиуса r алгоритмом средней точки.
+/// plot вызывается для каждой точки (x, y), где x >= y.
+fn midpoint_circle(r: i32, mut plot: impl FnMut(i32, i32)) {
+    let mut x = r;
+    let mut y = 0;
+    let mut error = 1 - r;
+
+    while x >= y {
+        plot(x, y);
+        y += 1;
+        if error < 0 {
+            error += 2 * y + 1;
+        } else {
+            x -= 1;
+            error += 2 * (y - x) + 1;
+        }
+    }
+}
+
+/// Точки первой четверти эллипса с полуосями rx, ry алгоритмом средней точки.
+/// plot вызывается для каждой точки (x, y) с x, y >= 0.
+fn midpoint_ellipse(rx: i32, ry: i32, mut plot: impl FnMut(i32, i32)) {
+    // вырожденный эллипс - отрезок вдоль другой оси
+    if rx == 0 {
+        (0..=ry).for_each(|y| plot(0, y));
+        return;
+    }
+    if ry == 0 {
+        (0..=rx).for_each(|x| plot(x, 0));
+        return;
+    }
+
+    let rx2 = (rx as f64).powi(2);
+    let ry2 = (ry as f64).powi(2);
+    let mut x = 0;
+    let mut y = ry;
+    let mut dx = 0.0;
+    let mut dy = 2.0 * rx2 * y as f64;
+
+    // область 1: наклон касательной меньше 1, шаг по x
+    let mut decision = ry2 - rx2 * ry as f64 + 0.25 * rx2;
+    while dx < dy {
+        plot(x, y);
+        x += 1;
+        dx += 2.0 * ry2;
+        if decision < 0.0 {
+            decision += dx + ry2;
+        } else {
+            y -= 1;
+            dy -= 2.0 * rx2;
+            decision += dx - dy + ry2;
+        }
+    }
+
+    // область 2: наклон больше 1, шаг по y
+    let mut decision = ry2 * (x as f64 + 0.5).powi(2) + rx2 * (y as f64 - 1.0).powi(2) - rx2 * ry2;
+    while y >= 0 {
+        plot(x, y);
+        y -= 1;
+        dy -= 2.0 * rx2;
+        if decision > 0.0 {
+            decision += rx2 - dy;
+        } else {
+            x += 1;
+            dx += 2.0 * ry2;
+            decision += dx - dy + rx2;
+        }
+    }
+}
+
+/// Неявное уравнение повёрнутого эллипса A dx^2 + B dx dy + C dy^2 = 1,
+/// где (dx, dy) - смещение от центра.
+struct Ellipse {
+    center: Pos2,
+    a: f32,
+    b: f32,
+    c: f32,
+    /// Половина размеров ограничивающего прямоугольника
+    half_extent: Vec2,
+}
+
+impl Ellipse {
+    fn new(center: Pos2, radii: Vec2, angle: f32) -> Self {
+        let (rx, ry) = (radii.x.abs().max(0.5), radii.y.abs().max(0.5));
+        let (sin, cos) = angle.sin_cos();
+        let (rx2, ry2) = (rx * rx, ry * ry);
+
+        Self {
+            center,
+            a: cos * cos / rx2 + sin * sin / ry2,
+            b: 2.0 * cos * sin * (1.0 / rx2 - 1.0 / ry2),
+            c: sin * sin / rx2 + cos * cos / ry2,
+            half_extent: Vec2::new(
+                (rx2 * cos * cos + ry2 * sin * sin).sqrt(),
+                (rx2 * sin * sin + ry2 * cos * cos).sqrt(),
+            ),
+        }
+    }
+
+    /// Корни квадратного уравнения p t^2 + q t + r = 0 (меньший, больший).
+    fn solve(p: f32, q: f32, r: f32) -> Option<(f32, f32)> {
+        let discriminant = q * q - 4.0 * p * r;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let sqrt = discriminant.sqrt();
+        Some(((-q - sqrt) / (2.0 * p), (-q + sqrt) / (2.0 * p)))
+    }
+
+    /// Пересечения эллипса с горизонталью y: (левый x, правый x).
+    fn row_intersections(&self, y: f32) -> Option<(f32, f32)> {
+        let dy = y - self.center.y;
+        let (left, right) = Self::solve(self.a, self.b * dy, self.c * dy * dy - 1.0)?;
+        Some((self.center.x + left, self.center.x + right))
+    }
+
+    /// Пересечения эллипса с вертикалью x: (верхний y, нижний y).
+    fn column_intersections(&self, x: f32) -> Option<(f32, f32)> {
+        let dx = x - self.center.x;
+        let (top, bottom) = Self::solve(self.c, self.b * dx, self.a * dx * dx - 1.0)?;
+        Some((self.center.y + top, self.center.y + bottom))
+    }
+
+    /// Направление нормали к эллипсу в точке p (градиент неявной функции).
+    fn gradient(&self, p: Pos2) -> Vec2 {
+        let d = p - self.center;
+        Vec2::new(
+            2.0 * self.a * d.x + self.b * d.y,
+            self.b * d.x + 2.0 * self.c * d.y,
+        )
+    }
+
+    /// Обход контура эллипса по центрам строк и столбцов пикселей.
+    /// В каждой точке выбирается направление с наименьшим наклоном (как у Ву):
+    /// plot(точка контура, true) - точка на строке, plot(точка, false) - на столбце.
+    fn trace(&self, mut plot: impl FnMut(Pos2, bool)) {
+        let min = self.center - self.half_extent;
+        let max = self.center + self.half_extent;
+
+        for y in (min.y - 0.5).floor() as i32..=(max.y + 0.5).ceil() as i32 {
+            let yc = y as f32 + 0.5;
+            if let Some((left, right)) = self.row_intersections(yc) {
+                for x in [left, right] {
+                    let p = Pos2::new(x, yc);
+                    let n = self.gradient(p);
+                    if n.x.abs() >= n.y.abs() {
+                        plot(p, true);
+                    }
+                }
+            }
+        }
+
+        for x in (min.x - 0.5).floor() as i32..=(max.x + 0.5).ceil() as i32 {
+            let xc = x as f32 + 0.5;
+            if let Some((top, bottom)) = self.column_intersections(xc) {
+                for y in [top, bottom] {
+                    let p = Pos2::new(xc, y);
+                    let n = self.gradient(p);
+                    if n.y.abs() > n.x.abs() {
+                        plot(p, false);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// =============== Окружности и эллипсы ===============
+
+impl Canvas {
+    /// Рисование окружности алгоритмом средней точки (Брезенхема).
+    /// center - центр окружности;
+    /// radius - радиус;
+    /// color - цвет линии;
+    pub fn draw_circle(&mut self, center: Pos2, radius: f32, color: Color32) {
+        let (cx, cy) = (center.x.floor() as i32, center.y.floor() as i32);
+        midpoint_circle(radius.round() as i32, |x, y| {
+            for (dx, dy) in [(x, y), (y, x)] {
+                self.put_pixel(cx + dx, cy + dy, color);
+                self.put_pixel(cx - dx, cy + dy, color);
+                self.put_pixel(cx + dx, cy - dy, color);
+                self.put_pixel(cx - dx, cy - dy, color);
+            }
+        });
+    }
+
+    /// Закрашенный круг по алгоритму средней точки.
+    /// center - центр круга;
+    /// radius - радиус;
+    /// color - цвет заливки;
+    pub fn fill_circle(&mut self, center: Pos2, radius: f32, color: Color32) {
+        let (cx, cy) = (center.x.floor() as i32, center.y.floor() as i32);
+        midpoint_circle(radius.round() as i32, |x, y| {
+            self.fill_row(cy + y, cx - x, cx + x, color);
+            self.fill_row(cy - y, cx - x, cx + x, color);
+            self.fill_row(cy + x, cx - y, cx + y, color);
+            self.fill_row(cy - x, cx - y, cx + y, color);
+        });
+    }
+
+    /// Рисование эллипса. Эллипс без поворота рисуется алгоритмом средней точки,
+    /// повёрнутый - обходом контура по строкам и столбцам пикселей.
+    /// center - центр эллипса;
+    /// radii - полуоси (до поворота);
+    /// angle - угол поворота в радианах;
+    /// color - цвет линии;
+    pub fn draw_ellipse(&mut self, center: Pos2, radii: Vec2, angle: f32, color: Color32) {
+        if angle.sin().abs() < 1e-6 || angle.cos().abs() < 1e-6 {
+            let radii = if angle.sin().abs() < 1e-6 {
+                radii
+            } else {
+                Vec2::new(radii.y, radii.x)
+            };
+            let (cx, cy) = (center.x.floor() as i32, center.y.floor() as i32);
+            midpoint_ellipse(radii.x.round() as i32, radii.y.round() as i32, |x, y| {
+                self.put_pixel(cx + x, cy + y, color);
+                self.put_pixel(cx - x, cy + y, color);
+                self.put_pixel(cx + x, cy - y, color);
+                self.put_pixel(cx - x, cy - y, color);
+            });
+            return;
+        }
+
+        Ellipse::new(center, radii, angle).trace(|p, _| {
+            self.put_pixel(p.x.floor() as i32, p.y.floor() as i32, color);
+        });
+    }
+
+    /// Закрашенный (возможно повёрнутый) эллипс.
+    /// Закрашиваются пиксели, центры которых лежат внутри эллипса.
+    /// center - центр эллипса;
+    /// radii - полуоси (до поворота);
+    /// angle - угол поворота в радианах;
+    /// color - цвет заливки;
+    pub fn fill_ellipse(&mut self, center: Pos2, radii: Vec2, angle: f32, color: Color32) {
+        let ellipse = Ellipse::new(center, radii, angle);
+        let min_y = (center.y - ellipse.half_extent.y).floor() as i32;
+        let max_y = (center.y + ellipse.half_extent.y).ceil() as i32;
+
+        for y in min_y..=max_y {
+            if let Some((left, right)) = ellipse.row_intersections(y as f32 + 0.5) {
+                self.fill_row(
+                    y,
+                    (left - 0.5).ceil() as i32,
+                    (right - 0.5).floor() as i32,
+                    color,
+                );
+            }
+        }
+    }
+
+    /// Рисование сглаженного (возможно повёрнутого) эллипса в стиле алгоритма Ву:
+    /// точка контура делит интенсивность между двумя соседними пикселями.
+    /// center - центр эллипса;
+    /// radii - полуоси (до поворота);
+    /// angle - угол поворота в радианах;
+    /// color - цвет линии;
+    pub fn draw_smooth_ellipse(&mut self, center: Pos2, radii: Vec2, angle: f32, color: Color32) {
+        Ellipse::new(center, radii, angle).trace(|p, on_row| {
+            if on_row {
+                let x = p.x - 0.5;
+                let x_floor = x.floor();
+                let y = p.y.floor() as i32;
+                self.set_pixel(x_floor as i32, y, color, 1.0 - (x - x_floor));
+                self.set_pixel(x_floor as i32 + 1, y, color, x - x_floor);
+            } else {
+                let y = p.y - 0.5;
+                let y_floor = y.floor();
+                let x = p.x.floor() as i32;
+                self.set_pixel(x, y_floor as i32, color, 1.0 - (y - y_floor));
+                self.set_pixel(x, y_floor as i32 + 1, color, y - y_floor);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::{Color32, Pos2, Vec2};
+
+    use super::*;
+
+    /// Закрашенные пиксели холста.
+    fn filled(canvas: &Canvas) -> Vec<(usize, usize)> {
+        let [width, height] = canvas.size();
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|&(x, y)| canvas[(x, y)] != Color32::WHITE)
+            .collect()
+    }
+
+    #[test]
+    fn midpoint_circle_stays_near_the_radius() {
+        for r in 1..40 {
+            let mut points = Vec::new();
+            midpoint_circle(r, |x, y| points.push((x, y)));
+            assert_eq!(points[0], (r, 0));
+            for (x, y) in points {
+                assert!(x >= y);
+                let distance = ((x * x + y * y) as f32).sqrt();
+                assert!((distance - r as f32).abs() <= 0.5, "r = {r}: ({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn midpoint_ellipse_quadrant_is_connected() {
+        for (rx, ry) in [(1, 1), (10, 3), (3, 10), (25, 24)] {
+            let mut points = Vec::new();
+            midpoint_ellipse(rx, ry, |x, y| points.push((x, y)));
+            assert_eq!(points.first(), Some(&(0, ry)));
+            assert_eq!(points.last(), Some(&(rx, 0)));
+            // соседние точки 8-связны
+            for pair in points.windows(2) {
+                let (dx, dy) = (pair[1].0 - pair[0].0, pair[1].1 - pair[0].1);
+                assert!((0..=1).contains(&dx) && (-1..=0).contains(&dy) && dx + dy.abs() > 0);
+            }
+        }
+    }
+
+    #[test]
+    fn circle_outline_is_symmetric() {
+        let mut canvas = Canvas::new(21, 21);
+        canvas.draw_circle(Pos2::new(10.0, 10.0), 7.0, Color32::RED);
+        let pixels = filled(&canvas);
+        assert!(!pixels.is_empty());
+        for &(x, y) in &pixels {
+            for mirrored in [(20 - x, y), (x, 20 - y), (y, x)] {
+                assert!(pixels.contains(&mirrored));
+            }
+        }
+        assert!(pixels.contains(&(17, 10)) && pixels.contains(&(10, 3)));
+    }
+
+    #[test]
+    fn filled_ellipse_covers_pixel_centers_inside() {
+        let mut canvas = Canvas::new(20, 20);
+        let (center, radii) = (Pos2::new(10.0, 10.0), Vec2::new(6.0, 3.0));
+        canvas.fill_ellipse(center, radii, 0.0, Color32::RED);
+        for y in 0..20 {
+            for x in 0..20 {
+                let dx = (x as f32 + 0.5 - center.x) / radii.x;
+                let dy = (y as f32 + 0.5 - center.y) / radii.y;
+                let inside = dx * dx + dy * dy <= 1.0;
+                assert_eq!(canvas[(x, y)] == Color32::RED, inside, "({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn filled_circle_area() {
+        let mut canvas = Canvas::new(41, 41);
+        canvas.fill_circle(Pos2::new(20.0, 20.0), 15.0, Color32::RED);
+        let area = filled(&canvas).len() as f32;
+        // закрашиваются и пиксели контура, поэтому радиус больше на полпикселя
+        let expected = std::f32::consts::PI * 15.5 * 15.5;
+        assert!((area - expected).abs() / expected < 0.02);
+    }
+
+    #[test]
+    fn flat_ellipse_is_a_line() {
+        let mut canvas = Canvas::new(30, 30);
+        canvas.draw_ellipse(
+            Pos2::new(15.0, 15.0),
+            Vec2::new(10.0, 0.0),
+            0.0,
+            Color32::RED,
+        );
+        let expected: Vec<_> = (5..=25).map(|x| (x, 15)).collect();
+        assert_eq!(filled(&canvas), expected);
+
+        let mut canvas = Canvas::new(30, 30);
+        canvas.draw_ellipse(
+            Pos2::new(15.0, 15.0),
+            Vec2::new(0.0, 4.0),
+            0.0,
+            Color32::RED,
+        );
+        let expected: Vec<_> = (11..=19).map(|y| (15, y)).collect();
+        assert_eq!(filled(&canvas), expected);
+    }
+}
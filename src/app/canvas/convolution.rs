@@ -0,0 +1,479 @@
+use super::resample::color_from_channels;
+use super::{Canvas, PixelRect};
+
+#[derive(Default, PartialEq, Clone, Copy)]
+/// Значения пикселей за краем изображения при свёртке.
+pub enum EdgeMode {
+    #[default]
+    /// Повторение крайнего пикселя
+    Clamp,
+    /// Изображение повторяется периодически
+    Wrap,
+    /// Зеркальное отражение относительно края
+    Mirror,
+    /// Прозрачные (нулевые) пиксели
+    Zero,
+}
+
+impl EdgeMode {
+    pub const ALL: [EdgeMode; 4] = [
+        EdgeMode::Clamp,
+        EdgeMode::Wrap,
+        EdgeMode::Mirror,
+        EdgeMode::Zero,
+    ];
+
+    pub fn get_name(&self) -> String {
+        match self {
+            EdgeMode::Clamp => String::from("крайний пиксель"),
+            EdgeMode::Wrap => String::from("повтор"),
+            EdgeMode::Mirror => String::from("зеркало"),
+            EdgeMode::Zero => String::from("прозрачный"),
+        }
+    }
+
+    /// Индекс пикселя для координаты coord на отрезке длины size
+    /// (None - пиксель считается нулевым).
+    #[inline]
+    fn index(&self, coord: i64, size: usize) -> Option<usize> {
+        let size = size as i64;
+        if (0..size).contains(&coord) {
+            return Some(coord as usize);
+        }
+        let coord = match self {
+            EdgeMode::Clamp => coord.clamp(0, size - 1),
+            EdgeMode::Wrap => coord.rem_euclid(size),
+            EdgeMode::Mirror => {
+                // отражение без повторения крайнего пикселя: -1 -> 1, size -> size - 2
+                let period = (2 * size - 2).max(1);
+                let coord = coord.rem_euclid(period);
+                if coord < size { coord } else { period - coord }
+            }
+            EdgeMode::Zero => return None,
+        };
+        Some(coord as usize)
+    }
+}
+
+/// Ядро свёртки размера width x height с центром в (width / 2, height / 2).
+#[derive(Clone, PartialEq, Debug)]
+pub struct Kernel {
+    width: usize,
+    height: usize,
+    /// Веса по строкам
+    weights: Vec<f32>,
+    /// Добавка к цветовым каналам результата (в [0, 255])
+    pub bias: f32,
+    /// Разложение ядра в произведение столбца и строки, если оно существует
+    separable: Option<(Vec<f32>, Vec<f32>)>,
+}
+
+impl Kernel {
+    /// Ядро из весов по строкам, разделимость определяется автоматически.
+    /// Возвращает None, если число весов не равно width * height или размеры нулевые.
+    pub fn new(width: usize, height: usize, weights: Vec<f32>) -> Option<Self> {
+        if width == 0 || height == 0 || weights.len() != width * height {
+            return None;
+        }
+        let separable = Self::factorize(width, height, &weights);
+        Some(Self {
+            width,
+            height,
+            weights,
+            bias: 0.0,
+            separable,
+        })
+    }
+
+    /// Разделимое ядро - произведение столбца column и строки row.
+    pub fn separable(column: Vec<f32>, row: Vec<f32>) -> Self {
+        let weights = column
+            .iter()
+            .flat_map(|&c| row.iter().map(move |&r| c * r))
+            .collect();
+        Self {
+            width: row.len(),
+            height: column.len(),
+            weights,
+            bias: 0.0,
+            separable: Some((column, row)),
+        }
+    }
+
+    /// Разложить ядро ранга 1 на столбец и строку.
+    fn factorize(width: usize, height: usize, weights: &[f32]) -> Option<(Vec<f32>, Vec<f32>)> {
+        // опорный элемент - наибольший по модулю
+        let (pivot, &value) = weights
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))?;
+        if value.abs() < 1e-12 || (width == 1 && height == 1) {
+            return None;
+        }
+        let (px, py) = (pivot % width, pivot / width);
+        let row: Vec<f32> = weights[py * width..(py + 1) * width].to_vec();
+        let column: Vec<f32> = (0..height)
+            .map(|y| weights[y * width + px] / value)
+            .collect();
+
+        let tolerance = 1e-5 * value.abs();
+        let is_rank_one = (0..height).all(|y| {
+            (0..width).all(|x| (weights[y * width + x] - column[y] * row[x]).abs() <= tolerance)
+        });
+        is_rank_one.then_some((column, row))
+    }
+
+    /// Ядро с весами, делёнными на divisor (обычно сумму весов).
+    pub fn normalized(mut self, divisor: f32) -> Self {
+        if divisor.abs() > 1e-12 {
+            self.weights.iter_mut().for_each(|w| *w /= divisor);
+            if let Some((column, _)) = &mut self.separable {
+                column.iter_mut().for_each(|w| *w /= divisor);
+            }
+        }
+        self
+    }
+
+    /// Ядро с добавкой bias к цветовым каналам.
+    pub fn with_bias(mut self, bias: f32) -> Self {
+        self.bias = bias;
+        self
+    }
+
+    pub fn size(&self) -> [usize; 2] {
+        [self.width, self.height]
+    }
+
+    pub fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+
+    pub fn is_separable(&self) -> bool {
+        self.separable.is_some()
+    }
+
+    /// Сумма весов.
+    pub fn sum(&self) -> f32 {
+        self.weights.iter().sum()
+    }
+
+    /// Усреднение по квадрату (2 radius + 1) x (2 radius + 1).
+    pub fn box_blur(radius: usize) -> Self {
+        let size = 2 * radius + 1;
+        let line = vec![1.0 / size as f32; size];
+        Self::separable(line.clone(), line)
+    }
+
+    /// Размытие по Гауссу со стандартным отклонением sigma (радиус ядра 3 sigma).
+    pub fn gaussian(sigma: f32) -> Self {
+        let sigma = sigma.max(0.1);
+        let radius = (3.0 * sigma).ceil() as i64;
+        let mut line: Vec<f32> = (-radius..=radius)
+            .map(|x| (-(x * x) as f32 / (2.0 * sigma * sigma)).exp())
+            .collect();
+        let sum: f32 = line.iter().sum();
+        line.iter_mut().for_each(|w| *w /= sum);
+        Self::separable(line.clone(), line)
+    }
+
+    /// Повышение резкости: центр 1 + 4 amount, соседи по сторонам -amount.
+    pub fn sharpen(amount: f32) -> Self {
+        #[rustfmt::skip]
+        let weights = vec![
+            0.0, -amount, 0.0,
+            -amount, 1.0 + 4.0 * amount, -amount,
+            0.0, -amount, 0.0,
+        ];
+        Self::new(3, 3, weights).unwrap()
+    }
+
+    /// Тиснение (рельеф с освещением слева сверху).
+    pub fn emboss() -> Self {
+        #[rustfmt::skip]
+        let weights = vec![
+            -2.0, -1.0, 0.0,
+            -1.0, 1.0, 1.0,
+            0.0, 1.0, 2.0,
+        ];
+        Self::new(3, 3, weights).unwrap()
+    }
+}
+
+// =============== Свёртка ===============
+
+impl Canvas {
+    /// Каналы пикселей (с премультиплицированной альфой) в f32.
+    pub(super) fn channels(&self) -> Vec<[f32; 4]> {
+        self.pixels
+            .iter()
+            .map(|color| color.to_array().map(|c| c as f32))
+            .collect()
+    }
+
+    /// Свёртка одномерным ядром weights вдоль строк (horizontal) или столбцов.
+    fn convolve_line(
+        &self,
+        source: &[[f32; 4]],
+        weights: &[f32],
+        horizontal: bool,
+        edge: EdgeMode,
+    ) -> Vec<[f32; 4]> {
+        let (width, height) = (self.width, self.height);
+        let center = (weights.len() / 2) as i64;
+        let mut result = vec![[0.0; 4]; source.len()];
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0.0; 4];
+                for (k, &weight) in weights.iter().enumerate() {
+                    let offset = k as i64 - center;
+                    let index = if horizontal {
+                        edge.index(x as i64 + offset, width)
+                            .map(|sx| y * width + sx)
+                    } else {
+                        edge.index(y as i64 + offset, height)
+                            .map(|sy| sy * width + x)
+                    };
+                    if let Some(index) = index {
+                        let color = source[index];
+                        for c in 0..4 {
+                            sum[c] += color[c] * weight;
+                        }
+                    }
+                }
+                result[y * width + x] = sum;
+            }
+        }
+        result
+    }
+
+    /// Свёртка ядром общего вида.
+    fn convolve_full(&self, source: &[[f32; 4]], kernel: &Kernel, edge: EdgeMode) -> Vec<[f32; 4]> {
+        let (width, height) = (self.width, self.height);
+        let (cx, cy) = ((kernel.width / 2) as i64, (kernel.height / 2) as i64);
+        let mut result = vec![[0.0; 4]; source.len()];
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0.0; 4];
+                for ky in 0..kernel.height {
+                    let Some(sy) = edge.index(y as i64 + ky as i64 - cy, height) else {
+                        continue;
+                    };
+                    for kx in 0..kernel.width {
+                        let weight = kernel.weights[ky * kernel.width + kx];
+                        if weight == 0.0 {
+                            continue;
+                        }
+                        let Some(sx) = edge.index(x as i64 + kx as i64 - cx, width) else {
+                            continue;
+                        };
+                        let color = source[sy * width + sx];
+                        for c in 0..4 {
+                            sum[c] += color[c] * weight;
+                        }
+                    }
+                }
+                result[y * width + x] = sum;
+            }
+        }
+        result
+    }
+
+    /// Каналы после свёртки ядром kernel (без добавки bias и обрезки).
+    /// Разделимые ядра применяются двумя одномерными проходами.
+    pub(super) fn convolve_channels(&self, kernel: &Kernel, edge: EdgeMode) -> Vec<[f32; 4]> {
+        self.convolve_values(&self.channels(), kernel, edge)
+    }
+
+    /// Свёртка произвольных значений source, расположенных как пиксели холста.
+    pub(super) fn convolve_values(
+        &self,
+        source: &[[f32; 4]],
+        kernel: &Kernel,
+        edge: EdgeMode,
+    ) -> Vec<[f32; 4]> {
+        if self.width == 0 || self.height == 0 {
+            return source.to_vec();
+        }
+        match &kernel.separable {
+            Some((column, row)) => {
+                let horizontal = self.convolve_line(source, row, true, edge);
+                self.convolve_line(&horizontal, column, false, edge)
+            }
+            None => self.convolve_full(source, kernel, edge),
+        }
+    }
+
+    /// Свёртка изображения с ядром kernel, edge задаёт пиксели за краем.
+    /// Цвета сворачиваются с премультиплицированной альфой. Альфа-канал сворачивается
+    /// тем же ядром, кроме ядер с нулевой суммой (выделение границ и т.п.),
+    /// для которых прозрачность сохраняется.
+    pub fn convolve(&self, kernel: &Kernel, edge: EdgeMode) -> Canvas {
+        let channels = self.convolve_channels(kernel, edge);
+        let keep_alpha = kernel.sum().abs() < 1e-3;
+
+        let pixels = channels
+            .iter()
+            .zip(&self.pixels)
+            .map(|(&[r, g, b, a], source)| {
+                let a = if keep_alpha { source.a() as f32 } else { a };
+                // добавка задана для непрозрачных цветов
+                let bias = kernel.bias * a.clamp(0.0, 255.0) / 255.0;
+                color_from_channels([r + bias, g + bias, b + bias, a])
+            })
+            .collect();
+        Canvas::from_pixels(self.width, self.height, pixels)
+    }
+
+    /// Нерезкое маскирование: к изображению добавляется amount разностей
+    /// с размытой по Гауссу (sigma) копией, если разность больше threshold.
+    pub fn unsharp_mask(&self, sigma: f32, amount: f32, threshold: f32, edge: EdgeMode) -> Canvas {
+        let blurred = self.convolve_channels(&Kernel::gaussian(sigma), edge);
+        let pixels = self
+            .pixels
+            .iter()
+            .zip(&blurred)
+            .map(|(color, blurred)| {
+                let original = color.to_array().map(|c| c as f32);
+                let mut result = original;
+                for c in 0..3 {
+                    let difference = original[c] - blurred[c];
+                    if difference.abs() > threshold {
+                        result[c] += amount * difference;
+                    }
+                }
+                color_from_channels(result)
+            })
+            .collect();
+        Canvas::from_pixels(self.width, self.height, pixels)
+    }
+
+    /// Скопировать пиксели области rect из холста source того же размера.
+    pub fn copy_region(&mut self, source: &Canvas, rect: PixelRect) {
+        let Some(rect) = rect.intersect(self.bounds()) else {
+            return;
+        };
+        for y in rect.y..rect.y + rect.height {
+            let row = y * self.width + rect.x..y * self.width + rect.x + rect.width;
+            self.pixels[row.clone()].copy_from_slice(&source.pixels[row]);
+        }
+        self.mark_dirty(rect);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::Color32;
+
+    use super::*;
+
+    #[test]
+    fn mirror_index_on_short_lines() {
+        // на отрезке из одного пикселя любое отражение попадает в него
+        for coord in [-3, -1, 1, 2, 5] {
+            assert_eq!(EdgeMode::Mirror.index(coord, 1), Some(0));
+        }
+        // на отрезке из двух пикселей отражение чередует их
+        let indices: Vec<_> = (-3..5).map(|c| EdgeMode::Mirror.index(c, 2)).collect();
+        let expected = [1, 0, 1, 0, 1, 0, 1, 0].map(Some);
+        assert_eq!(indices, expected);
+        // крайний пиксель не повторяется
+        assert_eq!(EdgeMode::Mirror.index(-1, 5), Some(1));
+        assert_eq!(EdgeMode::Mirror.index(5, 5), Some(3));
+    }
+
+    #[test]
+    fn wrap_index_with_negative_coordinates() {
+        assert_eq!(EdgeMode::Wrap.index(-1, 4), Some(3));
+        assert_eq!(EdgeMode::Wrap.index(-4, 4), Some(0));
+        assert_eq!(EdgeMode::Wrap.index(-9, 4), Some(3));
+        assert_eq!(EdgeMode::Wrap.index(6, 4), Some(2));
+    }
+
+    #[test]
+    fn clamp_and_zero_index() {
+        assert_eq!(EdgeMode::Clamp.index(-5, 3), Some(0));
+        assert_eq!(EdgeMode::Clamp.index(7, 3), Some(2));
+        assert_eq!(EdgeMode::Zero.index(-1, 3), None);
+        assert_eq!(EdgeMode::Zero.index(3, 3), None);
+        assert_eq!(EdgeMode::Zero.index(1, 3), Some(1));
+    }
+
+    #[test]
+    fn rank_one_kernel_is_factorized() {
+        #[rustfmt::skip]
+        let weights = vec![
+            1.0, 2.0, 1.0,
+            2.0, 4.0, 2.0,
+            1.0, 2.0, 1.0,
+        ];
+        let kernel = Kernel::new(3, 3, weights.clone()).unwrap();
+        let (column, row) = kernel.separable.clone().unwrap();
+        for y in 0..3 {
+            for x in 0..3 {
+                assert!((column[y] * row[x] - weights[y * 3 + x]).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn non_separable_kernels_are_rejected() {
+        assert!(!Kernel::sharpen(1.0).is_separable());
+        assert!(!Kernel::emboss().is_separable());
+        assert!(Kernel::new(1, 1, vec![2.0]).is_some_and(|k| !k.is_separable()));
+        assert!(Kernel::new(2, 2, vec![0.0; 4]).is_some_and(|k| !k.is_separable()));
+        assert!(Kernel::new(2, 2, vec![1.0; 3]).is_none());
+        assert!(Kernel::new(0, 2, vec![]).is_none());
+    }
+
+    #[test]
+    fn separable_and_full_convolution_agree() {
+        let pixels = (0..20)
+            .map(|i| Color32::from_rgba_premultiplied(i * 10, 200 - i * 5, i * 3, 255))
+            .collect();
+        let canvas = Canvas::from_pixels(5, 4, pixels);
+        let separable = Kernel::gaussian(0.8);
+        let [width, height] = separable.size();
+        let full = Kernel {
+            separable: None,
+            ..Kernel::new(width, height, separable.weights().to_vec()).unwrap()
+        };
+        for edge in EdgeMode::ALL {
+            let a = canvas.convolve_channels(&separable, edge);
+            let b = canvas.convolve_channels(&full, edge);
+            for (a, b) in a.iter().zip(&b) {
+                for c in 0..4 {
+                    assert!((a[c] - b[c]).abs() < 1e-3);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn zero_sum_kernel_keeps_alpha() {
+        let mut canvas =
+            Canvas::new_filled(4, 4, Color32::from_rgba_premultiplied(60, 60, 60, 128));
+        canvas[(1, 1)] = Color32::from_rgba_premultiplied(100, 0, 0, 200);
+        #[rustfmt::skip]
+        let laplacian = Kernel::new(3, 3, vec![
+            0.0, -1.0, 0.0,
+            -1.0, 4.0, -1.0,
+            0.0, -1.0, 0.0,
+        ]).unwrap();
+        let result = canvas.convolve(&laplacian, EdgeMode::Clamp);
+        for y in 0..4 {
+            for x in 0..4 {
+                assert_eq!(result[(x, y)].a(), canvas[(x, y)].a());
+            }
+        }
+    }
+
+    #[test]
+    fn blur_of_uniform_image_is_unchanged() {
+        let color = Color32::from_rgba_premultiplied(40, 80, 120, 160);
+        let canvas = Canvas::new_filled(6, 3, color);
+        for edge in [EdgeMode::Clamp, EdgeMode::Wrap, EdgeMode::Mirror] {
+            let result = canvas.convolve(&Kernel::box_blur(2), edge);
+            assert!(result.pixels.iter().all(|&c| c == color));
+        }
+    }
+}
@@ -0,0 +1,323 @@
+use egui::{Color32, Pos2};
+
+use super::Canvas;
+
+#[derive(Default, PartialEq, Clone, Copy, serde::Serialize, serde::Deserialize)]
+/// Вид кривой.
+pub enum CurveKind {
+    /// Квадратичная кривая Безье (3 контрольные точки)
+    Quadratic,
+    #[default]
+    /// Кубическая кривая Безье (4 контрольные точки)
+    Cubic,
+    /// Сплайн Катмулла-Рома, проходящий через все точки
+    CatmullRom,
+}
+
+impl CurveKind {
+    pub fn get_name(&self) -> String {
+        match self {
+            CurveKind::Quadratic => String::from("квадратичная Безье"),
+            CurveKind::Cubic => String::from("кубическая Безье"),
+            CurveKind::CatmullRom => String::from("Катмулл-Ром"),
+        }
+    }
+
+    /// Число контрольных точек кривой, None - любое (от 2-х).
+    pub fn control_points(&self) -> Option<usize> {
+        match self {
+            CurveKind::Quadratic => Some(3),
+            CurveKind::Cubic => Some(4),
+            CurveKind::CatmullRom => None,
+        }
+    }
+}
+
+#[derive(Default, PartialEq, Clone, Copy, serde::Serialize, serde::Deserialize)]
+/// Способ разбиения кривой на отрезки.
+pub enum CurveMethod {
+    #[default]
+    /// Адаптивное деление пополам (де Кастельжо) до достижения плоскостности
+    Subdivision,
+    /// Равномерные шаги по параметру через конечные разности
+    ForwardDifferencing,
+}
+
+impl CurveMethod {
+    pub fn get_name(&self) -> String {
+        match self {
+            CurveMethod::Subdivision => String::from("деление"),
+            CurveMethod::ForwardDifferencing => String::from("конечные разности"),
+        }
+    }
+}
+
+/// Максимальное отклонение кривой от отрезка при адаптивном делении (в пикселях).
+const FLATNESS_TOLERANCE: f32 = 0.25;
+/// Максимальная глубина адаптивного деления.
+const MAX_SUBDIVISION_DEPTH: u32 = 16;
+
+/// Расстояние от точки p до прямой ab.
+fn distance_to_line(p: Pos2, a: Pos2, b: Pos2) -> f32 {
+    let ab = b - a;
+    let length = ab.length();
+    if length < f32::EPSILON {
+        return p.distance(a);
+    }
+    (ab.x * (p.y - a.y) - ab.y * (p.x - a.x)).abs() / length
+}
+
+/// Адаптивное деление кубической кривой Безье, добавляет в points все точки кроме первой.
+fn subdivide_cubic(control: [Pos2; 4], depth: u32, points: &mut Vec<Pos2>) {
+    let [p0, p1, p2, p3] = control;
+    let flatness = distance_to_line(p1, p0, p3).max(distance_to_line(p2, p0, p3));
+    if flatness <= FLATNESS_TOLERANCE || depth >= MAX_SUBDIVISION_DEPTH {
+        points.push(p3);
+        return;
+    }
+
+    // де Кастельжо при t = 0.5
+    let p01 = p0.lerp(p1, 0.5);
+    let p12 = p1.lerp(p2, 0.5);
+    let p23 = p2.lerp(p3, 0.5);
+    let p012 = p01.lerp(p12, 0.5);
+    let p123 = p12.lerp(p23, 0.5);
+    let middle = p012.lerp(p123, 0.5);
+
+    subdivide_cubic([p0, p01, p012, middle], depth + 1, points);
+    subdivide_cubic([middle, p123, p23, p3], depth + 1, points);
+}
+
+/// Разбиение кубической кривой Безье на равные шаги по параметру через
+/// конечные разности, добавляет в points все точки кроме первой.
+fn forward_difference_cubic(control: [Pos2; 4], points: &mut Vec<Pos2>) {
+    let [p0, p1, p2, p3] = control.map(|p| p.to_vec2());
+
+    // число шагов - примерно по одному на 2 пикселя длины контрольной ломаной
+    let length = (p1 - p0).length() + (p2 - p1).length() + (p3 - p2).length();
+    let steps = (length / 2.0).ceil().clamp(1.0, 1000.0) as usize;
+    let h = 1.0 / steps as f32;
+
+    // коэффициенты многочлена a t^3 + b t^2 + c t + d
+    let a = -p0 + 3.0 * p1 - 3.0 * p2 + p3;
+    let b = 3.0 * p0 - 6.0 * p1 + 3.0 * p2;
+    let c = -3.0 * p0 + 3.0 * p1;
+
+    let mut point = p0;
+    let mut d1 = a * h * h * h + b * h * h + c * h;
+    let mut d2 = 6.0 * a * h * h * h + 2.0 * b * h * h;
+    let d3 = 6.0 * a * h * h * h;
+    for _ in 0..steps {
+        point += d1;
+        d1 += d2;
+        d2 += d3;
+        points.push(point.to_pos2());
+    }
+
+    // накопленная погрешность не должна сдвигать конец кривой
+    if let Some(last) = points.last_mut() {
+        *last = p3.to_pos2();
+    }
+}
+
+/// Кубические сегменты Безье, из которых состоит кривая.
+fn cubic_segments(kind: CurveKind, points: &[Pos2]) -> Vec<[Pos2; 4]> {
+    match kind {
+        CurveKind::Quadratic => {
+            let [p0, p1, p2] = points[..3] else {
+                return Vec::new();
+            };
+            // повышение степени: квадратичная кривая как кубическая
+            vec![[
+                p0,
+                p0 + (p1 - p0) * 2.0 / 3.0,
+                p2 + (p1 - p2) * 2.0 / 3.0,
+                p2,
+            ]]
+        }
+        CurveKind::Cubic => {
+            let [p0, p1, p2, p3] = points[..4] else {
+                return Vec::new();
+            };
+            vec![[p0, p1, p2, p3]]
+        }
+        CurveKind::CatmullRom => {
+            // сегмент между p1 и p2 с касательными (p2 - p0) / 2 и (p3 - p1) / 2,
+            // крайние точки дублируются
+            let n = points.len();
+            (0..n.saturating_sub(1))
+                .map(|i| {
+                    let p0 = points[i.saturating_sub(1)];
+                    let p1 = points[i];
+                    let p2 = points[i + 1];
+                    let p3 = points[(i + 2).min(n - 1)];
+                    [p1, p1 + (p2 - p0) / 6.0, p2 - (p3 - p1) / 6.0, p2]
+                })
+                .collect()
+        }
+    }
+}
+
+/// Разбиение кривой на ломаную.
+/// kind - вид кривой;
+/// points - контрольные точки;
+/// method - способ разбиения;
+pub fn flatten_curve(kind: CurveKind, points: &[Pos2], method: CurveMethod) -> Vec<Pos2> {
+    if points.len() < kind.control_points().unwrap_or(2) {
+        return Vec::new();
+    }
+
+    let mut polyline = vec![points[0]];
+    for segment in cubic_segments(kind, points) {
+        match method {
+            CurveMethod::Subdivision => subdivide_cubic(segment, 0, &mut polyline),
+            CurveMethod::ForwardDifferencing => forward_difference_cubic(segment, &mut polyline),
+        }
+    }
+    polyline
+}
+
+// =============== Ломаные и кривые ===============
+
+impl Canvas {
+    /// Рисование ломаной линии.
+    /// points - вершины ломаной;
+    /// color - цвет линии;
+    /// smooth - рисовать сглаженными отрезками (Ву) вместо Брезенхема;
+    pub fn draw_polyline(&mut self, points: &[Pos2], color: Color32, smooth: bool) {
+        for segment in points.windows(2) {
+            if segment[0] == segment[1] {
+                continue;
+            }
+            if smooth {
+                self.draw_smooth_line_simple(segment[0], segment[1], color);
+            } else {
+                self.draw_sharp_line(segment[0], segment[1], color);
+            }
+        }
+    }
+
+    /// Рисование кривой Безье или сплайна Катмулла-Рома.
+    /// kind - вид кривой;
+    /// points - контрольные точки;
+    /// color - цвет кривой;
+    /// method - способ разбиения кривой на отрезки;
+    /// smooth - рисовать сглаженными отрезками (Ву) вместо Брезенхема;
+    pub fn draw_curve(
+        &mut self,
+        kind: CurveKind,
+        points: &[Pos2],
+        color: Color32,
+        method: CurveMethod,
+        smooth: bool,
+    ) {
+        let polyline = flatten_curve(kind, points, method);
+        self.draw_polyline(&polyline, color, smooth);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METHODS: [CurveMethod; 2] = [CurveMethod::Subdivision, CurveMethod::ForwardDifferencing];
+
+    /// Точка кубической кривой Безье при параметре t.
+    fn cubic_at([p0, p1, p2, p3]: [Pos2; 4], t: f32) -> Pos2 {
+        let s = 1.0 - t;
+        let v = p0.to_vec2() * s * s * s
+            + p1.to_vec2() * 3.0 * s * s * t
+            + p2.to_vec2() * 3.0 * s * t * t
+            + p3.to_vec2() * t * t * t;
+        v.to_pos2()
+    }
+
+    #[test]
+    fn flattened_cubic_stays_on_the_curve() {
+        let control = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(30.0, 80.0),
+            Pos2::new(90.0, -40.0),
+            Pos2::new(120.0, 30.0),
+        ];
+        let samples: Vec<_> = (0..=2000)
+            .map(|i| cubic_at(control, i as f32 / 2000.0))
+            .collect();
+        for method in METHODS {
+            let polyline = flatten_curve(CurveKind::Cubic, &control, method);
+            assert_eq!(polyline.first(), Some(&control[0]));
+            assert_eq!(polyline.last(), Some(&control[3]));
+            assert!(polyline.len() > 4);
+            for p in polyline {
+                let distance = samples
+                    .iter()
+                    .map(|s| s.distance(p))
+                    .fold(f32::MAX, f32::min);
+                assert!(distance < 0.1);
+            }
+        }
+    }
+
+    #[test]
+    fn straight_cubic_is_one_segment() {
+        let control = [0.0, 10.0, 20.0, 30.0].map(|x| Pos2::new(x, x / 2.0));
+        let polyline = flatten_curve(CurveKind::Cubic, &control, CurveMethod::Subdivision);
+        assert_eq!(polyline, [control[0], control[3]]);
+    }
+
+    #[test]
+    fn quadratic_is_elevated_to_cubic() {
+        let [p0, p1, p2] = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(10.0, 20.0),
+            Pos2::new(20.0, 0.0),
+        ];
+        let segments = cubic_segments(CurveKind::Quadratic, &[p0, p1, p2]);
+        assert_eq!(segments.len(), 1);
+        // вершина параболы при t = 0.5
+        assert_eq!(cubic_at(segments[0], 0.5), Pos2::new(10.0, 10.0));
+    }
+
+    #[test]
+    fn catmull_rom_passes_through_all_points() {
+        let points = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(20.0, 15.0),
+            Pos2::new(40.0, -5.0),
+            Pos2::new(60.0, 25.0),
+            Pos2::new(70.0, 0.0),
+        ];
+        for method in METHODS {
+            let polyline = flatten_curve(CurveKind::CatmullRom, &points, method);
+            for p in points {
+                assert!(polyline.contains(&p));
+            }
+        }
+    }
+
+    #[test]
+    fn too_few_points_give_no_curve() {
+        let points = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(5.0, 5.0),
+            Pos2::new(9.0, 1.0),
+        ];
+        assert!(flatten_curve(CurveKind::Cubic, &points, CurveMethod::Subdivision).is_empty());
+        assert!(
+            flatten_curve(
+                CurveKind::CatmullRom,
+                &points[..1],
+                CurveMethod::Subdivision
+            )
+            .is_empty()
+        );
+        assert_eq!(
+            flatten_curve(
+                CurveKind::CatmullRom,
+                &points[..2],
+                CurveMethod::Subdivision
+            ),
+            points[..2]
+        );
+    }
+}
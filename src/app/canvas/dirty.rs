@@ -0,0 +1,250 @@
+use egui::{Color32, ColorImage};
+
+use super::Canvas;
+
+/// Прямоугольная область пикселей холста [x, x + width) x [y, y + height).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PixelRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl PixelRect {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Область из одного пикселя.
+    pub fn pixel(x: usize, y: usize) -> Self {
+        Self::new(x, y, 1, 1)
+    }
+
+    /// Область между двумя угловыми пикселями (оба входят в область).
+    pub fn from_corners(a: [usize; 2], b: [usize; 2]) -> Self {
+        let x = a[0].min(b[0]);
+        let y = a[1].min(b[1]);
+        Self::new(x, y, a[0].abs_diff(b[0]) + 1, a[1].abs_diff(b[1]) + 1)
+    }
+
+    /// Наименьший прямоугольник, содержащий обе области.
+    pub fn union(self, other: PixelRect) -> Self {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        Self::new(x, y, right - x, bottom - y)
+    }
+
+    /// Пересечение областей (None, если они не пересекаются).
+    pub fn intersect(self, other: PixelRect) -> Option<Self> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        (x < right && y < bottom).then(|| Self::new(x, y, right - x, bottom - y))
+    }
+
+    /// Содержит ли область пиксель (x, y).
+    #[inline]
+    fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height
+    }
+}
+
+// =============== Отслеживание изменённых областей холста ===============
+
+impl Canvas {
+    /// Весь холст как область.
+    pub fn bounds(&self) -> PixelRect {
+        PixelRect::new(0, 0, self.width, self.height)
+    }
+
+    /// Пометить область rect как изменённую (часть за пределами холста отбрасывается).
+    pub fn mark_dirty(&mut self, rect: PixelRect) {
+        let Some(rect) = rect.intersect(self.bounds()) else {
+            return;
+        };
+        self.dirty = Some(match self.dirty {
+            Some(dirty) => dirty.union(rect),
+            None => rect,
+        });
+    }
+
+    /// Пометить весь холст как изменённый.
+    pub fn mark_all_dirty(&mut self) {
+        self.dirty = (self.width > 0 && self.height > 0).then(|| self.bounds());
+    }
+
+    /// Пометить изменённым пиксель (x, y), лежащий в пределах холста.
+    #[inline]
+    pub(super) fn mark_pixel_dirty(&mut self, x: usize, y: usize) {
+        match &mut self.dirty {
+            Some(dirty) if dirty.contains(x, y) => {}
+            Some(dirty) => *dirty = dirty.union(PixelRect::pixel(x, y)),
+            None => self.dirty = Some(PixelRect::pixel(x, y)),
+        }
+    }
+
+    /// Область, изменённая с последнего вызова take_dirty.
+    pub fn dirty_rect(&self) -> Option<PixelRect> {
+        self.dirty
+    }
+
+    /// Забрать изменённую область, после чего холст считается неизменённым.
+    pub fn take_dirty(&mut self) -> Option<PixelRect> {
+        self.dirty.take()
+    }
+
+    /// Копия области rect в виде ColorImage (например, для TextureHandle::set_partial).
+    pub fn region_image(&self, rect: PixelRect) -> ColorImage {
+        let mut pixels = Vec::with_capacity(rect.width * rect.height);
+        for y in rect.y..rect.y + rect.height {
+            let row = y * self.width;
+            pixels.extend_from_slice(&self.pixels[row + rect.x..row + rect.x + rect.width]);
+        }
+        ColorImage::new([rect.width, rect.height], pixels)
+    }
+
+    /// Закрасить область rect цветом color.
+    pub fn fill_region(&mut self, rect: PixelRect, color: Color32) {
+        let Some(rect) = rect.intersect(self.bounds()) else {
+            return;
+        };
+        for y in rect.y..rect.y + rect.height {
+            let row = y * self.width;
+            self.pixels[row + rect.x..row + rect.x + rect.width].fill(color);
+        }
+        self.mark_dirty(rect);
+    }
+
+    /// Вставить холст source левым верхним углом в пиксель (x, y)
+    /// (часть за пределами холста отбрасывается).
+    pub fn paste(&mut self, source: &Canvas, x: usize, y: usize) {
+        let rect = PixelRect::new(x, y, source.width, source.height);
+        let Some(rect) = rect.intersect(self.bounds()) else {
+            return;
+        };
+        for row in 0..rect.height {
+            let target = (y + row) * self.width + x;
+            let from = row * source.width;
+            self.pixels[target..target + rect.width]
+                .copy_from_slice(&source.pixels[from..from + rect.width]);
+        }
+        self.mark_dirty(rect);
+    }
+
+    /// Наименьшая область, в которой холст отличается от холста other того же размера
+    /// (None, если холсты совпадают; весь холст, если размеры разные).
+    pub fn diff_rect(&self, other: &Canvas) -> Option<PixelRect> {
+        if self.size() != other.size() {
+            return Some(self.bounds());
+        }
+        let mut rect: Option<PixelRect> = None;
+        for y in 0..self.height {
+            let row = y * self.width..(y + 1) * self.width;
+            let (a, b) = (&self.pixels[row.clone()], &other.pixels[row]);
+            if a == b {
+                continue;
+            }
+            let differs = |(p, q): (&Color32, &Color32)| p != q;
+            let left = a.iter().zip(b).position(differs).unwrap_or(0);
+            let right = a.iter().zip(b).rposition(differs).unwrap_or(0);
+            let changed = PixelRect::new(left, y, right - left + 1, 1);
+            rect = Some(rect.map_or(changed, |rect| rect.union(changed)));
+        }
+        rect
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rect_union_and_intersection() {
+        let a = PixelRect::new(1, 2, 3, 4);
+        let b = PixelRect::new(3, 0, 5, 3);
+        assert_eq!(a.union(b), PixelRect::new(1, 0, 7, 6));
+        assert_eq!(a.intersect(b), Some(PixelRect::new(3, 2, 1, 1)));
+        // касающиеся области не пересекаются
+        assert_eq!(a.intersect(PixelRect::new(4, 2, 2, 2)), None);
+        assert_eq!(
+            PixelRect::from_corners([5, 1], [2, 3]),
+            PixelRect::new(2, 1, 4, 3)
+        );
+    }
+
+    #[test]
+    fn new_canvas_is_dirty_until_taken() {
+        let mut canvas = Canvas::new(5, 3);
+        assert_eq!(canvas.take_dirty(), Some(PixelRect::new(0, 0, 5, 3)));
+        assert_eq!(canvas.take_dirty(), None);
+        assert_eq!(Canvas::new(0, 3).dirty_rect(), None);
+    }
+
+    #[test]
+    fn changed_pixels_grow_the_dirty_rect() {
+        let mut canvas = Canvas::new(10, 10);
+        canvas.take_dirty();
+        canvas[(2, 3)] = Color32::RED;
+        assert_eq!(canvas.dirty_rect(), Some(PixelRect::pixel(2, 3)));
+        canvas[(6, 1)] = Color32::RED;
+        assert_eq!(canvas.take_dirty(), Some(PixelRect::new(2, 1, 5, 3)));
+
+        // чтение пикселя не помечает его изменённым
+        let _ = canvas[(4, 4)];
+        assert_eq!(canvas.dirty_rect(), None);
+    }
+
+    #[test]
+    fn drawing_marks_only_touched_area() {
+        let mut canvas = Canvas::new(10, 10);
+        canvas.take_dirty();
+        canvas.fill_row(4, -3, 2, Color32::RED);
+        assert_eq!(canvas.take_dirty(), Some(PixelRect::new(0, 4, 3, 1)));
+
+        canvas.fill_region(PixelRect::new(8, 8, 5, 5), Color32::RED);
+        assert_eq!(canvas.take_dirty(), Some(PixelRect::new(8, 8, 2, 2)));
+
+        canvas.mark_dirty(PixelRect::new(20, 20, 2, 2));
+        assert_eq!(canvas.dirty_rect(), None);
+    }
+
+    #[test]
+    fn region_image_copies_the_rect() {
+        let mut canvas = Canvas::new(4, 4);
+        canvas[(2, 1)] = Color32::RED;
+        let image = canvas.region_image(PixelRect::new(1, 1, 2, 3));
+        assert_eq!(image.size, [2, 3]);
+        assert_eq!(image[(1, 0)], Color32::RED);
+        assert_eq!(image[(0, 2)], Color32::WHITE);
+    }
+
+    #[test]
+    fn paste_and_diff_rect() {
+        let mut canvas = Canvas::new(6, 6);
+        let original = canvas.clone();
+        assert_eq!(canvas.diff_rect(&original), None);
+
+        // вставка у края обрезается
+        canvas.paste(&Canvas::new_filled(3, 2, Color32::RED), 4, 1);
+        assert_eq!(canvas[(5, 2)], Color32::RED);
+        assert_eq!(canvas[(3, 1)], Color32::WHITE);
+        assert_eq!(
+            canvas.diff_rect(&original),
+            Some(PixelRect::new(4, 1, 2, 2))
+        );
+
+        assert_eq!(
+            canvas.diff_rect(&Canvas::new(2, 2)),
+            Some(PixelRect::new(0, 0, 6, 6))
+        );
+    }
+}
@@ -0,0 +1,347 @@
+use egui::Color32;
+
+use super::{Canvas, EdgeMode, Kernel};
+
+#[derive(Default, PartialEq, Clone, Copy)]
+/// Оператор вычисления градиента яркости.
+pub enum EdgeOperator {
+    #[default]
+    /// Сглаживание весами 1, 2, 1
+    Sobel,
+    /// Сглаживание весами 1, 1, 1
+    Prewitt,
+    /// Сглаживание весами 3, 10, 3 (лучшая изотропность)
+    Scharr,
+}
+
+impl EdgeOperator {
+    pub const ALL: [EdgeOperator; 3] = [
+        EdgeOperator::Sobel,
+        EdgeOperator::Prewitt,
+        EdgeOperator::Scharr,
+    ];
+
+    pub fn get_name(&self) -> String {
+        match self {
+            EdgeOperator::Sobel => String::from("Собель"),
+            EdgeOperator::Prewitt => String::from("Прюитт"),
+            EdgeOperator::Scharr => String::from("Щарр"),
+        }
+    }
+
+    /// Ядра производных по x и по y. Сглаживающая часть нормирована,
+    /// поэтому перепад яркости на 255 даёт производную 255 у всех операторов.
+    pub fn kernels(&self) -> (Kernel, Kernel) {
+        let smoothing = match self {
+            EdgeOperator::Sobel => [1.0, 2.0, 1.0],
+            EdgeOperator::Prewitt => [1.0, 1.0, 1.0],
+            EdgeOperator::Scharr => [3.0, 10.0, 3.0],
+        };
+        let sum: f32 = smoothing.iter().sum();
+        let smoothing = smoothing.map(|w| w / sum).to_vec();
+        let derivative = vec![-1.0, 0.0, 1.0];
+        (
+            Kernel::separable(smoothing.clone(), derivative.clone()),
+            Kernel::separable(derivative, smoothing),
+        )
+    }
+}
+
+/// Пиксель карты величин: оттенок серого.
+fn gray(value: f32) -> Color32 {
+    Color32::from_gray(value.round().clamp(0.0, 255.0) as u8)
+}
+
+/// Пиксель бинарной карты: граница белая, фон чёрный.
+fn binary(is_edge: bool) -> Color32 {
+    if is_edge {
+        Color32::WHITE
+    } else {
+        Color32::BLACK
+    }
+}
+
+/// Одномерное ядро Гаусса и его вторая производная (с нулевой суммой).
+fn gaussian_lines(sigma: f32) -> (Vec<f32>, Vec<f32>) {
+    let sigma = sigma.max(0.3);
+    let radius = (4.0 * sigma).ceil() as i64;
+    let variance = sigma * sigma;
+    let mut line: Vec<f32> = (-radius..=radius)
+        .map(|x| (-(x * x) as f32 / (2.0 * variance)).exp())
+        .collect();
+    let sum: f32 = line.iter().sum();
+    line.iter_mut().for_each(|w| *w /= sum);
+
+    // умножение на sigma^2 делает отклик независимым от масштаба
+    let mut second: Vec<f32> = (-radius..=radius)
+        .zip(&line)
+        .map(|(x, &g)| g * ((x * x) as f32 - variance) / variance)
+        .collect();
+    let mean = second.iter().sum::<f32>() / second.len() as f32;
+    second.iter_mut().for_each(|w| *w -= mean);
+    (line, second)
+}
+
+// =============== Выделение границ ===============
+
+impl Canvas {
+    /// Яркость пикселей (веса BT.601) в первом канале; прозрачные пиксели чёрные.
+    fn luminance_values(&self) -> Vec<[f32; 4]> {
+        self.pixels
+            .iter()
+            .map(|color| {
+                let [r, g, b, _] = color.to_array().map(|c| c as f32);
+                [0.299 * r + 0.587 * g + 0.114 * b, 0.0, 0.0, 0.0]
+            })
+            .collect()
+    }
+
+    /// Производные яркости values по x и по y.
+    fn gradient(&self, values: &[[f32; 4]], operator: EdgeOperator) -> (Vec<f32>, Vec<f32>) {
+        let (kernel_x, kernel_y) = operator.kernels();
+        let first = |channels: Vec<[f32; 4]>| channels.iter().map(|c| c[0]).collect();
+        (
+            first(self.convolve_values(values, &kernel_x, EdgeMode::Clamp)),
+            first(self.convolve_values(values, &kernel_y, EdgeMode::Clamp)),
+        )
+    }
+
+    /// Модуль градиента яркости, вычисленного оператором operator.
+    /// Без порога threshold результат - карта величин в оттенках серого,
+    /// с порогом - бинарная карта (белые пиксели с модулем не меньше порога на чёрном).
+    pub fn edge_magnitude(&self, operator: EdgeOperator, threshold: Option<f32>) -> Canvas {
+        let (gx, gy) = self.gradient(&self.luminance_values(), operator);
+        let pixels = gx
+            .iter()
+            .zip(&gy)
+            .map(|(x, y)| {
+                let magnitude = x.hypot(*y);
+                match threshold {
+                    Some(threshold) => binary(magnitude >= threshold),
+                    None => gray(magnitude),
+                }
+            })
+            .collect();
+        Canvas::from_pixels(self.width, self.height, pixels)
+    }
+
+    /// Лапласиан гауссианы со стандартным отклонением sigma.
+    /// Без порога threshold результат - модуль отклика, растянутый до [0, 255];
+    /// с порогом - бинарная карта переходов отклика через ноль, перепад на которых
+    /// не меньше threshold (в долях 1/255 наибольшего перепада).
+    pub fn laplacian_of_gaussian(&self, sigma: f32, threshold: Option<f32>) -> Canvas {
+        let (line, second) = gaussian_lines(sigma);
+        let values = self.luminance_values();
+        // ядро разделяется на сумму двух разделимых: G''(x) G(y) + G(x) G''(y)
+        let along_x = Kernel::separable(line.clone(), second.clone());
+        let along_y = Kernel::separable(second, line);
+        let response: Vec<f32> = self
+            .convolve_values(&values, &along_x, EdgeMode::Clamp)
+            .iter()
+            .zip(self.convolve_values(&values, &along_y, EdgeMode::Clamp))
+            .map(|(x, y)| x[0] + y[0])
+            .collect();
+
+        let pixels = match threshold {
+            None => {
+                let max = response.iter().fold(0.0f32, |max, r| max.max(r.abs()));
+                let scale = if max > 1e-6 { 255.0 / max } else { 0.0 };
+                response.iter().map(|r| gray(r.abs() * scale)).collect()
+            }
+            Some(threshold) => self.zero_crossings(&response, threshold),
+        };
+        Canvas::from_pixels(self.width, self.height, pixels)
+    }
+
+    /// Бинарная карта переходов через ноль между соседями по строке и столбцу.
+    /// Отмечается пиксель с меньшим модулем отклика, чтобы линии были толщиной в пиксель.
+    fn zero_crossings(&self, response: &[f32], threshold: f32) -> Vec<Color32> {
+        let (width, height) = (self.width, self.height);
+        let mut crossings = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                let index = y * width + x;
+                let neighbors = [
+                    (x + 1 < width).then_some(index + 1),
+                    (y + 1 < height).then_some(index + width),
+                ];
+                for neighbor in neighbors.into_iter().flatten() {
+                    let (a, b) = (response[index], response[neighbor]);
+                    if a * b < 0.0 {
+                        let closer = if a.abs() <= b.abs() { index } else { neighbor };
+                        crossings.push((closer, (a - b).abs()));
+                    }
+                }
+            }
+        }
+
+        let max = crossings.iter().fold(0.0f32, |max, c| max.max(c.1));
+        let scale = if max > 1e-6 { 255.0 / max } else { 0.0 };
+        let mut edges = vec![false; width * height];
+        for (index, slope) in crossings {
+            if slope * scale >= threshold {
+                edges[index] = true;
+            }
+        }
+        edges.into_iter().map(binary).collect()
+    }
+
+    /// Детектор границ Кэнни: размытие по Гауссу (sigma), градиент оператором Собеля,
+    /// подавление немаксимумов вдоль градиента и двойной порог с гистерезисом:
+    /// пиксели с модулем не меньше high - границы, не меньше low - границы,
+    /// если связаны с сильными. Результат - бинарная карта с линиями толщиной в пиксель.
+    pub fn canny(&self, sigma: f32, low: f32, high: f32) -> Canvas {
+        let (width, height) = (self.width, self.height);
+        let mut values = self.luminance_values();
+        if sigma > 0.0 {
+            values = self.convolve_values(&values, &Kernel::gaussian(sigma), EdgeMode::Clamp);
+        }
+        let (gx, gy) = self.gradient(&values, EdgeOperator::Sobel);
+        let magnitude: Vec<f32> = gx.iter().zip(&gy).map(|(x, y)| x.hypot(*y)).collect();
+        let at = |x: i64, y: i64| -> f32 {
+            if x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+                0.0
+            } else {
+                magnitude[y as usize * width + x as usize]
+            }
+        };
+
+        // подавление немаксимумов: направление градиента округляется до 45 градусов
+        let mut thin = vec![0.0; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                let index = y * width + x;
+                let value = magnitude[index];
+                if value < low || value == 0.0 {
+                    continue;
+                }
+                let angle = gy[index].atan2(gx[index]).to_degrees().rem_euclid(180.0);
+                let (dx, dy) = match angle {
+                    a if !(22.5..157.5).contains(&a) => (1, 0),
+                    a if a < 67.5 => (1, 1),
+                    a if a < 112.5 => (0, 1),
+                    _ => (-1, 1),
+                };
+                let (x, y) = (x as i64, y as i64);
+                // нестрогое сравнение с одной стороны оставляет одну линию на плато
+                if value > at(x - dx, y - dy) && value >= at(x + dx, y + dy) {
+                    thin[index] = value;
+                }
+            }
+        }
+
+        // гистерезис: слабые пиксели присоединяются к сильным через 8-связность
+        let mut edges = vec![false; width * height];
+        let mut stack: Vec<usize> = (0..thin.len())
+            .filter(|&i| thin[i] > 0.0 && thin[i] >= high)
+            .collect();
+        for &index in &stack {
+            edges[index] = true;
+        }
+        while let Some(index) = stack.pop() {
+            let (x, y) = ((index % width) as i64, (index / width) as i64);
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let (nx, ny) = (x + dx, y + dy);
+                    if nx < 0 || ny < 0 || nx >= width as i64 || ny >= height as i64 {
+                        continue;
+                    }
+                    let neighbor = ny as usize * width + nx as usize;
+                    if !edges[neighbor] && thin[neighbor] >= low && thin[neighbor] > 0.0 {
+                        edges[neighbor] = true;
+                        stack.push(neighbor);
+                    }
+                }
+            }
+        }
+
+        let pixels = edges.into_iter().map(binary).collect();
+        Canvas::from_pixels(width, height, pixels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Холст 12x8: левая половина чёрная, правая белая.
+    fn step() -> Canvas {
+        let pixels = (0..96)
+            .map(|i| {
+                if i % 12 < 6 {
+                    Color32::BLACK
+                } else {
+                    Color32::WHITE
+                }
+            })
+            .collect();
+        Canvas::from_pixels(12, 8, pixels)
+    }
+
+    /// Номера белых пикселей строки y.
+    fn white_in_row(canvas: &Canvas, y: usize) -> Vec<usize> {
+        let [width, _] = canvas.size();
+        (0..width)
+            .filter(|&x| canvas[(x, y)] == Color32::WHITE)
+            .collect()
+    }
+
+    #[test]
+    fn uniform_image_has_no_edges() {
+        let canvas = Canvas::new_filled(6, 6, Color32::from_rgb(40, 90, 200));
+        for operator in EdgeOperator::ALL {
+            let edges = canvas.edge_magnitude(operator, None);
+            assert!(edges.pixels.iter().all(|&c| c == Color32::BLACK));
+        }
+        let log = canvas.laplacian_of_gaussian(1.0, Some(10.0));
+        assert!(log.pixels.iter().all(|&c| c == Color32::BLACK));
+        let canny = canvas.canny(1.0, 20.0, 60.0);
+        assert!(canny.pixels.iter().all(|&c| c == Color32::BLACK));
+    }
+
+    #[test]
+    fn step_gives_full_magnitude_for_every_operator() {
+        let canvas = step();
+        for operator in EdgeOperator::ALL {
+            let edges = canvas.edge_magnitude(operator, None);
+            for y in 0..8 {
+                assert_eq!(edges[(5, y)].r(), 255);
+                assert_eq!(edges[(6, y)].r(), 255);
+                assert_eq!(edges[(2, y)], Color32::BLACK);
+                assert_eq!(edges[(9, y)], Color32::BLACK);
+            }
+            let binary = canvas.edge_magnitude(operator, Some(100.0));
+            assert_eq!(white_in_row(&binary, 3), [5, 6]);
+        }
+    }
+
+    #[test]
+    fn canny_line_is_one_pixel_wide() {
+        let canny = step().canny(1.0, 20.0, 60.0);
+        for y in 0..8 {
+            let row = white_in_row(&canny, y);
+            assert_eq!(row.len(), 1);
+            assert!((5..=6).contains(&row[0]));
+        }
+    }
+
+    #[test]
+    fn laplacian_zero_crossing_follows_the_step() {
+        let log = step().laplacian_of_gaussian(1.0, Some(50.0));
+        for y in 0..8 {
+            let row = white_in_row(&log, y);
+            assert_eq!(row.len(), 1);
+            assert!((5..=6).contains(&row[0]));
+        }
+    }
+
+    #[test]
+    fn second_derivative_kernel_sums_to_zero() {
+        for sigma in [0.5, 1.0, 2.5] {
+            let (line, second) = gaussian_lines(sigma);
+            assert_eq!(line.len(), second.len());
+            assert!((line.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+            assert!(second.iter().sum::<f32>().abs() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use egui::Color32;
+
+use super::{Canvas, GrayscaleMethod, PixelRect};
+
+/// Гистограммы каналов R, G, B и яркости (BT.601) по непрозрачным пикселям.
+/// Цвета учитываются без премультипликации.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Histogram {
+    pub red: [u32; 256],
+    pub green: [u32; 256],
+    pub blue: [u32; 256],
+    pub luminance: [u32; 256],
+    /// Число учтённых пикселей
+    pub count: u32,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            red: [0; 256],
+            green: [0; 256],
+            blue: [0; 256],
+            luminance: [0; 256],
+            count: 0,
+        }
+    }
+}
+
+impl Histogram {
+    /// Среднее значение по гистограмме bins.
+    pub fn mean(&self, bins: &[u32; 256]) -> f32 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let sum: u64 = bins
+            .iter()
+            .enumerate()
+            .map(|(v, &n)| v as u64 * n as u64)
+            .sum();
+        sum as f32 / self.count as f32
+    }
+
+    /// Медиана по гистограмме bins.
+    pub fn median(&self, bins: &[u32; 256]) -> u8 {
+        let half = self.count.div_ceil(2);
+        let mut total = 0;
+        for (v, &n) in bins.iter().enumerate() {
+            total += n;
+            if total >= half && total > 0 {
+                return v as u8;
+            }
+        }
+        0
+    }
+}
+
+/// Яркость пикселя (BT.601) без премультипликации.
+fn luminance(color: Color32) -> u8 {
+    let [r, g, b, _] = color.to_srgba_unmultiplied();
+    let gray = GrayscaleMethod::Luma601.gray([r, g, b].map(f32::from));
+    gray.round().clamp(0.0, 255.0) as u8
+}
+
+/// Таблица выравнивания гистограммы bins с числом пикселей count:
+/// значения распределяются по [0, 255] пропорционально накопленной частоте.
+fn equalization_lut(bins: &[u32; 256], count: u32) -> [f32; 256] {
+    let mut lut = [0.0; 256];
+    let first = bins.iter().copied().find(|&n| n > 0).unwrap_or(0);
+    let range = count.saturating_sub(first);
+    let mut total = 0;
+    for (v, &n) in bins.iter().enumerate() {
+        total += n;
+        lut[v] = if range == 0 {
+            v as f32
+        } else {
+            total.saturating_sub(first) as f32 / range as f32 * 255.0
+        };
+    }
+    lut
+}
+
+// =============== Гистограмма и выравнивание ===============
+
+impl Canvas {
+    /// Гистограмма пикселей области rect (всего холста, если rect - None).
+    /// Полностью прозрачные пиксели не учитываются.
+    pub fn histogram(&self, rect: Option<PixelRect>) -> Histogram {
+        let mut histogram = Histogram::default();
+        let Some(rect) = rect.unwrap_or(self.bounds()).intersect(self.bounds()) else {
+            return histogram;
+        };
+        for y in rect.y..rect.y + rect.height {
+            for &color in
+                &self.pixels[y * self.width + rect.x..y * self.width + rect.x + rect.width]
+            {
+                if color.a() == 0 {
+                    continue;
+                }
+                let [r, g, b, _] = color.to_srgba_unmultiplied();
+                histogram.red[r as usize] += 1;
+                histogram.green[g as usize] += 1;
+                histogram.blue[b as usize] += 1;
+                histogram.luminance[luminance(color) as usize] += 1;
+                histogram.count += 1;
+            }
+        }
+        histogram
+    }
+
+    /// Замена яркости каждого пикселя на map(номер пикселя, яркость).
+    /// Цветоразностные компоненты (Cb, Cr) сохраняются: ко всем каналам
+    /// добавляется изменение яркости. Прозрачность сохраняется.
+    fn map_luminance(&self, map: impl Fn(usize, u8) -> f32) -> Canvas {
+        let pixels = self
+            .pixels
+            .iter()
+            .enumerate()
+            .map(|(i, &color)| {
+                if color.a() == 0 {
+                    return color;
+                }
+                let y = luminance(color);
+                let delta = map(i, y) - y as f32;
+                let [r, g, b, a] = color.to_srgba_unmultiplied();
+                let [r, g, b] =
+                    [r, g, b].map(|c| (c as f32 + delta).round().clamp(0.0, 255.0) as u8);
+                Color32::from_rgba_unmultiplied(r, g, b, a)
+            })
+            .collect();
+        Canvas::from_pixels(self.width, self.height, pixels)
+    }
+
+    /// Глобальное выравнивание гистограммы яркости.
+    pub fn equalize(&self) -> Canvas {
+        let histogram = self.histogram(None);
+        let lut = equalization_lut(&histogram.luminance, histogram.count);
+        self.map_luminance(|_, y| lut[y as usize])
+    }
+
+    /// Адаптивное выравнивание гистограммы с ограничением контраста (CLAHE).
+    /// Изображение делится на tiles x tiles блоков, гистограмма каждого блока
+    /// обрезается на уровне clip_limit средних значений столбца (избыток
+    /// распределяется поровну) и выравнивается; таблицы соседних блоков
+    /// билинейно интерполируются между их центрами.
+    pub fn clahe(&self, tiles: usize, clip_limit: f32) -> Canvas {
+        let (width, height) = (self.width, self.height);
+        if width == 0 || height == 0 {
+            return self.clone();
+        }
+        let tiles_x = tiles.clamp(1, width);
+        let tiles_y = tiles.clamp(1, height);
+        let tile_x = |i: usize| i * width / tiles_x;
+        let tile_y = |j: usize| j * height / tiles_y;
+
+        let mut luts = Vec::with_capacity(tiles_x * tiles_y);
+        for j in 0..tiles_y {
+            for i in 0..tiles_x {
+                let rect = PixelRect::new(
+                    tile_x(i),
+                    tile_y(j),
+                    tile_x(i + 1) - tile_x(i),
+                    tile_y(j + 1) - tile_y(j),
+                );
+                let histogram = self.histogram(Some(rect));
+                let mut bins = histogram.luminance;
+                if clip_limit > 0.0 {
+                    let limit = ((clip_limit * histogram.count as f32 / 256.0) as u32).max(1);
+                    let mut excess = 0;
+                    for n in &mut bins {
+                        excess += n.saturating_sub(limit);
+                        *n = (*n).min(limit);
+                    }
+                    let (share, rest) = (excess / 256, excess % 256);
+                    bins.iter_mut().for_each(|n| *n += share);
+                    // остаток - по одному в равномерно расставленные столбцы
+                    for k in 0..rest {
+                        bins[(k * 256 / rest) as usize] += 1;
+                    }
+                }
+                luts.push(equalization_lut(&bins, histogram.count));
+            }
+        }
+
+        // номера соседних блоков и доля второго для координаты пикселя
+        let neighbors = |coord: usize, size: usize, count: usize| {
+            let position = ((coord as f32 + 0.5) * count as f32 / size as f32 - 0.5)
+                .clamp(0.0, (count - 1) as f32);
+            let first = position.floor() as usize;
+            ((first, (first + 1).min(count - 1)), position - first as f32)
+        };
+        self.map_luminance(|index, y| {
+            let ((x0, x1), fx) = neighbors(index % width, width, tiles_x);
+            let ((y0, y1), fy) = neighbors(index / width, height, tiles_y);
+            let value = |i: usize, j: usize| luts[j * tiles_x + i][y as usize];
+            let top = value(x0, y0) * (1.0 - fx) + value(x1, y0) * fx;
+            let bottom = value(x0, y1) * (1.0 - fx) + value(x1, y1) * fx;
+            top * (1.0 - fy) + bottom * fy
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray(v: u8) -> Color32 {
+        Color32::from_gray(v)
+    }
+
+    /// Холст 8x8: левая половина уровня low, правая - high.
+    fn two_levels(low: u8, high: u8) -> Canvas {
+        let pixels = (0..64)
+            .map(|i| if i % 8 < 4 { gray(low) } else { gray(high) })
+            .collect();
+        Canvas::from_pixels(8, 8, pixels)
+    }
+
+    #[test]
+    fn uniform_image_is_unchanged() {
+        let canvas = Canvas::new_filled(5, 4, Color32::from_rgb(90, 120, 40));
+        assert!(canvas.equalize().pixels == canvas.pixels);
+        assert!(canvas.clahe(2, 0.0).pixels == canvas.pixels);
+
+        let histogram = canvas.histogram(None);
+        let lut = equalization_lut(&histogram.luminance, histogram.count);
+        assert!(lut.iter().enumerate().all(|(v, &l)| l == v as f32));
+    }
+
+    #[test]
+    fn two_level_image_equalizes_to_black_and_white() {
+        let equalized = two_levels(50, 200).equalize();
+        assert_eq!(equalized[(0, 0)], gray(0));
+        assert_eq!(equalized[(7, 7)], gray(255));
+
+        let histogram = two_levels(50, 200).histogram(None);
+        let lut = equalization_lut(&histogram.luminance, histogram.count);
+        assert_eq!((lut[50], lut[200]), (0.0, 255.0));
+    }
+
+    #[test]
+    fn single_tile_clahe_without_clipping_matches_equalize() {
+        let pixels = (0..96u32).map(|i| gray((i * 37 % 256) as u8)).collect();
+        let canvas = Canvas::from_pixels(12, 8, pixels);
+        assert!(canvas.clahe(1, 0.0).pixels == canvas.equalize().pixels);
+
+        let canvas = two_levels(30, 90);
+        assert!(canvas.clahe(1, 0.0).pixels == canvas.equalize().pixels);
+    }
+
+    #[test]
+    fn transparent_pixels_are_ignored() {
+        let mut canvas = two_levels(50, 200);
+        canvas[(0, 0)] = Color32::TRANSPARENT;
+        let histogram = canvas.histogram(None);
+        assert_eq!(histogram.count, 63);
+        assert_eq!(canvas.equalize()[(0, 0)], Color32::TRANSPARENT);
+    }
+}
@@ -0,0 +1,155 @@
+use egui::Color32;
+use image::{DynamicImage, ImageBuffer, Rgba, RgbaImage};
+
+use super::Canvas;
+
+/// Холст в виде картинки крейта image без копирования пикселей.
+/// Цвета в ней премультиплицированы альфой, как в Color32.
+pub type CanvasView<'a> = ImageBuffer<Rgba<u8>, &'a [u8]>;
+
+// =============== Обмен данными с крейтом image ===============
+
+impl Canvas {
+    /// Холст из готовых пикселей (с премультиплицированной альфой), без копирования.
+    /// Пиксели перечисляются по строкам, их число должно быть равно width * height.
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Color32>) -> Self {
+        assert_eq!(
+            pixels.len(),
+            width * height,
+            "число пикселей не совпадает с размером"
+        );
+        let mut canvas = Self {
+            pixels,
+            width,
+            height,
+            dirty: None,
+        };
+        canvas.mark_all_dirty();
+        canvas
+    }
+
+    /// Холст из картинки RGBA без премультипликации (так хранят цвета файлы изображений).
+    pub fn from_rgba_image(image: &RgbaImage) -> Self {
+        let pixels = image
+            .as_raw()
+            .chunks_exact(4)
+            .map(|p| Color32::from_rgba_unmultiplied(p[0], p[1], p[2], p[3]))
+            .collect();
+        Self::from_pixels(image.width() as usize, image.height() as usize, pixels)
+    }
+
+    /// Картинка RGBA без премультипликации (например, для сохранения в файл).
+    pub fn to_rgba_image(&self) -> RgbaImage {
+        let bytes = self
+            .pixels
+            .iter()
+            .flat_map(|color| color.to_srgba_unmultiplied())
+            .collect();
+        RgbaImage::from_raw(self.width as u32, self.height as u32, bytes)
+            .expect("размер буфера совпадает с размером холста")
+    }
+
+    /// Пиксели холста по строкам.
+    pub fn pixels(&self) -> &[Color32] {
+        &self.pixels
+    }
+
+    /// Изменяемые пиксели холста по строкам, весь холст помечается изменённым.
+    pub fn pixels_mut(&mut self) -> &mut [Color32] {
+        self.mark_all_dirty();
+        &mut self.pixels
+    }
+
+    /// Байты RGBA (с премультиплицированной альфой) без копирования.
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.pixels)
+    }
+
+    /// Холст в виде картинки крейта image без копирования.
+    pub fn as_image_view(&self) -> CanvasView<'_> {
+        ImageBuffer::from_raw(self.width as u32, self.height as u32, self.as_bytes())
+            .expect("размер буфера совпадает с размером холста")
+    }
+}
+
+impl From<&RgbaImage> for Canvas {
+    fn from(image: &RgbaImage) -> Self {
+        Self::from_rgba_image(image)
+    }
+}
+
+impl From<&DynamicImage> for Canvas {
+    /// Картинки RGBA8 читаются напрямую, остальные форматы сначала преобразуются в RGBA8.
+    fn from(image: &DynamicImage) -> Self {
+        match image.as_rgba8() {
+            Some(rgba) => Self::from_rgba_image(rgba),
+            None => Self::from_rgba_image(&image.to_rgba8()),
+        }
+    }
+}
+
+impl From<&Canvas> for RgbaImage {
+    fn from(canvas: &Canvas) -> Self {
+        canvas.to_rgba_image()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+
+    #[test]
+    fn rgba_image_round_trip() {
+        let mut image = RgbaImage::new(3, 2);
+        image.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        image.put_pixel(2, 1, Rgba([10, 20, 30, 0]));
+        image.put_pixel(1, 1, Rgba([200, 100, 50, 255]));
+
+        let canvas = Canvas::from(&image);
+        assert_eq!(canvas.size(), [3, 2]);
+        assert_eq!(canvas[(0, 0)], Color32::RED);
+        assert_eq!(canvas[(2, 1)], Color32::TRANSPARENT);
+        // цвет полностью прозрачного пикселя не сохраняется
+        let back = RgbaImage::from(&canvas);
+        assert_eq!(back.get_pixel(1, 1), image.get_pixel(1, 1));
+        assert_eq!(back.get_pixel(2, 1)[3], 0);
+    }
+
+    #[test]
+    fn semi_transparent_colors_are_premultiplied() {
+        let image = RgbaImage::from_pixel(1, 1, Rgba([255, 128, 0, 128]));
+        let canvas = Canvas::from(&image);
+        assert_eq!(canvas[(0, 0)].to_array(), [128, 64, 0, 128]);
+        assert_eq!(canvas.as_bytes(), &[128, 64, 0, 128]);
+        assert_eq!(
+            *canvas.as_image_view().get_pixel(0, 0),
+            Rgba([128, 64, 0, 128])
+        );
+    }
+
+    #[test]
+    fn rgb_images_become_opaque() {
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(2, 2, Rgb([1, 2, 3])));
+        let canvas = Canvas::from(&image);
+        assert_eq!(canvas[(1, 1)], Color32::from_rgb(1, 2, 3));
+    }
+
+    #[test]
+    fn views_share_pixels() {
+        let mut canvas = Canvas::new(2, 2);
+        canvas.take_dirty();
+        canvas.pixels_mut()[3] = Color32::BLACK;
+        assert_eq!(canvas[(1, 1)], Color32::BLACK);
+        assert_eq!(canvas.take_dirty(), Some(canvas.bounds()));
+        assert_eq!(canvas.pixels().len(), 4);
+        assert_eq!(canvas.as_image_view().dimensions(), (2, 2));
+    }
+
+    #[test]
+    #[should_panic]
+    fn wrong_pixel_count_is_rejected() {
+        Canvas::from_pixels(2, 2, vec![Color32::WHITE; 3]);
+    }
+}
@@ -0,0 +1,347 @@
+use egui::{Color32, Pos2};
+
+use super::{Canvas, PixelRect};
+
+#[derive(Default, PartialEq, Clone, Copy, serde::Serialize, serde::Deserialize)]
+/// Правило определения внутренних точек многоугольника.
+pub enum FillRule {
+    #[default]
+    /// Точка внутри, если луч из неё пересекает нечётное число рёбер
+    EvenOdd,
+    /// Точка внутри, если число оборотов контура вокруг неё не равно нулю
+    NonZero,
+}
+
+impl FillRule {
+    pub fn get_name(&self) -> String {
+        match self {
+            FillRule::EvenOdd => String::from("чётно-нечётное"),
+            FillRule::NonZero => String::from("ненулевое"),
+        }
+    }
+}
+
+/// Ребро многоугольника для построчной растеризации.
+struct Edge {
+    /// Первая строка, которую пересекает ребро
+    first_row: i32,
+    /// Строка после последней, которую пересекает ребро
+    end_row: i32,
+    /// x пересечения с центром текущей строки
+    x: f32,
+    /// Приращение x при переходе на следующую строку
+    dx: f32,
+    /// Цвет в точке пересечения с текущей строкой (rgba)
+    color: [f32; 4],
+    /// Приращение цвета при переходе на следующую строку
+    dcolor: [f32; 4],
+    /// Направление ребра: +1 вниз, -1 вверх
+    winding: i32,
+}
+
+impl Edge {
+    /// Переместить точку пересечения ребра на rows строк вниз.
+    fn step(&mut self, rows: i32) {
+        self.x += self.dx * rows as f32;
+        for i in 0..4 {
+            self.color[i] += self.dcolor[i] * rows as f32;
+        }
+    }
+}
+
+fn color_to_array(color: Color32) -> [f32; 4] {
+    [
+        color.r() as f32,
+        color.g() as f32,
+        color.b() as f32,
+        color.a() as f32,
+    ]
+}
+
+fn array_to_color(color: [f32; 4]) -> Color32 {
+    let channel = |value: f32| value.round().clamp(0.0, 255.0) as u8;
+    Color32::from_rgba_premultiplied(
+        channel(color[0]),
+        channel(color[1]),
+        channel(color[2]),
+        channel(color[3]),
+    )
+}
+
+// =============== Построчная заливка многоугольника ===============
+
+impl Canvas {
+    /// Заливка многоугольника одним цветом.
+    /// contours - контуры многоугольника (каждый автоматически замыкается);
+    /// rule - правило заливки;
+    /// color - цвет заливки;
+    pub fn fill_polygon(&mut self, contours: &[Vec<Pos2>], rule: FillRule, color: Color32) {
+        self.rasterize_polygon(contours, None, rule, color);
+    }
+
+    /// Заливка многоугольника с интерполяцией цветов вершин (по Гуро).
+    /// contours - контуры многоугольника (каждый автоматически замыкается);
+    /// colors - цвета вершин, по одному на каждую точку контуров;
+    /// rule - правило заливки;
+    pub fn fill_gradient_polygon(
+        &mut self,
+        contours: &[Vec<Pos2>],
+        colors: &[Vec<Color32>],
+        rule: FillRule,
+    ) {
+        self.rasterize_polygon(contours, Some(colors), rule, Color32::TRANSPARENT);
+    }
+
+    /// Построение таблицы рёбер, отсортированной по первой строке.
+    fn build_edge_table(contours: &[Vec<Pos2>], colors: Option<&[Vec<Color32>]>) -> Vec<Edge> {
+        let mut edges = Vec::new();
+
+        for (contour_index, contour) in contours.iter().enumerate() {
+            for i in 0..contour.len() {
+                let j = (i + 1) % contour.len();
+                let (mut top, mut bottom) = (contour[i], contour[j]);
+                let (mut top_color, mut bottom_color) = match colors {
+                    Some(colors) => (
+                        color_to_array(colors[contour_index][i]),
+                        color_to_array(colors[contour_index][j]),
+                    ),
+                    None => ([0.0; 4], [0.0; 4]),
+                };
+
+                // горизонтальные рёбра не пересекают центры строк
+                if top.y == bottom.y {
+                    continue;
+                }
+                let winding = if top.y < bottom.y { 1 } else { -1 };
+                if winding < 0 {
+                    std::mem::swap(&mut top, &mut bottom);
+                    std::mem::swap(&mut top_color, &mut bottom_color);
+                }
+
+                // строка y пересекается, если её центр y + 0.5 лежит в [top.y, bottom.y)
+                let first_row = (top.y - 0.5).ceil() as i32;
+                let end_row = (bottom.y - 0.5).ceil() as i32;
+                if first_row >= end_row {
+                    continue;
+                }
+
+                let height = bottom.y - top.y;
+                let dx = (bottom.x - top.x) / height;
+                let offset = first_row as f32 + 0.5 - top.y;
+                let mut color = [0.0; 4];
+                let mut dcolor = [0.0; 4];
+                for c in 0..4 {
+                    dcolor[c] = (bottom_color[c] - top_color[c]) / height;
+                    color[c] = top_color[c] + dcolor[c] * offset;
+                }
+
+                edges.push(Edge {
+                    first_row,
+                    end_row,
+                    x: top.x + dx * offset,
+                    dx,
+                    color,
+                    dcolor,
+                    winding,
+                });
+            }
+        }
+
+        edges.sort_by_key(|edge| edge.first_row);
+        edges
+    }
+
+    /// Растеризация многоугольника через таблицу активных рёбер.
+    /// Если colors не заданы, многоугольник заливается цветом fill_color.
+    fn rasterize_polygon(
+        &mut self,
+        contours: &[Vec<Pos2>],
+        colors: Option<&[Vec<Color32>]>,
+        rule: FillRule,
+        fill_color: Color32,
+    ) {
+        let mut edges = Self::build_edge_table(contours, colors)
+            .into_iter()
+            .peekable();
+        let Some(first_row) = edges.peek().map(|edge| edge.first_row) else {
+            return;
+        };
+
+        let mut active: Vec<Edge> = Vec::new();
+        let mut row = first_row.max(0);
+        while row < self.height as i32 && (edges.peek().is_some() || !active.is_empty()) {
+            // убрать закончившиеся рёбра и добавить начинающиеся
+            active.retain(|edge| edge.end_row > row);
+            while let Some(mut edge) = edges.next_if(|edge| edge.first_row <= row) {
+                // ребро могло начаться выше холста
+                edge.step(row - edge.first_row);
+                active.push(edge);
+            }
+            active.sort_by(|a, b| a.x.total_cmp(&b.x));
+
+            // поиск интервалов между рёбрами, лежащих внутри многоугольника
+            let mut winding = 0;
+            for i in 0..active.len().saturating_sub(1) {
+                winding += match rule {
+                    FillRule::EvenOdd => 1,
+                    FillRule::NonZero => active[i].winding,
+                };
+                let inside = match rule {
+                    FillRule::EvenOdd => winding % 2 != 0,
+                    FillRule::NonZero => winding != 0,
+                };
+                if inside {
+                    let (left, right) = (&active[i], &active[i + 1]);
+                    match colors {
+                        Some(_) => self.fill_gradient_span(row as usize, left, right),
+                        None => self.fill_span(row as usize, left.x, right.x, fill_color),
+                    }
+                }
+            }
+
+            for edge in active.iter_mut() {
+                edge.step(1);
+            }
+            row += 1;
+        }
+    }
+
+    /// Диапазон пикселей строки, центры которых лежат в [left_x, right_x).
+    fn span_pixels(&self, left_x: f32, right_x: f32) -> std::ops::Range<usize> {
+        let start = (left_x - 0.5).ceil().clamp(0.0, self.width as f32) as usize;
+        let end = (right_x - 0.5).ceil().clamp(0.0, self.width as f32) as usize;
+        start..end.max(start)
+    }
+
+    /// Заливка части строки одним цветом.
+    fn fill_span(&mut self, y: usize, left_x: f32, right_x: f32, color: Color32) {
+        let span = self.span_pixels(left_x, right_x);
+        let row = y * self.width;
+        self.pixels[row + span.start..row + span.end].fill(color);
+        self.mark_dirty(PixelRect::new(span.start, y, span.len(), 1));
+    }
+
+    /// Заливка части строки с интерполяцией цвета между двумя рёбрами.
+    fn fill_gradient_span(&mut self, y: usize, left: &Edge, right: &Edge) {
+        let width = right.x - left.x;
+        for x in self.span_pixels(left.x, right.x) {
+            let t = if width > 0.0 {
+                (x as f32 + 0.5 - left.x) / width
+            } else {
+                0.0
+            };
+            let color =
+                std::array::from_fn(|c| left.color[c] + (right.color[c] - left.color[c]) * t);
+            self[(x, y)] = array_to_color(color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::{Color32, Pos2, pos2};
+
+    use super::*;
+
+    fn square(x0: f32, y0: f32, x1: f32, y1: f32) -> Vec<Pos2> {
+        vec![pos2(x0, y0), pos2(x1, y0), pos2(x1, y1), pos2(x0, y1)]
+    }
+
+    /// Закрашенные пиксели холста.
+    fn filled(canvas: &Canvas) -> Vec<(usize, usize)> {
+        let [width, height] = canvas.size();
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|&(x, y)| canvas[(x, y)] != Color32::WHITE)
+            .collect()
+    }
+
+    #[test]
+    fn square_covers_pixels_with_centers_inside() {
+        let mut canvas = Canvas::new(8, 8);
+        canvas.fill_polygon(
+            &[square(1.0, 2.0, 4.0, 5.0)],
+            FillRule::EvenOdd,
+            Color32::RED,
+        );
+        let expected: Vec<_> = (2..5).flat_map(|y| (1..4).map(move |x| (x, y))).collect();
+        assert_eq!(filled(&canvas), expected);
+    }
+
+    #[test]
+    fn nested_contours_with_same_direction() {
+        let contours = [square(0.0, 0.0, 8.0, 8.0), square(2.0, 2.0, 6.0, 6.0)];
+
+        let mut even_odd = Canvas::new(8, 8);
+        even_odd.fill_polygon(&contours, FillRule::EvenOdd, Color32::RED);
+        assert_eq!(even_odd[(4, 4)], Color32::WHITE);
+        assert_eq!(even_odd[(1, 4)], Color32::RED);
+        assert_eq!(filled(&even_odd).len(), 64 - 16);
+
+        // внутренний контур обходит точки в ту же сторону - число оборотов 2
+        let mut non_zero = Canvas::new(8, 8);
+        non_zero.fill_polygon(&contours, FillRule::NonZero, Color32::RED);
+        assert_eq!(filled(&non_zero).len(), 64);
+    }
+
+    #[test]
+    fn reversed_inner_contour_is_a_hole_for_both_rules() {
+        let mut inner = square(2.0, 2.0, 6.0, 6.0);
+        inner.reverse();
+        let contours = [square(0.0, 0.0, 8.0, 8.0), inner];
+        for rule in [FillRule::EvenOdd, FillRule::NonZero] {
+            let mut canvas = Canvas::new(8, 8);
+            canvas.fill_polygon(&contours, rule, Color32::RED);
+            assert_eq!(canvas[(4, 4)], Color32::WHITE);
+            assert_eq!(filled(&canvas).len(), 64 - 16);
+        }
+    }
+
+    #[test]
+    fn pentagram_center_depends_on_rule() {
+        let star: Vec<Pos2> = (0..5)
+            .map(|i| {
+                let angle = std::f32::consts::TAU * (2 * i) as f32 / 5.0;
+                pos2(10.0 + 9.0 * angle.sin(), 10.0 - 9.0 * angle.cos())
+            })
+            .collect();
+        let contours = [star];
+
+        let mut even_odd = Canvas::new(20, 20);
+        even_odd.fill_polygon(&contours, FillRule::EvenOdd, Color32::RED);
+        assert_eq!(even_odd[(10, 10)], Color32::WHITE);
+        assert_eq!(even_odd[(10, 3)], Color32::RED);
+
+        let mut non_zero = Canvas::new(20, 20);
+        non_zero.fill_polygon(&contours, FillRule::NonZero, Color32::RED);
+        assert_eq!(non_zero[(10, 10)], Color32::RED);
+        assert_eq!(non_zero[(10, 3)], Color32::RED);
+    }
+
+    #[test]
+    fn polygon_outside_canvas_is_clipped() {
+        let mut canvas = Canvas::new(4, 4);
+        let contours = [square(-10.0, -10.0, 2.0, 20.0)];
+        canvas.fill_polygon(&contours, FillRule::NonZero, Color32::RED);
+        let expected: Vec<_> = (0..4).flat_map(|y| (0..2).map(move |x| (x, y))).collect();
+        assert_eq!(filled(&canvas), expected);
+    }
+
+    #[test]
+    fn gradient_polygon_interpolates_vertex_colors() {
+        let mut canvas = Canvas::new(10, 2);
+        let contours = [square(0.0, 0.0, 10.0, 2.0)];
+        let colors = [vec![
+            Color32::BLACK,
+            Color32::WHITE,
+            Color32::WHITE,
+            Color32::BLACK,
+        ]];
+        canvas.fill_gradient_polygon(&contours, &colors, FillRule::EvenOdd);
+        // цвет растёт слева направо
+        for x in 1..10 {
+            assert!(canvas[(x, 1)].r() > canvas[(x - 1, 1)].r());
+        }
+        assert!(canvas[(0, 0)].r() < 20 && canvas[(9, 0)].r() > 235);
+    }
+}
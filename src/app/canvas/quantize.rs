@@ -0,0 +1,546 @@
+use std::collections::HashMap;
+
+use egui::Color32;
+
+use super::Canvas;
+
+#[derive(Default, PartialEq, Clone, Copy)]
+/// Стандартная палитра.
+pub enum FixedPalette {
+    #[default]
+    /// 216 «безопасных» веб-цветов: по 6 уровней в каждом канале
+    Web,
+    /// 16 цветов CGA (RGBI, тёмно-жёлтый заменён коричневым)
+    Cga,
+    /// 64 цвета EGA: по 4 уровня в каждом канале
+    Ega,
+}
+
+impl FixedPalette {
+    pub const ALL: [FixedPalette; 3] = [FixedPalette::Web, FixedPalette::Cga, FixedPalette::Ega];
+
+    pub fn get_name(&self) -> String {
+        match self {
+            FixedPalette::Web => String::from("веб (216)"),
+            FixedPalette::Cga => String::from("CGA (16)"),
+            FixedPalette::Ega => String::from("EGA (64)"),
+        }
+    }
+
+    /// Цвета палитры.
+    pub fn colors(&self) -> Vec<Color32> {
+        // все сочетания уровней levels в каналах R, G, B
+        let cube = |levels: &[u8]| {
+            let mut colors = Vec::new();
+            for &r in levels {
+                for &g in levels {
+                    for &b in levels {
+                        colors.push(Color32::from_rgb(r, g, b));
+                    }
+                }
+            }
+            colors
+        };
+        match self {
+            FixedPalette::Web => cube(&[0x00, 0x33, 0x66, 0x99, 0xCC, 0xFF]),
+            FixedPalette::Cga => (0..16u8)
+                .map(|i| {
+                    let intensity = if i & 8 != 0 { 0x55 } else { 0 };
+                    let channel = |bit: u8| intensity + if i & bit != 0 { 0xAA } else { 0 };
+                    let (r, g, b) = (channel(4), channel(2), channel(1));
+                    // цвет 6 - коричневый, а не тёмно-жёлтый
+                    let g = if i == 6 { 0x55 } else { g };
+                    Color32::from_rgb(r, g, b)
+                })
+                .collect(),
+            FixedPalette::Ega => cube(&[0x00, 0x55, 0xAA, 0xFF]),
+        }
+    }
+}
+
+/// Соседи, получающие ошибку квантования: смещения (dx, dy) и веса.
+type DiffusionWeights = &'static [(i64, i64, f32)];
+
+#[derive(Default, PartialEq, Clone, Copy)]
+/// Способ распределения ошибки квантования.
+pub enum Dithering {
+    /// Ближайший цвет палитры без сглаживания
+    None,
+    #[default]
+    FloydSteinberg,
+    /// Распределяет 3/4 ошибки, сохраняя контраст
+    Atkinson,
+    JarvisJudiceNinke,
+    Stucki,
+    /// Трёхстрочный фильтр Серры
+    Sierra,
+    /// Упорядоченное сглаживание матрицей Байера 2 x 2
+    Bayer2,
+    /// Упорядоченное сглаживание матрицей Байера 4 x 4
+    Bayer4,
+    /// Упорядоченное сглаживание матрицей Байера 8 x 8
+    Bayer8,
+}
+
+impl Dithering {
+    pub const ALL: [Dithering; 9] = [
+        Dithering::None,
+        Dithering::FloydSteinberg,
+        Dithering::Atkinson,
+        Dithering::JarvisJudiceNinke,
+        Dithering::Stucki,
+        Dithering::Sierra,
+        Dithering::Bayer2,
+        Dithering::Bayer4,
+        Dithering::Bayer8,
+    ];
+
+    pub fn get_name(&self) -> String {
+        match self {
+            Dithering::None => String::from("без сглаживания"),
+            Dithering::FloydSteinberg => String::from("Флойд-Стейнберг"),
+            Dithering::Atkinson => String::from("Аткинсон"),
+            Dithering::JarvisJudiceNinke => String::from("Джарвис-Джудис-Нинке"),
+            Dithering::Stucki => String::from("Штуки"),
+            Dithering::Sierra => String::from("Серра"),
+            Dithering::Bayer2 => String::from("Байер 2 x 2"),
+            Dithering::Bayer4 => String::from("Байер 4 x 4"),
+            Dithering::Bayer8 => String::from("Байер 8 x 8"),
+        }
+    }
+
+    /// Распределение ошибки: смещения (dx, dy) соседей вперёд по обходу, их веса и делитель.
+    #[rustfmt::skip]
+    fn diffusion(&self) -> Option<(DiffusionWeights, f32)> {
+        match self {
+            Dithering::FloydSteinberg => Some((&[
+                (1, 0, 7.0),
+                (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0),
+            ], 16.0)),
+            Dithering::Atkinson => Some((&[
+                (1, 0, 1.0), (2, 0, 1.0),
+                (-1, 1, 1.0), (0, 1, 1.0), (1, 1, 1.0),
+                (0, 2, 1.0),
+            ], 8.0)),
+            Dithering::JarvisJudiceNinke => Some((&[
+                (1, 0, 7.0), (2, 0, 5.0),
+                (-2, 1, 3.0), (-1, 1, 5.0), (0, 1, 7.0), (1, 1, 5.0), (2, 1, 3.0),
+                (-2, 2, 1.0), (-1, 2, 3.0), (0, 2, 5.0), (1, 2, 3.0), (2, 2, 1.0),
+            ], 48.0)),
+            Dithering::Stucki => Some((&[
+                (1, 0, 8.0), (2, 0, 4.0),
+                (-2, 1, 2.0), (-1, 1, 4.0), (0, 1, 8.0), (1, 1, 4.0), (2, 1, 2.0),
+                (-2, 2, 1.0), (-1, 2, 2.0), (0, 2, 4.0), (1, 2, 2.0), (2, 2, 1.0),
+            ], 42.0)),
+            Dithering::Sierra => Some((&[
+                (1, 0, 5.0), (2, 0, 3.0),
+                (-2, 1, 2.0), (-1, 1, 4.0), (0, 1, 5.0), (1, 1, 4.0), (2, 1, 2.0),
+                (-1, 2, 2.0), (0, 2, 3.0), (1, 2, 2.0),
+            ], 32.0)),
+            _ => None,
+        }
+    }
+
+    /// Сторона матрицы Байера для упорядоченного сглаживания.
+    fn bayer_size(&self) -> Option<usize> {
+        match self {
+            Dithering::Bayer2 => Some(2),
+            Dithering::Bayer4 => Some(4),
+            Dithering::Bayer8 => Some(8),
+            _ => None,
+        }
+    }
+
+    /// Является ли сглаживание диффузией ошибки (для него имеет смысл змеевидный обход).
+    pub fn is_error_diffusion(&self) -> bool {
+        self.diffusion().is_some()
+    }
+}
+
+/// Матрица Байера size x size (size - степень двойки) с порогами в (-1/2, 1/2).
+fn bayer_matrix(size: usize) -> Vec<f32> {
+    let mut matrix = vec![0u32];
+    let mut side = 1;
+    while side < size {
+        // M(2n) = [[4M, 4M + 2], [4M + 3, 4M + 1]]
+        let mut next = vec![0; 4 * side * side];
+        for y in 0..side {
+            for x in 0..side {
+                let m = 4 * matrix[y * side + x];
+                next[y * 2 * side + x] = m;
+                next[y * 2 * side + x + side] = m + 2;
+                next[(y + side) * 2 * side + x] = m + 3;
+                next[(y + side) * 2 * side + x + side] = m + 1;
+            }
+        }
+        matrix = next;
+        side *= 2;
+    }
+    let count = (side * side) as f32;
+    matrix
+        .into_iter()
+        .map(|m| (m as f32 + 0.5) / count - 0.5)
+        .collect()
+}
+
+/// Квадрат расстояния между цветами.
+fn distance([r1, g1, b1]: [f32; 3], [r2, g2, b2]: [f32; 3]) -> f32 {
+    (r1 - r2).powi(2) + (g1 - g2).powi(2) + (b1 - b2).powi(2)
+}
+
+/// Номер ближайшего к color цвета палитры.
+fn nearest(palette: &[[f32; 3]], color: [f32; 3]) -> usize {
+    palette
+        .iter()
+        .enumerate()
+        .min_by(|a, b| distance(*a.1, color).total_cmp(&distance(*b.1, color)))
+        .map_or(0, |(index, _)| index)
+}
+
+/// Цвет с каналами [r, g, b] в [0, 255].
+fn opaque([r, g, b]: [f32; 3]) -> Color32 {
+    let channel = |c: f32| c.round().clamp(0.0, 255.0) as u8;
+    Color32::from_rgb(channel(r), channel(g), channel(b))
+}
+
+/// Средний цвет набора цветов с весами.
+fn weighted_mean(colors: &[([f32; 3], u32)]) -> [f32; 3] {
+    let mut sum = [0.0; 3];
+    let mut total = 0.0;
+    for &(color, weight) in colors {
+        for c in 0..3 {
+            sum[c] += color[c] * weight as f32;
+        }
+        total += weight as f32;
+    }
+    sum.map(|c| c / total.max(1.0))
+}
+
+// =============== Квантование и сглаживание ===============
+
+impl Canvas {
+    /// Различные цвета непрозрачных пикселей (без премультипликации) и их количество.
+    fn color_counts(&self) -> Vec<([f32; 3], u32)> {
+        let mut counts: HashMap<[u8; 3], u32> = HashMap::new();
+        for color in &self.pixels {
+            if color.a() == 0 {
+                continue;
+            }
+            let [r, g, b, _] = color.to_srgba_unmultiplied();
+            *counts.entry([r, g, b]).or_default() += 1;
+        }
+        let mut counts: Vec<_> = counts
+            .into_iter()
+            .map(|(color, count)| (color.map(f32::from), count))
+            .collect();
+        // порядок HashMap случаен, а результат должен быть воспроизводимым
+        counts.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        counts
+    }
+
+    /// Палитра из не более чем count цветов методом медианного сечения:
+    /// параллелепипед цветов с наибольшим разбросом делится пополам по числу пикселей
+    /// вдоль самого длинного канала, цвет палитры - средний цвет параллелепипеда.
+    pub fn median_cut_palette(&self, count: usize) -> Vec<Color32> {
+        let mut boxes = vec![self.color_counts()];
+        boxes.retain(|colors| !colors.is_empty());
+
+        // разброс параллелепипеда: (наибольшая длина, канал)
+        let extent = |colors: &[([f32; 3], u32)]| {
+            (0..3)
+                .map(|c| {
+                    let (min, max) = colors
+                        .iter()
+                        .fold((f32::MAX, f32::MIN), |(min, max), (color, _)| {
+                            (min.min(color[c]), max.max(color[c]))
+                        });
+                    (max - min, c)
+                })
+                .max_by(|a, b| a.0.total_cmp(&b.0))
+                .unwrap()
+        };
+
+        while boxes.len() < count {
+            let Some((index, channel)) = boxes
+                .iter()
+                .enumerate()
+                .filter(|(_, colors)| colors.len() > 1)
+                .map(|(index, colors)| (index, extent(colors)))
+                .max_by(|a, b| a.1.0.total_cmp(&b.1.0))
+                .map(|(index, (_, channel))| (index, channel))
+            else {
+                break;
+            };
+
+            let mut colors = boxes.swap_remove(index);
+            colors.sort_by(|a, b| a.0[channel].total_cmp(&b.0[channel]));
+            let total: u32 = colors.iter().map(|(_, n)| n).sum();
+            let mut accumulated = 0;
+            let median = colors
+                .iter()
+                .position(|(_, n)| {
+                    accumulated += n;
+                    accumulated * 2 >= total
+                })
+                .unwrap();
+            // обе части должны быть непустыми
+            let split = (median + 1).clamp(1, colors.len() - 1);
+            let upper = colors.split_off(split);
+            boxes.push(colors);
+            boxes.push(upper);
+        }
+
+        boxes
+            .iter()
+            .map(|colors| opaque(weighted_mean(colors)))
+            .collect()
+    }
+
+    /// Палитра из не более чем count цветов кластеризацией k-средних
+    /// (начальные центры - палитра медианного сечения, не более iterations итераций).
+    pub fn kmeans_palette(&self, count: usize, iterations: usize) -> Vec<Color32> {
+        let colors = self.color_counts();
+        let mut centers: Vec<[f32; 3]> = self
+            .median_cut_palette(count)
+            .iter()
+            .map(|color| {
+                let [r, g, b, _] = color.to_array();
+                [r, g, b].map(f32::from)
+            })
+            .collect();
+
+        for _ in 0..iterations {
+            let mut clusters = vec![Vec::new(); centers.len()];
+            for &(color, weight) in &colors {
+                clusters[nearest(&centers, color)].push((color, weight));
+            }
+            let mut moved = false;
+            for (center, cluster) in centers.iter_mut().zip(&clusters) {
+                // пустой кластер сохраняет прежний центр
+                if cluster.is_empty() {
+                    continue;
+                }
+                let mean = weighted_mean(cluster);
+                moved |= distance(mean, *center) > 0.25;
+                *center = mean;
+            }
+            if !moved {
+                break;
+            }
+        }
+        centers.into_iter().map(opaque).collect()
+    }
+
+    /// Замена цветов ближайшими цветами палитры palette со сглаживанием dithering.
+    /// При диффузии ошибки serpentine включает змеевидный обход строк
+    /// (нечётные строки справа налево), уменьшающий направленные узоры.
+    /// Для упорядоченного сглаживания размах порогов - среднее расстояние между
+    /// уровнями канала в палитре (корень кубический из числа цветов - число уровней).
+    /// Прозрачность пикселей сохраняется, полностью прозрачные пиксели не изменяются.
+    pub fn quantize(&self, palette: &[Color32], dithering: Dithering, serpentine: bool) -> Canvas {
+        if palette.is_empty() {
+            return self.clone();
+        }
+        let palette: Vec<[f32; 3]> = palette
+            .iter()
+            .map(|color| {
+                let [r, g, b, _] = color.to_srgba_unmultiplied();
+                [r, g, b].map(f32::from)
+            })
+            .collect();
+        let (width, height) = (self.width, self.height);
+        let mut colors: Vec<[f32; 3]> = self
+            .pixels
+            .iter()
+            .map(|color| {
+                let [r, g, b, _] = color.to_srgba_unmultiplied();
+                [r, g, b].map(f32::from)
+            })
+            .collect();
+
+        let diffusion = dithering.diffusion();
+        let bayer = dithering
+            .bayer_size()
+            .map(|size| (size, bayer_matrix(size)));
+        let spread = 255.0 / ((palette.len() as f32).cbrt() - 1.0).max(1.0);
+
+        let mut pixels = self.pixels.clone();
+        for y in 0..height {
+            let reverse = serpentine && y % 2 == 1;
+            for i in 0..width {
+                let x = if reverse { width - 1 - i } else { i };
+                let index = y * width + x;
+                let alpha = self.pixels[index].a();
+                if alpha == 0 {
+                    continue;
+                }
+
+                let mut color = colors[index].map(|c| c.clamp(0.0, 255.0));
+                if let Some((size, matrix)) = &bayer {
+                    let threshold = matrix[(y % size) * size + x % size] * spread;
+                    color = color.map(|c| c + threshold);
+                }
+                let chosen = palette[nearest(&palette, color)];
+                let [r, g, b, _] = opaque(chosen).to_array();
+                pixels[index] = Color32::from_rgba_unmultiplied(r, g, b, alpha);
+
+                let Some((weights, divisor)) = diffusion else {
+                    continue;
+                };
+                let error = [0, 1, 2].map(|c| color[c] - chosen[c]);
+                for &(dx, dy, weight) in weights {
+                    let dx = if reverse { -dx } else { dx };
+                    let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                    if nx < 0 || nx >= width as i64 || ny >= height as i64 {
+                        continue;
+                    }
+                    let neighbor = ny as usize * width + nx as usize;
+                    if self.pixels[neighbor].a() == 0 {
+                        continue;
+                    }
+                    for c in 0..3 {
+                        colors[neighbor][c] += error[c] * weight / divisor;
+                    }
+                }
+            }
+        }
+        Canvas::from_pixels(width, height, pixels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAY: Color32 = Color32::from_rgb(128, 128, 128);
+
+    #[test]
+    fn bayer_matrix_2x2() {
+        assert_eq!(bayer_matrix(1), [0.0]);
+        assert_eq!(bayer_matrix(2), [-0.375, 0.125, 0.375, -0.125]);
+    }
+
+    #[test]
+    fn bayer_matrix_has_uniform_thresholds() {
+        for size in [4, 8] {
+            let matrix = bayer_matrix(size);
+            let count = size * size;
+            // пороги - все уровни (k + 1/2) / count - 1/2 ровно по одному разу
+            let mut levels: Vec<usize> = matrix
+                .iter()
+                .map(|t| ((t + 0.5) * count as f32 - 0.5).round() as usize)
+                .collect();
+            levels.sort();
+            assert_eq!(levels, (0..count).collect::<Vec<_>>());
+            // соседние по горизонтали пороги далеки друг от друга
+            for y in 0..size {
+                for x in 1..size {
+                    let (a, b) = (matrix[y * size + x - 1], matrix[y * size + x]);
+                    assert!((a - b).abs() >= 0.25);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn median_cut_keeps_few_colors_exactly() {
+        let colors = [Color32::RED, Color32::BLUE, Color32::from_rgb(10, 20, 30)];
+        let pixels = (0..12).map(|i| colors[i % 3]).collect();
+        let canvas = Canvas::from_pixels(4, 3, pixels);
+        let mut palette = canvas.median_cut_palette(8);
+        palette.sort_by_key(|c| c.to_array());
+        let mut expected = colors.to_vec();
+        expected.sort_by_key(|c| c.to_array());
+        assert_eq!(palette, expected);
+    }
+
+    #[test]
+    fn median_cut_separates_clusters() {
+        // два равных по числу пикселей скопления цветов: тёмные и светлые
+        let mut pixels = Vec::new();
+        for i in 0..50u8 {
+            pixels.push(Color32::from_rgb(i % 10, i % 7, i % 5));
+        }
+        for i in 0..50u8 {
+            pixels.push(Color32::from_rgb(250 - i % 10, 245 + i % 10 / 2, 240));
+        }
+        // прозрачные пиксели не учитываются
+        pixels.extend([Color32::TRANSPARENT; 10]);
+        let canvas = Canvas::from_pixels(11, 10, pixels);
+
+        let palette = canvas.median_cut_palette(2);
+        assert_eq!(palette.len(), 2);
+        let dark = palette.iter().filter(|c| c.r() < 20).count();
+        let light = palette.iter().filter(|c| c.r() > 230).count();
+        assert_eq!((dark, light), (1, 1));
+
+        assert!(
+            Canvas::new_filled(3, 3, Color32::TRANSPARENT)
+                .median_cut_palette(4)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn quantize_uses_only_palette_colors() {
+        let pixels = (0..64u32)
+            .map(|i| Color32::from_rgb((i * 4) as u8, (255 - i * 3) as u8, (i * 37 % 256) as u8))
+            .collect();
+        let canvas = Canvas::from_pixels(8, 8, pixels);
+        let palette = FixedPalette::Cga.colors();
+        for dithering in Dithering::ALL {
+            for serpentine in [false, true] {
+                let result = canvas.quantize(&palette, dithering, serpentine);
+                assert!(result.pixels.iter().all(|c| palette.contains(c)));
+            }
+        }
+    }
+
+    #[test]
+    fn quantize_keeps_alpha() {
+        let mut canvas =
+            Canvas::new_filled(4, 4, Color32::from_rgba_unmultiplied(200, 30, 30, 128));
+        canvas[(0, 0)] = Color32::TRANSPARENT;
+        let palette = [Color32::BLACK, Color32::RED];
+        for dithering in Dithering::ALL {
+            let result = canvas.quantize(&palette, dithering, false);
+            assert_eq!(result[(0, 0)], Color32::TRANSPARENT);
+            for y in 0..4 {
+                for x in 0..4 {
+                    assert_eq!(result[(x, y)].a(), canvas[(x, y)].a());
+                }
+            }
+        }
+        // пустая палитра ничего не меняет
+        let result = canvas.quantize(&[], Dithering::FloydSteinberg, false);
+        assert!(result.pixels == canvas.pixels);
+    }
+
+    #[test]
+    fn dithering_preserves_mean_brightness() {
+        let canvas = Canvas::new_filled(16, 16, GRAY);
+        let palette = [Color32::BLACK, Color32::WHITE];
+
+        let plain = canvas.quantize(&palette, Dithering::None, false);
+        let white = plain
+            .pixels
+            .iter()
+            .filter(|&&c| c == Color32::WHITE)
+            .count();
+        assert_eq!(white, 256);
+
+        for dithering in [
+            Dithering::FloydSteinberg,
+            Dithering::Bayer4,
+            Dithering::Bayer8,
+        ] {
+            let result = canvas.quantize(&palette, dithering, true);
+            let white = result
+                .pixels
+                .iter()
+                .filter(|&&c| c == Color32::WHITE)
+                .count();
+            assert!((120..=136).contains(&white));
+        }
+    }
+}
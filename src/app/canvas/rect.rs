@@ -0,0 +1,252 @@
+use egui::{Color32, Rect};
+
+use super::{Canvas, lerp_color};
+
+#[derive(Default, PartialEq, Clone, Copy, serde::Serialize, serde::Deserialize)]
+/// Направление линейного градиента.
+pub enum GradientDirection {
+    #[default]
+    /// Слева направо
+    Horizontal,
+    /// Сверху вниз
+    Vertical,
+}
+
+impl GradientDirection {
+    pub fn get_name(&self) -> String {
+        match self {
+            GradientDirection::Horizontal => String::from("горизонтальный"),
+            GradientDirection::Vertical => String::from("вертикальный"),
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, serde::Serialize, serde::Deserialize)]
+/// Способ заливки фигуры.
+pub enum Fill {
+    /// Один цвет
+    Solid(Color32),
+    /// Линейный градиент от start до end
+    Gradient {
+        start: Color32,
+        end: Color32,
+        direction: GradientDirection,
+    },
+}
+
+impl Default for Fill {
+    fn default() -> Self {
+        Fill::Solid(Color32::WHITE)
+    }
+}
+
+/// Горизонтальные границы прямоугольника со скруглёнными углами на высоте y.
+fn rounded_rect_span(rect: Rect, radius: f32, y: f32) -> Option<(f32, f32)> {
+    if y < rect.top() || y > rect.bottom() {
+        return None;
+    }
+
+    // в зоне углов граница - дуга окружности
+    let radius = radius.clamp(0.0, rect.width().min(rect.height()) / 2.0);
+    let dy = (rect.top() + radius - y).max(y - (rect.bottom() - radius));
+    let inset = if dy > 0.0 {
+        radius - (radius * radius - dy * dy).max(0.0).sqrt()
+    } else {
+        0.0
+    };
+    Some((rect.left() + inset, rect.right() - inset))
+}
+
+/// Номера пикселей, центры которых лежат в [left, right].
+fn span_to_pixels(left: f32, right: f32) -> (i32, i32) {
+    ((left - 0.5).ceil() as i32, (right - 0.5).floor() as i32)
+}
+
+// =============== Прямоугольники ===============
+
+impl Canvas {
+    /// Заливка прямоугольника (возможно со скруглёнными углами) построчно.
+    /// Закрашиваются пиксели, центры которых лежат внутри фигуры.
+    /// rect - прямоугольник в координатах холста;
+    /// corner_radius - радиус скругления углов;
+    /// fill - способ заливки;
+    pub fn fill_rect(&mut self, rect: Rect, corner_radius: f32, fill: Fill) {
+        let first_row = (rect.top() - 0.5).ceil() as i32;
+        let last_row = (rect.bottom() - 0.5).floor() as i32;
+
+        for y in first_row.max(0)..=last_row.min(self.height as i32 - 1) {
+            let Some((left, right)) = rounded_rect_span(rect, corner_radius, y as f32 + 0.5) else {
+                continue;
+            };
+            let (start, end) = span_to_pixels(left, right);
+
+            match fill {
+                Fill::Solid(color) => self.fill_row(y, start, end, color),
+                Fill::Gradient {
+                    start: from,
+                    end: to,
+                    direction: GradientDirection::Vertical,
+                } => {
+                    // цвет постоянен вдоль строки
+                    let t = (y as f32 + 0.5 - rect.top()) / rect.height().max(f32::EPSILON);
+                    self.fill_row(y, start, end, lerp_color(from, to, t.clamp(0.0, 1.0)));
+                }
+                Fill::Gradient {
+                    start: from,
+                    end: to,
+                    direction: GradientDirection::Horizontal,
+                } => {
+                    for x in start.max(0)..=end.min(self.width as i32 - 1) {
+                        let t = (x as f32 + 0.5 - rect.left()) / rect.width().max(f32::EPSILON);
+                        self[(x as usize, y as usize)] = lerp_color(from, to, t.clamp(0.0, 1.0));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Контур прямоугольника (возможно со скруглёнными углами) заданной толщины.
+    /// Контур лежит внутри rect.
+    /// rect - прямоугольник в координатах холста;
+    /// corner_radius - радиус скругления углов;
+    /// stroke_width - толщина контура;
+    /// color - цвет контура;
+    pub fn draw_rect(&mut self, rect: Rect, corner_radius: f32, stroke_width: f32, color: Color32) {
+        let stroke_width = stroke_width.max(1.0);
+        let inner = rect.shrink(stroke_width);
+        let inner_radius = (corner_radius - stroke_width).max(0.0);
+
+        let first_row = (rect.top() - 0.5).ceil() as i32;
+        let last_row = (rect.bottom() - 0.5).floor() as i32;
+
+        for y in first_row.max(0)..=last_row.min(self.height as i32 - 1) {
+            let y_center = y as f32 + 0.5;
+            let Some((left, right)) = rounded_rect_span(rect, corner_radius, y_center) else {
+                continue;
+            };
+            let (start, end) = span_to_pixels(left, right);
+
+            // контур - строка внешней фигуры без строки внутренней
+            let hole = if inner.is_positive() {
+                rounded_rect_span(inner, inner_radius, y_center)
+            } else {
+                None
+            };
+            match hole {
+                Some((inner_left, inner_right)) => {
+                    let (inner_start, inner_end) = span_to_pixels(inner_left, inner_right);
+                    self.fill_row(y, start, inner_start - 1, color);
+                    self.fill_row(y, inner_end + 1, end, color);
+                }
+                None => self.fill_row(y, start, end, color),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::Pos2;
+
+    use super::*;
+
+    fn rect(x0: f32, y0: f32, x1: f32, y1: f32) -> Rect {
+        Rect::from_min_max(Pos2::new(x0, y0), Pos2::new(x1, y1))
+    }
+
+    /// Число пикселей холста цвета color.
+    fn count(canvas: &Canvas, color: Color32) -> usize {
+        canvas.pixels.iter().filter(|&&c| c == color).count()
+    }
+
+    #[test]
+    fn sharp_rect_covers_whole_pixels() {
+        let mut canvas = Canvas::new(10, 10);
+        canvas.fill_rect(rect(2.0, 3.0, 7.0, 5.0), 0.0, Fill::Solid(Color32::RED));
+        assert_eq!(count(&canvas, Color32::RED), 10);
+        assert_eq!(canvas[(2, 3)], Color32::RED);
+        assert_eq!(canvas[(6, 4)], Color32::RED);
+        assert_eq!(canvas[(7, 4)], Color32::WHITE);
+    }
+
+    #[test]
+    fn rounded_corners_are_cut() {
+        let mut canvas = Canvas::new(20, 20);
+        canvas.fill_rect(rect(0.0, 0.0, 20.0, 20.0), 6.0, Fill::Solid(Color32::RED));
+        for (x, y) in [(0, 0), (19, 0), (0, 19), (19, 19)] {
+            assert_eq!(canvas[(x, y)], Color32::WHITE);
+        }
+        assert_eq!(canvas[(10, 0)], Color32::RED);
+        assert_eq!(canvas[(0, 10)], Color32::RED);
+
+        // радиус не больше половины меньшей стороны: фигура - круг
+        let span = rounded_rect_span(rect(0.0, 0.0, 10.0, 10.0), 100.0, 5.0);
+        assert_eq!(span, Some((0.0, 10.0)));
+        assert_eq!(
+            rounded_rect_span(rect(0.0, 0.0, 10.0, 10.0), 100.0, 0.0),
+            Some((5.0, 5.0))
+        );
+        assert_eq!(
+            rounded_rect_span(rect(0.0, 0.0, 10.0, 10.0), 0.0, 11.0),
+            None
+        );
+    }
+
+    #[test]
+    fn outline_lies_inside_the_rect() {
+        let mut canvas = Canvas::new(12, 12);
+        canvas.draw_rect(rect(1.0, 1.0, 11.0, 11.0), 0.0, 2.0, Color32::RED);
+        // 10x10 без внутреннего квадрата 6x6
+        assert_eq!(count(&canvas, Color32::RED), 100 - 36);
+        assert_eq!(canvas[(0, 0)], Color32::WHITE);
+        assert_eq!(canvas[(2, 2)], Color32::RED);
+        assert_eq!(canvas[(3, 3)], Color32::WHITE);
+
+        // толстый контур заливает фигуру целиком
+        let mut canvas = Canvas::new(12, 12);
+        canvas.draw_rect(rect(1.0, 1.0, 11.0, 11.0), 0.0, 6.0, Color32::RED);
+        assert_eq!(count(&canvas, Color32::RED), 100);
+    }
+
+    #[test]
+    fn gradient_runs_from_start_to_end() {
+        let fill = |direction| Fill::Gradient {
+            start: Color32::BLACK,
+            end: Color32::WHITE,
+            direction,
+        };
+        let mut canvas = Canvas::new(8, 8);
+        canvas.fill_rect(
+            rect(0.0, 0.0, 8.0, 8.0),
+            0.0,
+            fill(GradientDirection::Horizontal),
+        );
+        for x in 1..8 {
+            assert!(canvas[(x, 3)].r() > canvas[(x - 1, 3)].r());
+            assert_eq!(canvas[(x, 0)], canvas[(x, 7)]);
+        }
+
+        let mut canvas = Canvas::new(8, 8);
+        canvas.fill_rect(
+            rect(0.0, 0.0, 8.0, 8.0),
+            0.0,
+            fill(GradientDirection::Vertical),
+        );
+        for y in 1..8 {
+            assert!(canvas[(3, y)].r() > canvas[(3, y - 1)].r());
+            assert_eq!(canvas[(0, y)], canvas[(7, y)]);
+        }
+    }
+
+    #[test]
+    fn rect_outside_the_canvas_is_clipped() {
+        let mut canvas = Canvas::new(4, 4);
+        canvas.fill_rect(
+            rect(-10.0, -10.0, 2.0, 20.0),
+            0.0,
+            Fill::Solid(Color32::RED),
+        );
+        canvas.draw_rect(rect(-10.0, -10.0, 20.0, 20.0), 3.0, 1.0, Color32::RED);
+        assert_eq!(count(&canvas, Color32::RED), 8);
+    }
+}
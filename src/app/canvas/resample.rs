@@ -0,0 +1,400 @@
+use egui::Color32;
+
+use super::{Canvas, PixelRect};
+
+#[derive(Default, PartialEq, Clone, Copy)]
+/// Фильтр передискретизации при изменении размеров изображения.
+pub enum Resampling {
+    /// Ближайший пиксель
+    Nearest,
+    #[default]
+    /// Линейная интерполяция (треугольный фильтр)
+    Bilinear,
+    /// Кубическая свёртка Катмулла-Рома
+    Bicubic,
+    /// Фильтр Ланцоша с радиусом 3
+    Lanczos,
+}
+
+impl Resampling {
+    pub const ALL: [Resampling; 4] = [
+        Resampling::Nearest,
+        Resampling::Bilinear,
+        Resampling::Bicubic,
+        Resampling::Lanczos,
+    ];
+
+    pub fn get_name(&self) -> String {
+        match self {
+            Resampling::Nearest => String::from("ближайший"),
+            Resampling::Bilinear => String::from("билинейная"),
+            Resampling::Bicubic => String::from("бикубическая"),
+            Resampling::Lanczos => String::from("Ланцош"),
+        }
+    }
+
+    /// Радиус ядра фильтра в пикселях исходного изображения.
+    pub(super) fn support(&self) -> f32 {
+        match self {
+            Resampling::Nearest => 0.5,
+            Resampling::Bilinear => 1.0,
+            Resampling::Bicubic => 2.0,
+            Resampling::Lanczos => 3.0,
+        }
+    }
+
+    /// Значение ядра фильтра на расстоянии x от центра.
+    pub(super) fn kernel(&self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            Resampling::Nearest => {
+                if x <= 0.5 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Resampling::Bilinear => (1.0 - x).max(0.0),
+            Resampling::Bicubic => {
+                // a = -0.5 (сплайн Катмулла-Рома)
+                if x < 1.0 {
+                    1.5 * x * x * x - 2.5 * x * x + 1.0
+                } else if x < 2.0 {
+                    -0.5 * x * x * x + 2.5 * x * x - 4.0 * x + 2.0
+                } else {
+                    0.0
+                }
+            }
+            Resampling::Lanczos => {
+                if x < 1e-6 {
+                    1.0
+                } else if x < 3.0 {
+                    let pi_x = std::f32::consts::PI * x;
+                    3.0 * pi_x.sin() * (pi_x / 3.0).sin() / (pi_x * pi_x)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+#[derive(Default, PartialEq, Clone, Copy)]
+/// Положение старого изображения на холсте нового размера.
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    #[default]
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// Все положения по строкам сетки 3 x 3.
+    pub const ALL: [Anchor; 9] = [
+        Anchor::TopLeft,
+        Anchor::Top,
+        Anchor::TopRight,
+        Anchor::Left,
+        Anchor::Center,
+        Anchor::Right,
+        Anchor::BottomLeft,
+        Anchor::Bottom,
+        Anchor::BottomRight,
+    ];
+
+    pub fn get_name(&self) -> String {
+        match self {
+            Anchor::TopLeft => String::from("слева сверху"),
+            Anchor::Top => String::from("сверху"),
+            Anchor::TopRight => String::from("справа сверху"),
+            Anchor::Left => String::from("слева"),
+            Anchor::Center => String::from("по центру"),
+            Anchor::Right => String::from("справа"),
+            Anchor::BottomLeft => String::from("слева снизу"),
+            Anchor::Bottom => String::from("снизу"),
+            Anchor::BottomRight => String::from("справа снизу"),
+        }
+    }
+
+    /// Стрелка для кнопки выбора положения.
+    pub fn get_arrow(&self) -> &'static str {
+        match self {
+            Anchor::TopLeft => "↖",
+            Anchor::Top => "⬆",
+            Anchor::TopRight => "↗",
+            Anchor::Left => "⬅",
+            Anchor::Center => "⏺",
+            Anchor::Right => "➡",
+            Anchor::BottomLeft => "↙",
+            Anchor::Bottom => "⬇",
+            Anchor::BottomRight => "↘",
+        }
+    }
+
+    /// Доли свободного места слева и сверху (0, 1/2 или 1).
+    fn fractions(&self) -> (i64, i64) {
+        let index = Anchor::ALL
+            .iter()
+            .position(|anchor| anchor == self)
+            .unwrap() as i64;
+        (index % 3, index / 3)
+    }
+
+    /// Смещение левого верхнего угла старого изображения размера old
+    /// на холсте размера new (может быть отрицательным при уменьшении).
+    pub fn offset(&self, old: [usize; 2], new: [usize; 2]) -> [i64; 2] {
+        let (fx, fy) = self.fractions();
+        [
+            (new[0] as i64 - old[0] as i64) * fx / 2,
+            (new[1] as i64 - old[1] as i64) * fy / 2,
+        ]
+    }
+}
+
+/// Веса фильтра для одного пикселя результата:
+/// вклад пикселей источника start, start + 1, ...
+struct Contribution {
+    start: usize,
+    weights: Vec<f32>,
+}
+
+/// Веса передискретизации отрезка из src пикселей в dst пикселей.
+/// При уменьшении ядро (кроме ближайшего пикселя) растягивается, чтобы каждый пиксель результата
+/// усреднял все попадающие в него пиксели источника.
+fn contributions(src: usize, dst: usize, filter: Resampling) -> Vec<Contribution> {
+    let scale = dst as f32 / src as f32;
+    let filter_scale = (1.0 / scale).max(1.0);
+    let support = filter.support() * filter_scale;
+
+    (0..dst)
+        .map(|i| {
+            let center = (i as f32 + 0.5) / scale;
+            if filter == Resampling::Nearest {
+                // без усреднения даже при уменьшении
+                let nearest = (center.floor() as usize).min(src - 1);
+                return Contribution {
+                    start: nearest,
+                    weights: vec![1.0],
+                };
+            }
+
+            let start = (center - support).floor().max(0.0) as usize;
+            let end = ((center + support).ceil() as usize).clamp(start + 1, src);
+
+            let mut weights: Vec<f32> = (start..end)
+                .map(|j| filter.kernel((j as f32 + 0.5 - center) / filter_scale))
+                .collect();
+            let sum: f32 = weights.iter().sum();
+            if sum.abs() > 1e-6 {
+                weights.iter_mut().for_each(|w| *w /= sum);
+            } else {
+                // ядро не накрыло ни одного пикселя - берём ближайший
+                let nearest = (center.floor() as usize).clamp(start, end - 1);
+                weights.fill(0.0);
+                weights[nearest - start] = 1.0;
+            }
+            Contribution { start, weights }
+        })
+        .collect()
+}
+
+/// Цвет из каналов с премультиплицированной альфой в [0, 255].
+/// Фильтры с отрицательными весами дают выбросы, которые обрезаются.
+pub(super) fn color_from_channels([r, g, b, a]: [f32; 4]) -> Color32 {
+    let a = a.round().clamp(0.0, 255.0);
+    let channel = |c: f32| c.round().clamp(0.0, a) as u8;
+    Color32::from_rgba_premultiplied(channel(r), channel(g), channel(b), a as u8)
+}
+
+// =============== Изменение размеров ===============
+
+impl Canvas {
+    /// Изображение, масштабированное до размера width x height фильтром filter.
+    /// Интерполяция ведётся в премультиплицированных цветах,
+    /// поэтому прозрачные пиксели не окрашивают края.
+    pub fn resize(&self, width: usize, height: usize, filter: Resampling) -> Canvas {
+        if width == 0 || height == 0 || self.width == 0 || self.height == 0 {
+            return Canvas::new_filled(width, height, Color32::TRANSPARENT);
+        }
+
+        // сначала по горизонтали: self.height строк по width пикселей
+        let columns = contributions(self.width, width, filter);
+        let mut horizontal = vec![[0.0f32; 4]; width * self.height];
+        for y in 0..self.height {
+            let row = &self.pixels[y * self.width..(y + 1) * self.width];
+            for (x, contribution) in columns.iter().enumerate() {
+                let mut sum = [0.0; 4];
+                for (k, &weight) in contribution.weights.iter().enumerate() {
+                    let color = row[contribution.start + k].to_array();
+                    for c in 0..4 {
+                        sum[c] += color[c] as f32 * weight;
+                    }
+                }
+                horizontal[y * width + x] = sum;
+            }
+        }
+
+        // затем по вертикали
+        let rows = contributions(self.height, height, filter);
+        let mut pixels = Vec::with_capacity(width * height);
+        for contribution in &rows {
+            for x in 0..width {
+                let mut sum = [0.0; 4];
+                for (k, &weight) in contribution.weights.iter().enumerate() {
+                    let color = horizontal[(contribution.start + k) * width + x];
+                    for c in 0..4 {
+                        sum[c] += color[c] * weight;
+                    }
+                }
+                pixels.push(color_from_channels(sum));
+            }
+        }
+        Canvas::from_pixels(width, height, pixels)
+    }
+
+    /// Холст размера width x height, на котором текущее изображение
+    /// расположено согласно anchor; новые области заливаются цветом fill.
+    pub fn resize_canvas(
+        &self,
+        width: usize,
+        height: usize,
+        anchor: Anchor,
+        fill: Color32,
+    ) -> Canvas {
+        let mut result = Canvas::new_filled(width, height, fill);
+        let [dx, dy] = anchor.offset(self.size(), [width, height]);
+
+        // пересечение старого изображения с новым холстом в координатах старого
+        let left = (-dx).max(0) as usize;
+        let top = (-dy).max(0) as usize;
+        let right = (width as i64 - dx).clamp(0, self.width as i64) as usize;
+        let bottom = (height as i64 - dy).clamp(0, self.height as i64) as usize;
+        if left >= right || top >= bottom {
+            return result;
+        }
+
+        for y in top..bottom {
+            let src = y * self.width;
+            let dst = (y as i64 + dy) as usize * width + (left as i64 + dx) as usize;
+            result.pixels[dst..dst + right - left]
+                .copy_from_slice(&self.pixels[src + left..src + right]);
+        }
+        result
+    }
+
+    /// Часть изображения внутри rect (обрезанного по границам холста).
+    pub fn crop(&self, rect: PixelRect) -> Canvas {
+        match rect.intersect(self.bounds()) {
+            Some(rect) => {
+                let region = self.region_image(rect);
+                Canvas::from_pixels(rect.width, rect.height, region.pixels)
+            }
+            None => Canvas::new_filled(0, 0, Color32::TRANSPARENT),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Холст 5x4 с разными цветами пикселей.
+    fn sample_canvas() -> Canvas {
+        let pixels = (0..20u8)
+            .map(|i| Color32::from_rgb(i * 12, 255 - i * 9, (i % 3) * 100))
+            .collect();
+        Canvas::from_pixels(5, 4, pixels)
+    }
+
+    #[test]
+    fn same_size_resize_is_identity() {
+        let canvas = sample_canvas();
+        for filter in Resampling::ALL {
+            assert!(canvas.resize(5, 4, filter).pixels == canvas.pixels);
+        }
+    }
+
+    #[test]
+    fn uniform_image_stays_uniform() {
+        let color = Color32::from_rgba_unmultiplied(30, 60, 90, 200);
+        let canvas = Canvas::new_filled(7, 5, color);
+        for filter in Resampling::ALL {
+            for (width, height) in [(3, 2), (16, 11), (7, 1)] {
+                let resized = canvas.resize(width, height, filter);
+                assert_eq!(resized.size(), [width, height]);
+                assert!(resized.pixels.iter().all(|&c| c == color));
+            }
+        }
+    }
+
+    #[test]
+    fn nearest_upscale_duplicates_pixels() {
+        let canvas = sample_canvas();
+        let resized = canvas.resize(10, 8, Resampling::Nearest);
+        for y in 0..8 {
+            for x in 0..10 {
+                assert_eq!(resized[(x, y)], canvas[(x / 2, y / 2)]);
+            }
+        }
+    }
+
+    #[test]
+    fn transparent_pixels_do_not_tint_edges() {
+        let mut canvas = Canvas::new_filled(4, 1, Color32::TRANSPARENT);
+        canvas[(0, 0)] = Color32::RED;
+        canvas[(1, 0)] = Color32::RED;
+        for filter in [
+            Resampling::Bilinear,
+            Resampling::Bicubic,
+            Resampling::Lanczos,
+        ] {
+            let resized = canvas.resize(9, 1, filter);
+            for color in resized.pixels.iter().filter(|c| c.a() > 0) {
+                let [r, g, b, _] = color.to_srgba_unmultiplied();
+                assert!(r >= 250 && g <= 5 && b <= 5);
+            }
+        }
+    }
+
+    #[test]
+    fn anchor_offsets() {
+        let (old, new) = ([4, 6], [8, 2]);
+        assert_eq!(Anchor::TopLeft.offset(old, new), [0, 0]);
+        assert_eq!(Anchor::Center.offset(old, new), [2, -2]);
+        assert_eq!(Anchor::BottomRight.offset(old, new), [4, -4]);
+        assert_eq!(Anchor::Left.offset(old, new), [0, -2]);
+    }
+
+    #[test]
+    fn canvas_size_change_keeps_pixels_at_anchor() {
+        let canvas = sample_canvas();
+        let grown = canvas.resize_canvas(9, 6, Anchor::Center, Color32::TRANSPARENT);
+        assert_eq!(grown.size(), [9, 6]);
+        assert_eq!(grown[(2, 1)], canvas[(0, 0)]);
+        assert_eq!(grown[(6, 4)], canvas[(4, 3)]);
+        assert_eq!(grown[(1, 1)], Color32::TRANSPARENT);
+
+        let shrunk = canvas.resize_canvas(2, 2, Anchor::BottomRight, Color32::WHITE);
+        assert_eq!(shrunk[(0, 0)], canvas[(3, 2)]);
+        assert_eq!(shrunk[(1, 1)], canvas[(4, 3)]);
+
+        // изображение целиком за пределами нового холста
+        let empty = canvas.resize_canvas(0, 3, Anchor::Center, Color32::WHITE);
+        assert_eq!(empty.size(), [0, 3]);
+    }
+
+    #[test]
+    fn crop_is_clipped_to_the_canvas() {
+        let canvas = sample_canvas();
+        let cropped = canvas.crop(PixelRect::new(3, 2, 10, 10));
+        assert_eq!(cropped.size(), [2, 2]);
+        assert_eq!(cropped[(0, 0)], canvas[(3, 2)]);
+        assert_eq!(canvas.crop(PixelRect::new(6, 0, 2, 2)).size(), [0, 0]);
+    }
+}
@@ -0,0 +1,371 @@
+use egui::{Color32, ColorImage, Pos2};
+
+use super::{BlendMode, Canvas, lerp_color};
+
+#[derive(Default, PartialEq, Clone, Copy, serde::Serialize, serde::Deserialize)]
+/// Способ выборки цвета из текстуры.
+pub enum Sampling {
+    #[default]
+    /// Ближайший тексель
+    Nearest,
+    /// Билинейная интерполяция 4-х соседних текселей
+    Bilinear,
+    /// Трилинейная интерполяция между уровнями mip-пирамиды
+    Mipmap,
+}
+
+impl Sampling {
+    pub fn get_name(&self) -> String {
+        match self {
+            Sampling::Nearest => String::from("ближайший"),
+            Sampling::Bilinear => String::from("билинейная"),
+            Sampling::Mipmap => String::from("mip-уровни"),
+        }
+    }
+}
+
+#[derive(Default, PartialEq, Clone, Copy, serde::Serialize, serde::Deserialize)]
+/// Поведение текстурных координат за пределами [0, 1].
+pub enum AddressMode {
+    #[default]
+    /// Повторение текстуры
+    Wrap,
+    /// Растягивание крайних текселей
+    Clamp,
+    /// Зеркальное повторение
+    Mirror,
+}
+
+impl AddressMode {
+    pub fn get_name(&self) -> String {
+        match self {
+            AddressMode::Wrap => String::from("повтор"),
+            AddressMode::Clamp => String::from("обрезка"),
+            AddressMode::Mirror => String::from("зеркало"),
+        }
+    }
+
+    /// Привести целочисленную координату текселя к диапазону [0, size).
+    fn apply(&self, coord: i32, size: usize) -> usize {
+        let size = size as i32;
+        let coord = match self {
+            AddressMode::Wrap => coord.rem_euclid(size),
+            AddressMode::Clamp => coord.clamp(0, size - 1),
+            AddressMode::Mirror => {
+                let period = coord.rem_euclid(2 * size);
+                if period < size {
+                    period
+                } else {
+                    2 * size - 1 - period
+                }
+            }
+        };
+        coord as usize
+    }
+}
+
+#[derive(Default, PartialEq, Clone, Copy, serde::Serialize, serde::Deserialize)]
+/// Настройки выборки из текстуры.
+pub struct TextureSampler {
+    pub filter: Sampling,
+    pub address: AddressMode,
+}
+
+// =============== Текстура с mip-пирамидой ===============
+
+#[derive(Default, Clone)]
+pub struct Texture {
+    /// Уровни mip-пирамиды, нулевой - исходное изображение.
+    levels: Vec<ColorImage>,
+}
+
+impl Texture {
+    /// Построить текстуру и её mip-пирамиду из изображения.
+    pub fn new(img: &ColorImage) -> Self {
+        let mut levels = Vec::new();
+        if img.width() == 0 || img.height() == 0 {
+            return Self { levels };
+        }
+
+        levels.push(img.clone());
+        loop {
+            let prev = levels.last().unwrap();
+            let [width, height] = prev.size;
+            if width == 1 && height == 1 {
+                break;
+            }
+
+            // каждый тексель следующего уровня - среднее блока 2x2 предыдущего
+            let next_width = (width / 2).max(1);
+            let next_height = (height / 2).max(1);
+            let mut pixels = Vec::with_capacity(next_width * next_height);
+            for y in 0..next_height {
+                for x in 0..next_width {
+                    let x0 = (2 * x).min(width - 1);
+                    let x1 = (2 * x + 1).min(width - 1);
+                    let y0 = (2 * y).min(height - 1);
+                    let y1 = (2 * y + 1).min(height - 1);
+                    let top = lerp_color(prev[(x0, y0)], prev[(x1, y0)], 0.5);
+                    let bottom = lerp_color(prev[(x0, y1)], prev[(x1, y1)], 0.5);
+                    pixels.push(lerp_color(top, bottom, 0.5));
+                }
+            }
+
+            levels.push(ColorImage::new([next_width, next_height], pixels));
+        }
+
+        Self { levels }
+    }
+
+    /// Размеры исходного изображения вида [ширина, высота].
+    pub fn size(&self) -> [usize; 2] {
+        self.levels.first().map_or([0, 0], |level| level.size)
+    }
+
+    /// Выборка цвета из текстуры.
+    /// uv - текстурные координаты, [0, 1] соответствует всей текстуре;
+    /// lod - уровень детализации (log2 числа текселей на пиксель), нужен только для mip-уровней;
+    /// sampler - настройки выборки;
+    pub fn sample(&self, uv: Pos2, lod: f32, sampler: TextureSampler) -> Color32 {
+        if self.levels.is_empty() {
+            return Color32::TRANSPARENT;
+        }
+
+        match sampler.filter {
+            Sampling::Nearest => self.sample_nearest(0, uv, sampler.address),
+            Sampling::Bilinear => self.sample_bilinear(0, uv, sampler.address),
+            Sampling::Mipmap => {
+                let max_level = (self.levels.len() - 1) as f32;
+                let lod = lod.clamp(0.0, max_level);
+                let lower = lod.floor() as usize;
+                let upper = (lower + 1).min(self.levels.len() - 1);
+
+                let color1 = self.sample_bilinear(lower, uv, sampler.address);
+                let color2 = self.sample_bilinear(upper, uv, sampler.address);
+                lerp_color(color1, color2, lod - lower as f32)
+            }
+        }
+    }
+
+    fn sample_nearest(&self, level: usize, uv: Pos2, address: AddressMode) -> Color32 {
+        let img = &self.levels[level];
+        let x = (uv.x * img.width() as f32).floor() as i32;
+        let y = (uv.y * img.height() as f32).floor() as i32;
+        img[(
+            address.apply(x, img.width()),
+            address.apply(y, img.height()),
+        )]
+    }
+
+    fn sample_bilinear(&self, level: usize, uv: Pos2, address: AddressMode) -> Color32 {
+        let img = &self.levels[level];
+
+        // центры текселей находятся в половинных координатах
+        let x = uv.x * img.width() as f32 - 0.5;
+        let y = uv.y * img.height() as f32 - 0.5;
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let tx = x - x0;
+        let ty = y - y0;
+
+        let x0 = x0 as i32;
+        let y0 = y0 as i32;
+        let fetch = |x: i32, y: i32| {
+            img[(
+                address.apply(x, img.width()),
+                address.apply(y, img.height()),
+            )]
+        };
+
+        let top = lerp_color(fetch(x0, y0), fetch(x0 + 1, y0), tx);
+        let bottom = lerp_color(fetch(x0, y0 + 1), fetch(x0 + 1, y0 + 1), tx);
+        lerp_color(top, bottom, ty)
+    }
+}
+
+// =============== Растеризация текстурированного треугольника ===============
+
+impl Canvas {
+    /// Растеризация треугольника с наложением текстуры через барицентрические координаты.
+    /// vertices - 3 точки треугольника;
+    /// uvs - текстурные координаты соответствующих точек;
+    /// texture - накладываемая текстура;
+    /// sampler - настройки выборки из текстуры;
+    pub fn draw_textured_triangle(
+        &mut self,
+        vertices: [Pos2; 3],
+        uvs: [Pos2; 3],
+        texture: &Texture,
+        sampler: TextureSampler,
+    ) {
+        let [pos1, pos2, pos3] = vertices;
+
+        // интерполяция текстурных координат по барицентрическим координатам
+        let interpolate_uv = |(alpha, beta, gamma): (f32, f32, f32)| {
+            Pos2::new(
+                alpha * uvs[0].x + beta * uvs[1].x + gamma * uvs[2].x,
+                alpha * uvs[0].y + beta * uvs[1].y + gamma * uvs[2].y,
+            )
+        };
+        let uv_at = |p: Pos2| {
+            self.compute_barycentric_coords(p, pos1, pos2, pos3)
+                .map(interpolate_uv)
+        };
+
+        // отображение аффинное, поэтому производные uv постоянны на всём треугольнике
+        let Some(uv_origin) = uv_at(Pos2::ZERO) else {
+            return; // вырожденный треугольник
+        };
+        let [tex_width, tex_height] = texture.size();
+        let tex_size = egui::Vec2::new(tex_width as f32, tex_height as f32);
+        let duv_dx = (uv_at(Pos2::new(1.0, 0.0)).unwrap() - uv_origin) * tex_size;
+        let duv_dy = (uv_at(Pos2::new(0.0, 1.0)).unwrap() - uv_origin) * tex_size;
+        let lod = duv_dx
+            .length()
+            .max(duv_dy.length())
+            .max(f32::EPSILON)
+            .log2();
+
+        self.for_each_triangle_pixel(pos1, pos2, pos3, |canvas, x, y, coords| {
+            // полупрозрачные тексели накладываются на прежний цвет
+            let texel = texture.sample(interpolate_uv(coords), lod, sampler);
+            canvas[(x, y)] = BlendMode::Normal.composite(canvas[(x, y)], texel, 1.0);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NEAREST: TextureSampler = TextureSampler {
+        filter: Sampling::Nearest,
+        address: AddressMode::Wrap,
+    };
+
+    /// Текстура 2x2: красный, зелёный / синий, белый.
+    fn quadrants() -> ColorImage {
+        ColorImage::new(
+            [2, 2],
+            vec![Color32::RED, Color32::GREEN, Color32::BLUE, Color32::WHITE],
+        )
+    }
+
+    #[test]
+    fn address_modes() {
+        let wrap: Vec<_> = (-3..5).map(|c| AddressMode::Wrap.apply(c, 3)).collect();
+        assert_eq!(wrap, [0, 1, 2, 0, 1, 2, 0, 1]);
+        let clamp: Vec<_> = (-3..5).map(|c| AddressMode::Clamp.apply(c, 3)).collect();
+        assert_eq!(clamp, [0, 0, 0, 0, 1, 2, 2, 2]);
+        let mirror: Vec<_> = (-3..5).map(|c| AddressMode::Mirror.apply(c, 3)).collect();
+        assert_eq!(mirror, [2, 1, 0, 0, 1, 2, 2, 1]);
+    }
+
+    #[test]
+    fn mip_pyramid_halves_down_to_one_texel() {
+        let pixels = (0..8u8).map(|i| Color32::from_gray(i * 32)).collect();
+        let texture = Texture::new(&ColorImage::new([4, 2], pixels));
+        let sizes: Vec<_> = texture.levels.iter().map(|level| level.size).collect();
+        assert_eq!(sizes, [[4, 2], [2, 1], [1, 1]]);
+        // тексель следующего уровня - среднее блока 2x2
+        assert_eq!(
+            texture.levels[1][(0, 0)],
+            lerp_color(
+                lerp_color(Color32::from_gray(0), Color32::from_gray(32), 0.5),
+                lerp_color(Color32::from_gray(128), Color32::from_gray(160), 0.5),
+                0.5,
+            )
+        );
+
+        assert_eq!(
+            Texture::new(&ColorImage::new([0, 0], Vec::new())).size(),
+            [0, 0]
+        );
+    }
+
+    #[test]
+    fn sampling_at_texel_centers() {
+        let texture = Texture::new(&quadrants());
+        let bilinear = TextureSampler {
+            filter: Sampling::Bilinear,
+            address: AddressMode::Clamp,
+        };
+        let centers = [(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)];
+        for ((u, v), color) in centers.into_iter().zip(quadrants().pixels) {
+            let uv = Pos2::new(u, v);
+            assert_eq!(texture.sample(uv, 0.0, NEAREST), color);
+            assert_eq!(texture.sample(uv, 0.0, bilinear), color);
+        }
+        // повторение текстуры
+        assert_eq!(
+            texture.sample(Pos2::new(1.25, -0.75), 0.0, NEAREST),
+            Color32::RED
+        );
+        // пустая текстура прозрачна
+        let empty = Texture::default();
+        assert_eq!(empty.sample(Pos2::ZERO, 0.0, NEAREST), Color32::TRANSPARENT);
+    }
+
+    #[test]
+    fn textured_triangle_maps_uvs_to_texels() {
+        let mut canvas = Canvas::new(20, 20);
+        let texture = Texture::new(&quadrants());
+        canvas.draw_textured_triangle(
+            [
+                Pos2::new(0.0, 0.0),
+                Pos2::new(40.0, 0.0),
+                Pos2::new(0.0, 40.0),
+            ],
+            [
+                Pos2::new(0.0, 0.0),
+                Pos2::new(2.0, 0.0),
+                Pos2::new(0.0, 2.0),
+            ],
+            &texture,
+            NEAREST,
+        );
+        // каждые 20 пикселей холста - одна копия текстуры
+        assert_eq!(canvas[(2, 2)], Color32::RED);
+        assert_eq!(canvas[(12, 2)], Color32::GREEN);
+        assert_eq!(canvas[(2, 12)], Color32::BLUE);
+        assert_eq!(canvas[(12, 12)], Color32::WHITE);
+    }
+
+    #[test]
+    fn degenerate_triangle_draws_nothing() {
+        let mut canvas = Canvas::new(8, 8);
+        let points = [
+            Pos2::new(1.0, 1.0),
+            Pos2::new(4.0, 4.0),
+            Pos2::new(7.0, 7.0),
+        ];
+        canvas.draw_textured_triangle(points, points, &Texture::new(&quadrants()), NEAREST);
+        assert!(canvas.pixels.iter().all(|&color| color == Color32::WHITE));
+    }
+
+    #[test]
+    fn transparent_texels_keep_the_backdrop() {
+        let mut canvas = Canvas::new(8, 8);
+        let half = Color32::from_rgba_unmultiplied(255, 0, 0, 128);
+        let image = ColorImage::new([2, 1], vec![Color32::TRANSPARENT, half]);
+        canvas.draw_textured_triangle(
+            [
+                Pos2::new(0.0, 0.0),
+                Pos2::new(16.0, 0.0),
+                Pos2::new(0.0, 16.0),
+            ],
+            [
+                Pos2::new(0.0, 0.0),
+                Pos2::new(2.0, 0.0),
+                Pos2::new(0.0, 2.0),
+            ],
+            &Texture::new(&image),
+            NEAREST,
+        );
+        assert_eq!(canvas[(1, 1)], Color32::WHITE);
+        assert_eq!(
+            canvas[(5, 1)],
+            BlendMode::Normal.composite(Color32::WHITE, half, 1.0)
+        );
+    }
+}
@@ -0,0 +1,363 @@
+use egui::{Color32, Pos2, Rect};
+
+use super::resample::color_from_channels;
+use super::{BlendMode, Canvas, PixelRect, Resampling};
+
+/// Аффинное преобразование плоскости:
+/// x' = m[0][0] x + m[0][1] y + m[0][2],
+/// y' = m[1][0] x + m[1][1] y + m[1][2].
+/// Ось y направлена вниз, как на холсте.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Affine {
+    pub m: [[f32; 3]; 2],
+}
+
+impl Default for Affine {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Affine {
+    pub const IDENTITY: Affine = Affine {
+        m: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+    };
+
+    /// Поворот на degrees градусов (по часовой стрелке на экране).
+    pub fn rotation(degrees: f32) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Self {
+            m: [[cos, -sin, 0.0], [sin, cos, 0.0]],
+        }
+    }
+
+    /// Масштабирование с коэффициентами sx и sy.
+    pub fn scale(sx: f32, sy: f32) -> Self {
+        Self {
+            m: [[sx, 0.0, 0.0], [0.0, sy, 0.0]],
+        }
+    }
+
+    /// Сдвиг: x' = x + kx y, y' = y + ky x.
+    pub fn shear(kx: f32, ky: f32) -> Self {
+        Self {
+            m: [[1.0, kx, 0.0], [ky, 1.0, 0.0]],
+        }
+    }
+
+    /// Перенос на (dx, dy).
+    pub fn translation(dx: f32, dy: f32) -> Self {
+        Self {
+            m: [[1.0, 0.0, dx], [0.0, 1.0, dy]],
+        }
+    }
+
+    /// Композиция: сначала self, затем next.
+    pub fn then(&self, next: Affine) -> Self {
+        let [a, b] = self.m;
+        let [c, d] = next.m;
+        let row = |r: [f32; 3]| {
+            [
+                r[0] * a[0] + r[1] * b[0],
+                r[0] * a[1] + r[1] * b[1],
+                r[0] * a[2] + r[1] * b[2] + r[2],
+            ]
+        };
+        Self {
+            m: [row(c), row(d)],
+        }
+    }
+
+    /// Обратное преобразование (None для вырожденного).
+    pub fn inverse(&self) -> Option<Self> {
+        let [[a, b, c], [d, e, f]] = self.m;
+        let det = a * e - b * d;
+        if det.abs() < 1e-8 {
+            return None;
+        }
+        let (a, b, d, e) = (e / det, -b / det, -d / det, a / det);
+        Some(Self {
+            m: [[a, b, -(a * c + b * f)], [d, e, -(d * c + e * f)]],
+        })
+    }
+
+    /// Образ точки p.
+    pub fn apply(&self, p: Pos2) -> Pos2 {
+        let [[a, b, c], [d, e, f]] = self.m;
+        Pos2::new(a * p.x + b * p.y + c, d * p.x + e * p.y + f)
+    }
+
+    /// Ограничивающий прямоугольник образа прямоугольника rect.
+    pub fn bounds(&self, rect: Rect) -> Rect {
+        let corners = [
+            rect.left_top(),
+            rect.right_top(),
+            rect.right_bottom(),
+            rect.left_bottom(),
+        ];
+        Rect::from_points(&corners.map(|corner| self.apply(corner)))
+    }
+}
+
+// =============== Геометрические преобразования ===============
+
+impl Canvas {
+    /// Холст размера width x height, пиксель (x, y) которого берётся из пикселя source(x, y).
+    fn remap(&self, width: usize, height: usize, source: impl Fn(usize, usize) -> usize) -> Canvas {
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.push(self.pixels[source(x, y)]);
+            }
+        }
+        Canvas::from_pixels(width, height, pixels)
+    }
+
+    /// Поворот на 90 градусов по часовой стрелке (без потерь).
+    pub fn rotate_90(&self) -> Canvas {
+        let (w, h) = (self.width, self.height);
+        self.remap(h, w, |x, y| (h - 1 - x) * w + y)
+    }
+
+    /// Поворот на 180 градусов (без потерь).
+    pub fn rotate_180(&self) -> Canvas {
+        let mut result = self.clone();
+        result.pixels.reverse();
+        result.mark_all_dirty();
+        result
+    }
+
+    /// Поворот на 90 градусов против часовой стрелки (без потерь).
+    pub fn rotate_270(&self) -> Canvas {
+        let (w, h) = (self.width, self.height);
+        self.remap(h, w, |x, y| x * w + (w - 1 - y))
+    }
+
+    /// Отражение слева направо (без потерь).
+    pub fn flip_horizontal(&self) -> Canvas {
+        let mut result = self.clone();
+        if self.width > 0 {
+            for row in result.pixels.chunks_exact_mut(self.width) {
+                row.reverse();
+            }
+        }
+        result.mark_all_dirty();
+        result
+    }
+
+    /// Отражение сверху вниз (без потерь).
+    pub fn flip_vertical(&self) -> Canvas {
+        let (w, h) = (self.width, self.height);
+        self.remap(w, h, |x, y| (h - 1 - y) * w + x)
+    }
+
+    /// Интерполированный цвет в точке pos (центр пикселя (x, y) - точка (x + 0.5, y + 0.5)).
+    /// За пределами холста изображение считается прозрачным.
+    /// Возвращает каналы с премультиплицированной альфой в [0, 255].
+    pub fn sample(&self, pos: Pos2, filter: Resampling) -> [f32; 4] {
+        let (x, y) = (pos.x - 0.5, pos.y - 0.5);
+        let pixel = |px: i64, py: i64| -> [f32; 4] {
+            if px < 0 || py < 0 || px >= self.width as i64 || py >= self.height as i64 {
+                [0.0; 4]
+            } else {
+                self[(px as usize, py as usize)]
+                    .to_array()
+                    .map(|c| c as f32)
+            }
+        };
+
+        if filter == Resampling::Nearest {
+            return pixel(x.round() as i64, y.round() as i64);
+        }
+
+        let support = filter.support();
+        let (x0, x1) = ((x - support).ceil() as i64, (x + support).floor() as i64);
+        let (y0, y1) = ((y - support).ceil() as i64, (y + support).floor() as i64);
+        let mut sum = [0.0; 4];
+        let mut total = 0.0;
+        for py in y0..=y1 {
+            let wy = filter.kernel(py as f32 - y);
+            if wy == 0.0 {
+                continue;
+            }
+            for px in x0..=x1 {
+                let weight = wy * filter.kernel(px as f32 - x);
+                let color = pixel(px, py);
+                for c in 0..4 {
+                    sum[c] += color[c] * weight;
+                }
+                total += weight;
+            }
+        }
+        if total.abs() < 1e-6 {
+            return [0.0; 4];
+        }
+        sum.map(|c| c / total)
+    }
+
+    /// Размер результата преобразования affine изображения размера size
+    /// (см. transform): образ всего изображения, если expand, иначе прежний размер.
+    pub fn transformed_size(size: [usize; 2], affine: Affine, expand: bool) -> [usize; 2] {
+        if !expand {
+            return size;
+        }
+        let size = egui::vec2(size[0] as f32, size[1] as f32);
+        let bounds = affine.bounds(Rect::from_center_size(Pos2::ZERO, size));
+        let width = (bounds.width() - 1e-3).ceil().max(1.0) as usize;
+        let height = (bounds.height() - 1e-3).ceil().max(1.0) as usize;
+        [width, height]
+    }
+
+    /// Аффинное преобразование изображения обратным отображением:
+    /// для каждого пикселя результата цвет берётся из прообраза его центра.
+    /// affine задаётся относительно центра изображения; если expand,
+    /// размер результата увеличивается до образа всего изображения,
+    /// иначе остаётся прежним. Вырожденное преобразование даёт прозрачный холст.
+    pub fn transform(&self, affine: Affine, filter: Resampling, expand: bool) -> Canvas {
+        let size = egui::vec2(self.width as f32, self.height as f32);
+        let [width, height] = Canvas::transformed_size(self.size(), affine, expand);
+
+        let mut result = Canvas::new_filled(width, height, Color32::TRANSPARENT);
+        let Some(inverse) = affine.inverse() else {
+            return result;
+        };
+        // координаты результата -> относительно его центра -> прообраз -> координаты источника
+        let to_source = Affine::translation(-(width as f32) / 2.0, -(height as f32) / 2.0)
+            .then(inverse)
+            .then(Affine::translation(size.x / 2.0, size.y / 2.0));
+
+        for y in 0..height {
+            for x in 0..width {
+                let source = to_source.apply(Pos2::new(x as f32 + 0.5, y as f32 + 0.5));
+                result.pixels[y * width + x] = color_from_channels(self.sample(source, filter));
+            }
+        }
+        result
+    }
+
+    /// Преобразовать часть изображения внутри rect: область вырезается
+    /// (остаётся прозрачной), преобразуется функцией map и накладывается обратно
+    /// с совмещением центров. Возвращает область, занятую результатом на холсте.
+    pub fn map_region(
+        &mut self,
+        rect: PixelRect,
+        map: impl FnOnce(&Canvas) -> Canvas,
+    ) -> Option<PixelRect> {
+        let rect = rect.intersect(self.bounds())?;
+        let region = map(&self.crop(rect));
+        self.fill_region(rect, Color32::TRANSPARENT);
+
+        let [width, height] = region.size();
+        let x = rect.x as i64 + (rect.width as i64 - width as i64) / 2;
+        let y = rect.y as i64 + (rect.height as i64 - height as i64) / 2;
+        self.overlay(&region, [x, y])
+    }
+
+    /// Наложить изображение image левым верхним углом в точку pos (обычное смешивание).
+    /// Возвращает изменённую область холста.
+    pub fn overlay(&mut self, image: &Canvas, pos: [i64; 2]) -> Option<PixelRect> {
+        let left = pos[0].max(0);
+        let top = pos[1].max(0);
+        let right = (pos[0] + image.width as i64).min(self.width as i64);
+        let bottom = (pos[1] + image.height as i64).min(self.height as i64);
+        if left >= right || top >= bottom {
+            return None;
+        }
+
+        let rect = PixelRect::new(
+            left as usize,
+            top as usize,
+            (right - left) as usize,
+            (bottom - top) as usize,
+        );
+        for y in rect.y..rect.y + rect.height {
+            let src =
+                (y as i64 - pos[1]) as usize * image.width + (rect.x as i64 - pos[0]) as usize;
+            let dst = y * self.width + rect.x;
+            let row = self.pixels[dst..dst + rect.width].iter_mut();
+            for (backdrop, &source) in row.zip(&image.pixels[src..src + rect.width]) {
+                *backdrop = BlendMode::Normal.composite(*backdrop, source, 1.0);
+            }
+        }
+        self.mark_dirty(rect);
+        Some(rect)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Холст 5x3 с различными (в том числе полупрозрачными) пикселями.
+    fn sample_canvas() -> Canvas {
+        let pixels = (0..15u8)
+            .map(|i| Color32::from_rgba_unmultiplied(i * 17, 255 - i * 13, i * 5, 40 + i * 14))
+            .collect();
+        Canvas::from_pixels(5, 3, pixels)
+    }
+
+    fn assert_close(a: Affine, b: Affine) {
+        for (row_a, row_b) in a.m.iter().zip(&b.m) {
+            for (x, y) in row_a.iter().zip(row_b) {
+                assert!((x - y).abs() < 1e-4, "{a:?} != {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn composition_with_inverse_is_identity() {
+        let affines = [
+            Affine::rotation(37.0),
+            Affine::scale(2.0, -0.5),
+            Affine::shear(0.3, -1.2),
+            Affine::translation(4.0, -7.5),
+            Affine::rotation(-120.0)
+                .then(Affine::scale(1.5, 3.0))
+                .then(Affine::translation(-2.0, 9.0)),
+        ];
+        for a in affines {
+            let inverse = a.inverse().unwrap();
+            assert_close(a.then(inverse), Affine::IDENTITY);
+            assert_close(inverse.then(a), Affine::IDENTITY);
+        }
+        assert!(Affine::scale(0.0, 1.0).inverse().is_none());
+    }
+
+    #[test]
+    fn then_applies_self_first() {
+        let a = Affine::translation(1.0, 0.0).then(Affine::scale(2.0, 2.0));
+        assert_eq!(a.apply(Pos2::new(1.0, 1.0)), Pos2::new(4.0, 2.0));
+    }
+
+    #[test]
+    fn four_quarter_turns_round_trip() {
+        let canvas = sample_canvas();
+        let turned = canvas.rotate_90();
+        assert_eq!(turned.size(), [3, 5]);
+        // левый нижний угол переходит в левый верхний
+        assert_eq!(turned[(0, 0)], canvas[(0, 2)]);
+
+        let back = turned.rotate_90().rotate_90().rotate_90();
+        assert!(back.pixels == canvas.pixels);
+        assert!(canvas.rotate_270().pixels == turned.rotate_180().pixels);
+    }
+
+    #[test]
+    fn flips_are_involutions() {
+        let canvas = sample_canvas();
+        assert!(canvas.flip_horizontal().flip_horizontal().pixels == canvas.pixels);
+        assert!(canvas.flip_vertical().flip_vertical().pixels == canvas.pixels);
+        let both = canvas.flip_horizontal().flip_vertical();
+        assert!(both.pixels == canvas.rotate_180().pixels);
+    }
+
+    #[test]
+    fn identity_transform_reproduces_input() {
+        let canvas = sample_canvas();
+        for expand in [false, true] {
+            let result = canvas.transform(Affine::IDENTITY, Resampling::Nearest, expand);
+            assert_eq!(result.size(), canvas.size());
+            assert!(result.pixels == canvas.pixels);
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use egui::{Color32, Pos2, Vec2};
+
+use super::Canvas;
+
+/// Векторное произведение (z-компонента).
+fn cross(a: Vec2, b: Vec2) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+/// Удвоенная ориентированная площадь контура.
+fn signed_area(vertices: &[Pos2], contour: &[usize]) -> f32 {
+    let mut area = 0.0;
+    for i in 0..contour.len() {
+        let a = vertices[contour[i]];
+        let b = vertices[contour[(i + 1) % contour.len()]];
+        area += a.x * b.y - b.x * a.y;
+    }
+    area
+}
+
+/// Лежит ли точка p внутри (или на границе) треугольника abc с положительной ориентацией.
+fn point_in_triangle(p: Pos2, a: Pos2, b: Pos2, c: Pos2) -> bool {
+    cross(b - a, p - a) >= 0.0 && cross(c - b, p - b) >= 0.0 && cross(a - c, p - c) >= 0.0
+}
+
+/// Вставить дыру в контур через мостовое ребро от самой правой вершины дыры
+/// до видимой из неё вершины контура.
+fn bridge_hole(vertices: &[Pos2], polygon: &mut Vec<usize>, hole: &[usize]) {
+    // самая правая вершина дыры
+    let (hole_start, &m) = hole
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| vertices[**a].x.total_cmp(&vertices[**b].x))
+        .unwrap();
+    let m_pos = vertices[m];
+
+    // ближайшее пересечение горизонтального луча из m с рёбрами контура
+    let mut nearest: Option<(f32, usize)> = None;
+    for i in 0..polygon.len() {
+        let a = vertices[polygon[i]];
+        let b = vertices[polygon[(i + 1) % polygon.len()]];
+        if (a.y > m_pos.y) == (b.y > m_pos.y) {
+            continue;
+        }
+
+        let x = a.x + (m_pos.y - a.y) * (b.x - a.x) / (b.y - a.y);
+        if x >= m_pos.x && nearest.is_none_or(|(best_x, _)| x < best_x) {
+            // кандидат на мост - конец ребра с наибольшим x
+            let candidate = if a.x > b.x {
+                i
+            } else {
+                (i + 1) % polygon.len()
+            };
+            nearest = Some((x, candidate));
+        }
+    }
+    let Some((intersection_x, mut bridge)) = nearest else {
+        return;
+    };
+
+    // если внутри треугольника (m, пересечение, кандидат) есть вершины контура,
+    // они загораживают кандидата - берём вершину с наименьшим углом к лучу
+    let i_pos = Pos2::new(intersection_x, m_pos.y);
+    let p_pos = vertices[polygon[bridge]];
+    let (a, b, c) = if cross(i_pos - m_pos, p_pos - m_pos) >= 0.0 {
+        (m_pos, i_pos, p_pos)
+    } else {
+        (m_pos, p_pos, i_pos)
+    };
+    let mut best_angle = f32::INFINITY;
+    for (i, &v) in polygon.iter().enumerate() {
+        let v_pos = vertices[v];
+        if i == bridge || v_pos == p_pos || !point_in_triangle(v_pos, a, b, c) {
+            continue;
+        }
+        let dir = v_pos - m_pos;
+        let angle = dir.y.abs().atan2(dir.x);
+        if angle < best_angle {
+            best_angle = angle;
+            bridge = i;
+        }
+    }
+
+    // контур: ..., P, M, (обход дыры), M, P, ...
+    let mut merged = Vec::with_capacity(polygon.len() + hole.len() + 2);
+    merged.extend_from_slice(&polygon[..=bridge]);
+    for k in 0..=hole.len() {
+        merged.push(hole[(hole_start + k) % hole.len()]);
+    }
+    merged.extend_from_slice(&polygon[bridge..]);
+    *polygon = merged;
+}
+
+/// Триангуляция многоугольника с дырами методом отсечения ушей.
+/// contours - первый контур внешний, остальные - дыры;
+/// Возвращает тройки индексов вершин, вершины нумеруются подряд по всем контурам.
+pub fn triangulate(contours: &[Vec<Pos2>]) -> Vec<[usize; 3]> {
+    let vertices: Vec<Pos2> = contours.iter().flatten().copied().collect();
+    let mut triangles = Vec::new();
+    let Some((outer, holes)) = contours.split_first() else {
+        return triangles;
+    };
+
+    // индексы вершин каждого контура
+    let mut offset = outer.len();
+    let mut polygon: Vec<usize> = (0..outer.len()).collect();
+    let mut hole_indices: Vec<Vec<usize>> = Vec::with_capacity(holes.len());
+    for hole in holes {
+        hole_indices.push((offset..offset + hole.len()).collect());
+        offset += hole.len();
+    }
+
+    // внешний контур с положительной ориентацией, дыры - с отрицательной
+    if signed_area(&vertices, &polygon) < 0.0 {
+        polygon.reverse();
+    }
+    hole_indices.retain(|hole| hole.len() >= 3);
+    for hole in hole_indices.iter_mut() {
+        if signed_area(&vertices, hole) > 0.0 {
+            hole.reverse();
+        }
+    }
+
+    // дыры вставляются по убыванию самой правой вершины
+    let max_x = |hole: &Vec<usize>| {
+        hole.iter()
+            .map(|&i| vertices[i].x)
+            .fold(f32::NEG_INFINITY, f32::max)
+    };
+    hole_indices.sort_by(|a, b| max_x(b).total_cmp(&max_x(a)));
+    for hole in &hole_indices {
+        bridge_hole(&vertices, &mut polygon, hole);
+    }
+
+    // отсечение ушей
+    while polygon.len() > 3 {
+        let n = polygon.len();
+        let is_ear = |i: usize| {
+            let (prev, cur, next) = (polygon[(i + n - 1) % n], polygon[i], polygon[(i + 1) % n]);
+            let (a, b, c) = (vertices[prev], vertices[cur], vertices[next]);
+            if cross(b - a, c - b) <= 0.0 {
+                return false; // вершина невыпуклая
+            }
+            polygon.iter().all(|&v| {
+                let p = vertices[v];
+                p == a || p == b || p == c || !point_in_triangle(p, a, b, c)
+            })
+        };
+
+        // для вырожденных контуров уха может не найтись - отсекаем любую вершину
+        let ear = (0..n).find(|&i| is_ear(i)).unwrap_or(0);
+        triangles.push([
+            polygon[(ear + n - 1) % n],
+            polygon[ear],
+            polygon[(ear + 1) % n],
+        ]);
+        polygon.remove(ear);
+    }
+    if polygon.len() == 3 {
+        triangles.push([polygon[0], polygon[1], polygon[2]]);
+    }
+
+    triangles
+}
+
+// =============== Градиентная заливка через триангуляцию ===============
+
+impl Canvas {
+    /// Градиентная заливка многоугольника через триангуляцию и растеризацию треугольников.
+    /// contours - первый контур внешний, остальные - дыры;
+    /// colors - цвета вершин, по одному на каждую точку контуров;
+    pub fn draw_gradient_polygon(&mut self, contours: &[Vec<Pos2>], colors: &[Vec<Color32>]) {
+        let vertices: Vec<Pos2> = contours.iter().flatten().copied().collect();
+        let vertex_colors: Vec<Color32> = colors.iter().flatten().copied().collect();
+
+        for [i, j, k] in triangulate(contours) {
+            self.draw_gradient_triangle(
+                vertices[i],
+                vertices[j],
+                vertices[k],
+                vertex_colors[i],
+                vertex_colors[j],
+                vertex_colors[k],
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::pos2;
+
+    use super::*;
+
+    fn square(x0: f32, y0: f32, x1: f32, y1: f32) -> Vec<Pos2> {
+        vec![pos2(x0, y0), pos2(x1, y0), pos2(x1, y1), pos2(x0, y1)]
+    }
+
+    /// Площади треугольников (без знака) и сумма площадей.
+    fn areas(contours: &[Vec<Pos2>], triangles: &[[usize; 3]]) -> (Vec<f32>, f32) {
+        let vertices: Vec<Pos2> = contours.iter().flatten().copied().collect();
+        let areas: Vec<f32> = triangles
+            .iter()
+            .map(|&[a, b, c]| cross(vertices[b] - vertices[a], vertices[c] - vertices[a]) / 2.0)
+            .collect();
+        let total = areas.iter().map(|a| a.abs()).sum();
+        (areas, total)
+    }
+
+    #[test]
+    fn convex_polygon_in_either_direction() {
+        let mut contour = vec![
+            pos2(0.0, 0.0),
+            pos2(4.0, 0.0),
+            pos2(6.0, 3.0),
+            pos2(3.0, 6.0),
+            pos2(0.0, 4.0),
+        ];
+        for _ in 0..2 {
+            let contours = [contour.clone()];
+            let triangles = triangulate(&contours);
+            assert_eq!(triangles.len(), 3);
+            let (_, total) = areas(&contours, &triangles);
+            let expected = signed_area(&contour, &[0, 1, 2, 3, 4]).abs() / 2.0;
+            assert!((total - expected).abs() < 1e-4);
+            contour.reverse();
+        }
+    }
+
+    #[test]
+    fn concave_polygon_area_is_preserved() {
+        // буква L
+        let contour = vec![
+            pos2(0.0, 0.0),
+            pos2(2.0, 0.0),
+            pos2(2.0, 4.0),
+            pos2(5.0, 4.0),
+            pos2(5.0, 6.0),
+            pos2(0.0, 6.0),
+        ];
+        let contours = [contour];
+        let triangles = triangulate(&contours);
+        assert_eq!(triangles.len(), 4);
+        let (areas, total) = areas(&contours, &triangles);
+        assert!((total - 18.0).abs() < 1e-4);
+        // все треугольники ориентированы одинаково, то есть не перекрываются
+        assert!(areas.iter().all(|&a| a > 0.0) || areas.iter().all(|&a| a < 0.0));
+    }
+
+    #[test]
+    fn holes_are_left_uncovered() {
+        let contours = [
+            square(0.0, 0.0, 10.0, 10.0),
+            square(2.0, 2.0, 4.0, 4.0),
+            square(6.0, 5.0, 8.0, 8.0),
+        ];
+        let triangles = triangulate(&contours);
+        // n - 2 + 2 * (число дыр)
+        assert_eq!(triangles.len(), 12 - 2 + 2 * 2);
+        let (_, total) = areas(&contours, &triangles);
+        assert!((total - (100.0 - 4.0 - 6.0)).abs() < 1e-3);
+
+        let vertices: Vec<Pos2> = contours.iter().flatten().copied().collect();
+        for &[a, b, c] in &triangles {
+            let center =
+                (vertices[a].to_vec2() + vertices[b].to_vec2() + vertices[c].to_vec2()) / 3.0;
+            let in_hole = |x0: f32, y0: f32, x1: f32, y1: f32| {
+                center.x > x0 && center.x < x1 && center.y > y0 && center.y < y1
+            };
+            assert!(!in_hole(2.0, 2.0, 4.0, 4.0) && !in_hole(6.0, 5.0, 8.0, 8.0));
+        }
+    }
+
+    #[test]
+    fn degenerate_input() {
+        assert!(triangulate(&[]).is_empty());
+        assert!(triangulate(&[vec![pos2(0.0, 0.0), pos2(1.0, 1.0)]]).is_empty());
+        // дыра из двух точек игнорируется
+        let contours = [
+            square(0.0, 0.0, 2.0, 2.0),
+            vec![pos2(1.0, 1.0), pos2(1.5, 1.0)],
+        ];
+        assert_eq!(triangulate(&contours).len(), 2);
+    }
+}
@@ -0,0 +1,347 @@
+use super::ColorsApp;
+use super::canvas::{Canvas, EdgeMode, EdgeOperator, Kernel};
+
+#[derive(PartialEq, Clone, Copy)]
+/// Фильтр из меню Filters.
+pub enum Filter {
+    BoxBlur,
+    GaussianBlur,
+    Sharpen,
+    UnsharpMask,
+    Emboss,
+    /// Свёртка с ядром, заданным пользователем
+    Custom,
+    /// Модуль градиента операторами Собеля, Прюитт или Щарра
+    EdgeDetect,
+    LaplacianOfGaussian,
+    Canny,
+}
+
+impl Filter {
+    pub const ALL: [Filter; 9] = [
+        Filter::BoxBlur,
+        Filter::GaussianBlur,
+        Filter::Sharpen,
+        Filter::UnsharpMask,
+        Filter::Emboss,
+        Filter::Custom,
+        Filter::EdgeDetect,
+        Filter::LaplacianOfGaussian,
+        Filter::Canny,
+    ];
+
+    /// Надпись пункта меню и заголовок окна.
+    pub fn get_label(&self) -> &'static str {
+        match self {
+            Filter::BoxBlur => "Box Blur",
+            Filter::GaussianBlur => "Gaussian Blur",
+            Filter::Sharpen => "Sharpen",
+            Filter::UnsharpMask => "Unsharp Mask",
+            Filter::Emboss => "Emboss",
+            Filter::Custom => "Custom Kernel",
+            Filter::EdgeDetect => "Edge Detect",
+            Filter::LaplacianOfGaussian => "Laplacian of Gaussian",
+            Filter::Canny => "Canny Edges",
+        }
+    }
+
+    /// Выделение границ: результат - карта границ вместо изображения.
+    pub fn is_edge_detection(&self) -> bool {
+        matches!(
+            self,
+            Filter::EdgeDetect | Filter::LaplacianOfGaussian | Filter::Canny
+        )
+    }
+}
+
+/// Состояние окна фильтра.
+pub struct FilterDialog {
+    filter: Filter,
+    /// Радиус усреднения
+    radius: usize,
+    /// Стандартное отклонение размытия по Гауссу
+    sigma: f32,
+    /// Сила повышения резкости
+    amount: f32,
+    /// Порог нерезкого маскирования (разности меньше порога не усиливаются)
+    threshold: f32,
+    edge: EdgeMode,
+    /// Размеры пользовательского ядра [ширина, высота]
+    custom_size: [usize; 2],
+    /// Веса пользовательского ядра по строкам
+    custom_weights: Vec<f32>,
+    /// Делитель весов пользовательского ядра
+    divisor: f32,
+    /// Добавка к цветам для пользовательского ядра
+    bias: f32,
+    /// Оператор градиента для выделения границ
+    operator: EdgeOperator,
+    /// Бинарная карта границ вместо карты величин
+    binary: bool,
+    /// Порог бинарной карты границ
+    edge_threshold: f32,
+    /// Нижний и верхний пороги детектора Кэнни
+    canny_thresholds: [f32; 2],
+}
+
+impl FilterDialog {
+    /// Наибольшая сторона пользовательского ядра
+    const MAX_CUSTOM_SIZE: usize = 9;
+
+    pub fn new(filter: Filter) -> Self {
+        #[rustfmt::skip]
+        let identity = vec![
+            0.0, 0.0, 0.0,
+            0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0,
+        ];
+        Self {
+            filter,
+            radius: 1,
+            sigma: 1.5,
+            amount: 1.0,
+            threshold: 0.0,
+            edge: EdgeMode::default(),
+            custom_size: [3, 3],
+            custom_weights: identity,
+            divisor: 1.0,
+            bias: 0.0,
+            operator: EdgeOperator::default(),
+            binary: true,
+            edge_threshold: 64.0,
+            canny_thresholds: [20.0, 50.0],
+        }
+    }
+
+    /// Пользовательское ядро с учётом делителя и добавки.
+    fn custom_kernel(&self) -> Option<Kernel> {
+        let [width, height] = self.custom_size;
+        Kernel::new(width, height, self.custom_weights.clone())
+            .map(|kernel| kernel.normalized(self.divisor).with_bias(self.bias))
+    }
+
+    /// Результат фильтра для холста canvas.
+    fn apply(&self, canvas: &Canvas) -> Canvas {
+        match self.filter {
+            Filter::BoxBlur => canvas.convolve(&Kernel::box_blur(self.radius), self.edge),
+            Filter::GaussianBlur => canvas.convolve(&Kernel::gaussian(self.sigma), self.edge),
+            Filter::Sharpen => canvas.convolve(&Kernel::sharpen(self.amount), self.edge),
+            Filter::UnsharpMask => {
+                canvas.unsharp_mask(self.sigma, self.amount, self.threshold, self.edge)
+            }
+            Filter::Emboss => canvas.convolve(&Kernel::emboss(), self.edge),
+            Filter::Custom => match self.custom_kernel() {
+                Some(kernel) => canvas.convolve(&kernel, self.edge),
+                None => canvas.clone(),
+            },
+            Filter::EdgeDetect => {
+                canvas.edge_magnitude(self.operator, self.binary.then_some(self.edge_threshold))
+            }
+            Filter::LaplacianOfGaussian => {
+                canvas.laplacian_of_gaussian(self.sigma, self.binary.then_some(self.edge_threshold))
+            }
+            Filter::Canny => {
+                let [low, high] = self.canny_thresholds;
+                canvas.canny(self.sigma, low, high)
+            }
+        }
+    }
+
+    /// Изменить размер пользовательского ядра, сохранив веса в пересечении.
+    fn resize_custom(&mut self, size: [usize; 2]) {
+        let [old_width, old_height] = self.custom_size;
+        let mut weights = vec![0.0; size[0] * size[1]];
+        for y in 0..size[1].min(old_height) {
+            for x in 0..size[0].min(old_width) {
+                weights[y * size[0] + x] = self.custom_weights[y * old_width + x];
+            }
+        }
+        self.custom_size = size;
+        self.custom_weights = weights;
+    }
+
+    /// Редактор пользовательского ядра.
+    fn show_custom_editor(&mut self, ui: &mut egui::Ui) {
+        let mut size = self.custom_size;
+        ui.horizontal(|ui| {
+            ui.label("размер:");
+            for side in &mut size {
+                ui.add(egui::DragValue::new(side).range(1..=Self::MAX_CUSTOM_SIZE));
+            }
+        });
+        if size != self.custom_size {
+            self.resize_custom(size);
+        }
+
+        let width = self.custom_size[0];
+        egui::Grid::new("custom_kernel_grid").show(ui, |ui| {
+            for (i, weight) in self.custom_weights.iter_mut().enumerate() {
+                ui.add(egui::DragValue::new(weight).speed(0.1).max_decimals(3));
+                if i % width == width - 1 {
+                    ui.end_row();
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("делитель:");
+            ui.add(egui::DragValue::new(&mut self.divisor).speed(0.1));
+            if ui
+                .button("Σ")
+                .on_hover_text("делитель равен сумме весов")
+                .clicked()
+            {
+                let sum: f32 = self.custom_weights.iter().sum();
+                self.divisor = if sum.abs() > 1e-6 { sum } else { 1.0 };
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("добавка:");
+            ui.add(egui::DragValue::new(&mut self.bias).range(-255.0..=255.0));
+        });
+
+        if let Some(kernel) = self.custom_kernel() {
+            let note = if kernel.is_separable() {
+                "ядро разделимое - два одномерных прохода"
+            } else {
+                "ядро неразделимое"
+            };
+            ui.label(note);
+        }
+    }
+
+    /// Выбор бинарной карты границ и её порога.
+    fn show_edge_threshold(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.binary, "бинарная карта")
+            .on_hover_text("иначе результат - величина перепада в оттенках серого");
+        if self.binary {
+            ui.horizontal(|ui| {
+                ui.label("порог:");
+                ui.add(egui::Slider::new(&mut self.edge_threshold, 0.0..=255.0));
+            });
+        }
+    }
+}
+
+// =============== Фильтры ===============
+
+impl ColorsApp {
+    /// Отображает окно настройки выбранного фильтра (Filters -> ...).
+    /// Фильтр применяется к активному слою или к выделению на нём.
+    pub(super) fn show_filter_window(&mut self, ctx: &egui::Context) {
+        let Some(mut dialog) = self.filter_dialog.take() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut apply = false;
+        egui::Window::new(dialog.filter.get_label())
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                match dialog.filter {
+                    Filter::BoxBlur => {
+                        ui.horizontal(|ui| {
+                            ui.label("радиус:");
+                            ui.add(egui::Slider::new(&mut dialog.radius, 1..=50));
+                        });
+                    }
+                    Filter::GaussianBlur => {
+                        ui.horizontal(|ui| {
+                            ui.label("сигма:");
+                            ui.add(egui::Slider::new(&mut dialog.sigma, 0.3..=20.0));
+                        });
+                    }
+                    Filter::Sharpen => {
+                        ui.horizontal(|ui| {
+                            ui.label("сила:");
+                            ui.add(egui::Slider::new(&mut dialog.amount, 0.0..=5.0));
+                        });
+                    }
+                    Filter::UnsharpMask => {
+                        ui.horizontal(|ui| {
+                            ui.label("сигма:");
+                            ui.add(egui::Slider::new(&mut dialog.sigma, 0.3..=20.0));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("сила:");
+                            ui.add(egui::Slider::new(&mut dialog.amount, 0.0..=5.0));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("порог:");
+                            ui.add(egui::Slider::new(&mut dialog.threshold, 0.0..=255.0));
+                        });
+                    }
+                    Filter::Emboss => {}
+                    Filter::Custom => dialog.show_custom_editor(ui),
+                    Filter::EdgeDetect => {
+                        ui.horizontal(|ui| {
+                            ui.label("оператор:");
+                            egui::ComboBox::from_id_salt("edge_operator_combo_box")
+                                .selected_text(dialog.operator.get_name())
+                                .show_ui(ui, |ui| {
+                                    for operator in EdgeOperator::ALL {
+                                        ui.selectable_value(
+                                            &mut dialog.operator,
+                                            operator,
+                                            operator.get_name(),
+                                        );
+                                    }
+                                });
+                        });
+                        dialog.show_edge_threshold(ui);
+                    }
+                    Filter::LaplacianOfGaussian => {
+                        ui.horizontal(|ui| {
+                            ui.label("сигма:");
+                            ui.add(egui::Slider::new(&mut dialog.sigma, 0.5..=10.0));
+                        });
+                        dialog.show_edge_threshold(ui);
+                    }
+                    Filter::Canny => {
+                        ui.horizontal(|ui| {
+                            ui.label("сигма:");
+                            ui.add(egui::Slider::new(&mut dialog.sigma, 0.0..=10.0));
+                        });
+                        let [low, high] = &mut dialog.canny_thresholds;
+                        ui.horizontal(|ui| {
+                            ui.label("нижний порог:");
+                            ui.add(egui::Slider::new(low, 0.0..=255.0));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("верхний порог:");
+                            ui.add(egui::Slider::new(high, 0.0..=255.0));
+                        });
+                        *low = low.min(*high);
+                    }
+                }
+
+                if dialog.filter.is_edge_detection() {
+                    ui.label("контуры на карте границ обводятся инструментом Border");
+                } else {
+                    ui.horizontal(|ui| {
+                        ui.label("за краем:");
+                        egui::ComboBox::from_id_salt("edge_mode_combo_box")
+                            .selected_text(dialog.edge.get_name())
+                            .show_ui(ui, |ui| {
+                                for edge in EdgeMode::ALL {
+                                    ui.selectable_value(&mut dialog.edge, edge, edge.get_name());
+                                }
+                            });
+                    });
+                }
+
+                apply = ui.button("Apply").clicked();
+            });
+
+        if apply {
+            self.map_active_layer(ctx, |canvas| dialog.apply(canvas));
+
+            #[cfg(debug_assertions)]
+            println!("применён фильтр {}", dialog.filter.get_label());
+        } else if open {
+            self.filter_dialog = Some(dialog);
+        }
+    }
+}
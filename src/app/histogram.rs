@@ -0,0 +1,165 @@
+use egui::Color32;
+
+use super::ColorsApp;
+use super::canvas::{Histogram, PixelRect};
+
+#[derive(Default, PartialEq, Clone, Copy)]
+/// Гистограмма, отображаемая на панели.
+pub enum HistogramChannel {
+    #[default]
+    Luminance,
+    Red,
+    Green,
+    Blue,
+    /// Каналы R, G и B поверх друг друга
+    Rgb,
+}
+
+impl HistogramChannel {
+    pub const ALL: [HistogramChannel; 5] = [
+        HistogramChannel::Luminance,
+        HistogramChannel::Red,
+        HistogramChannel::Green,
+        HistogramChannel::Blue,
+        HistogramChannel::Rgb,
+    ];
+
+    pub fn get_name(&self) -> String {
+        match self {
+            HistogramChannel::Luminance => String::from("яркость"),
+            HistogramChannel::Red => String::from("красный"),
+            HistogramChannel::Green => String::from("зелёный"),
+            HistogramChannel::Blue => String::from("синий"),
+            HistogramChannel::Rgb => String::from("RGB"),
+        }
+    }
+
+    /// Столбцы гистограммы и их цвета.
+    fn bins<'a>(&self, histogram: &'a Histogram) -> Vec<(&'a [u32; 256], Color32)> {
+        let red = (
+            &histogram.red,
+            Color32::from_rgba_unmultiplied(255, 0, 0, 128),
+        );
+        let green = (
+            &histogram.green,
+            Color32::from_rgba_unmultiplied(0, 200, 0, 128),
+        );
+        let blue = (
+            &histogram.blue,
+            Color32::from_rgba_unmultiplied(0, 0, 255, 128),
+        );
+        match self {
+            HistogramChannel::Luminance => vec![(&histogram.luminance, Color32::DARK_GRAY)],
+            HistogramChannel::Red => vec![red],
+            HistogramChannel::Green => vec![green],
+            HistogramChannel::Blue => vec![blue],
+            HistogramChannel::Rgb => vec![red, green, blue],
+        }
+    }
+}
+
+/// Состояние панели гистограммы.
+#[derive(Default)]
+pub struct HistogramPanel {
+    channel: HistogramChannel,
+    /// Высота столбцов пропорциональна логарифму числа пикселей
+    logarithmic: bool,
+    histogram: Option<Histogram>,
+    /// Номер слоя и выделение, по которым построена гистограмма
+    source: Option<(usize, Option<PixelRect>)>,
+    /// Изображение, слой или выделение изменились после построения гистограммы
+    stale: bool,
+}
+
+// =============== Гистограмма ===============
+
+impl ColorsApp {
+    /// Высота графика гистограммы
+    const HISTOGRAM_HEIGHT: f32 = 100.0;
+
+    /// Отображает гистограмму активного слоя (или выделения на нём).
+    /// Гистограмма пересчитывается после изменения изображения (image_changed),
+    /// смены активного слоя или выделения - по завершении действия (отпускании
+    /// кнопок мыши), чтобы не пересчитывать её в каждом кадре мазка.
+    pub(super) fn show_histogram_panel(&mut self, ui: &mut egui::Ui, image_changed: bool) {
+        let source = (self.layers.active_index(), self.selection());
+        let pointer_down = ui.input(|i| i.pointer.any_down());
+        let panel = &mut self.histogram_panel;
+        panel.stale |= image_changed || panel.source != Some(source);
+        if panel.histogram.is_none() || (panel.stale && !pointer_down) {
+            panel.histogram = Some(self.layers.active().canvas.histogram(source.1));
+            panel.source = Some(source);
+            panel.stale = false;
+        }
+
+        ui.heading("Histogram");
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("histogram_channel_combo_box")
+                .selected_text(panel.channel.get_name())
+                .show_ui(ui, |ui| {
+                    for channel in HistogramChannel::ALL {
+                        ui.selectable_value(&mut panel.channel, channel, channel.get_name());
+                    }
+                });
+            ui.checkbox(&mut panel.logarithmic, "лог. шкала");
+        });
+
+        let Some(histogram) = &panel.histogram else {
+            return;
+        };
+        let bins = panel.channel.bins(histogram);
+        let scale = |n: u32| {
+            if panel.logarithmic {
+                (n as f32 + 1.0).ln()
+            } else {
+                n as f32
+            }
+        };
+        let max = bins
+            .iter()
+            .flat_map(|(bins, _)| bins.iter())
+            .map(|&n| scale(n))
+            .fold(0.0, f32::max);
+
+        let size = egui::vec2(ui.available_width(), ColorsApp::HISTOGRAM_HEIGHT);
+        let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+        let rect = response.rect;
+        painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+        let column = rect.width() / 256.0;
+        if max > 0.0 {
+            for (bins, color) in &bins {
+                for (v, &n) in bins.iter().enumerate() {
+                    let height = scale(n) / max * rect.height();
+                    let left = rect.left() + v as f32 * column;
+                    painter.rect_filled(
+                        egui::Rect::from_min_max(
+                            egui::pos2(left, rect.bottom() - height),
+                            egui::pos2(left + column, rect.bottom()),
+                        ),
+                        0.0,
+                        *color,
+                    );
+                }
+            }
+        }
+
+        // значение столбца под курсором
+        if let Some(pos) = response.hover_pos() {
+            let v = (((pos.x - rect.left()) / column) as usize).min(255);
+            let counts: Vec<String> = bins.iter().map(|(bins, _)| bins[v].to_string()).collect();
+            response.on_hover_text(format!("уровень {v}: {}", counts.join(" / ")));
+        }
+
+        // статистика по яркости для RGB
+        let stats = match panel.channel {
+            HistogramChannel::Rgb => &histogram.luminance,
+            _ => bins[0].0,
+        };
+        ui.label(format!("пикселей: {}", histogram.count));
+        ui.label(format!(
+            "среднее: {:.1}, медиана: {}",
+            histogram.mean(stats),
+            histogram.median(stats)
+        ));
+    }
+}
@@ -0,0 +1,412 @@
+use std::collections::VecDeque;
+
+use egui::Color32;
+
+use super::canvas::{Canvas, PixelRect};
+use super::layers::LayerStack;
+
+/// Снимок состояния слоёв, к которому возвращает отмена или повтор действия.
+pub enum Snapshot {
+    /// Полная копия слоёв (пока действие не завершено, или если действие
+    /// изменило число или размер слоёв)
+    Full(LayerStack),
+    /// Свойства слоёв (стопка без пикселей) и прежние пиксели только
+    /// изменённых действием областей: номер слоя, область и её содержимое
+    Regions {
+        properties: LayerStack,
+        regions: Vec<(usize, PixelRect, Canvas)>,
+    },
+}
+
+impl Snapshot {
+    /// Сжать полный снимок состояния до действия, оставив только области,
+    /// которые действие изменило; after - слои после действия.
+    fn compact(self, after: &LayerStack) -> Snapshot {
+        let Snapshot::Full(before) = self else {
+            return self;
+        };
+        if before.size() != after.size() || before.layers().len() != after.layers().len() {
+            return Snapshot::Full(before);
+        }
+
+        let regions = before
+            .layers()
+            .iter()
+            .zip(after.layers())
+            .enumerate()
+            .filter_map(|(i, (old, new))| {
+                let rect = old.canvas.diff_rect(&new.canvas)?;
+                Some((i, rect, old.canvas.crop(rect)))
+            })
+            .collect();
+        Snapshot::Regions {
+            properties: before.properties(),
+            regions,
+        }
+    }
+
+    /// Вернуть слои layers к состоянию снимка.
+    fn apply(&self, layers: &mut LayerStack) {
+        match self {
+            Snapshot::Full(stack) => *layers = stack.clone(),
+            Snapshot::Regions {
+                properties,
+                regions,
+            } => {
+                layers.set_properties(properties);
+                for (i, rect, pixels) in regions {
+                    if let Some(layer) = layers.layers_mut().get_mut(*i) {
+                        layer.canvas.paste(pixels, rect.x, rect.y);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Вернуть слои layers к состоянию снимка.
+    /// Возвращает снимок, возвращающий слои к прежнему состоянию.
+    fn swap(self, layers: &mut LayerStack) -> Snapshot {
+        match self {
+            Snapshot::Full(stack) => Snapshot::Full(std::mem::replace(layers, stack)),
+            Snapshot::Regions { ref regions, .. } => {
+                let inverse = Snapshot::Regions {
+                    properties: layers.properties(),
+                    regions: regions
+                        .iter()
+                        .filter_map(|&(i, rect, _)| {
+                            Some((i, rect, layers.layers().get(i)?.canvas.crop(rect)))
+                        })
+                        .collect(),
+                };
+                self.apply(layers);
+                inverse
+            }
+        }
+    }
+
+    /// Объём пикселей снимка в байтах.
+    fn bytes(&self) -> usize {
+        let bytes = |canvas: &Canvas| {
+            let [width, height] = canvas.size();
+            width * height * size_of::<Color32>()
+        };
+        match self {
+            Snapshot::Full(stack) => stack
+                .layers()
+                .iter()
+                .map(|layer| bytes(&layer.canvas))
+                .sum(),
+            Snapshot::Regions { regions, .. } => {
+                regions.iter().map(|(_, _, pixels)| bytes(pixels)).sum()
+            }
+        }
+    }
+}
+
+/// История изменений для отмены и повтора действий.
+/// Перед действием сохраняется полная копия слоёв, а по его завершении
+/// от неё остаются только изменённые действием области.
+#[derive(Default)]
+pub struct History {
+    undo: VecDeque<Snapshot>,
+    redo: Vec<Snapshot>,
+    /// Идёт действие, состояние до которого уже сохранено
+    in_action: bool,
+}
+
+impl History {
+    /// Максимальное число хранимых снимков
+    const LIMIT: usize = 50;
+
+    /// Наибольший объём пикселей во всех снимках (последний снимок хранится всегда)
+    const MAX_BYTES: usize = 512 * 1024 * 1024;
+
+    /// История из состояний слоёв для отмены (от старых к новым) и повтора
+    /// (последнее отменённое - в конце); layers - текущие слои.
+    pub fn from_states(undo: Vec<LayerStack>, redo: Vec<LayerStack>, layers: &LayerStack) -> Self {
+        // снимок хранит отличия состояния от того, из которого к нему переходят
+        let compact = |states: Vec<LayerStack>| -> Vec<Snapshot> {
+            let mut snapshots: Vec<Snapshot> = Vec::with_capacity(states.len());
+            let mut next = layers;
+            for state in states.iter().rev() {
+                snapshots.push(Snapshot::Full(state.clone()).compact(next));
+                next = state;
+            }
+            snapshots.reverse();
+            snapshots
+        };
+        let mut history = Self {
+            undo: compact(undo).into(),
+            redo: compact(redo),
+            in_action: false,
+        };
+        history.trim();
+        history
+    }
+
+    /// История из готовых снимков для отмены (от старых к новым) и повтора
+    /// (последний отменённый - в конце).
+    pub fn from_snapshots(undo: Vec<Snapshot>, redo: Vec<Snapshot>) -> Self {
+        let mut history = Self {
+            undo: undo.into(),
+            redo,
+            in_action: false,
+        };
+        history.trim();
+        history
+    }
+
+    /// Снимки для отмены, от старых к новым.
+    pub fn undo_snapshots(&self) -> impl Iterator<Item = &Snapshot> {
+        self.undo.iter()
+    }
+
+    /// Снимки для повтора, последний отменённый - в конце.
+    pub fn redo_snapshots(&self) -> &[Snapshot] {
+        &self.redo
+    }
+
+    /// Сохранить состояние слоёв перед началом действия.
+    /// Повторные вызовы до end_action относятся к тому же действию и игнорируются.
+    pub fn record(&mut self, layers: &LayerStack) {
+        if self.in_action {
+            return;
+        }
+        self.in_action = true;
+
+        self.undo.push_back(Snapshot::Full(layers.clone()));
+        self.redo.clear();
+        self.trim();
+    }
+
+    /// Завершить текущее действие (например, при отпускании кнопки мыши);
+    /// layers - слои после действия.
+    pub fn end_action(&mut self, layers: &LayerStack) {
+        if !self.in_action {
+            return;
+        }
+        self.in_action = false;
+
+        if let Some(snapshot) = self.undo.pop_back() {
+            self.undo.push_back(snapshot.compact(layers));
+        }
+        self.trim();
+    }
+
+    /// Удалить самые старые снимки сверх ограничений числа и объёма.
+    /// Полный снимок незавершённого действия в объёме не учитывается
+    /// и не удаляется: по завершении действия он сжимается.
+    fn trim(&mut self) {
+        let pending = match self.undo.back() {
+            Some(snapshot) if self.in_action => snapshot.bytes(),
+            _ => 0,
+        };
+        let mut bytes: usize = self
+            .undo
+            .iter()
+            .chain(&self.redo)
+            .map(Snapshot::bytes)
+            .sum::<usize>()
+            - pending;
+        while self.undo.len() > Self::LIMIT || (bytes > Self::MAX_BYTES && self.undo.len() > 1) {
+            let Some(oldest) = self.undo.pop_front() else {
+                break;
+            };
+            bytes -= oldest.bytes();
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Вернуть слои к состоянию до последнего действия.
+    /// Возвращает false, если отменять нечего.
+    pub fn undo(&mut self, layers: &mut LayerStack) -> bool {
+        let Some(previous) = self.undo.pop_back() else {
+            return false;
+        };
+        self.redo.push(previous.swap(layers));
+        self.in_action = false;
+        true
+    }
+
+    /// Повторить последнее отменённое действие.
+    /// Возвращает false, если повторять нечего.
+    pub fn redo(&mut self, layers: &mut LayerStack) -> bool {
+        let Some(next) = self.redo.pop() else {
+            return false;
+        };
+        self.undo.push_back(next.swap(layers));
+        self.in_action = false;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Слои из одного белого холста 4x4.
+    fn stack() -> LayerStack {
+        LayerStack::new(Canvas::new(4, 4))
+    }
+
+    /// Пиксели всех слоёв стопки.
+    fn pixels(layers: &LayerStack) -> Vec<Color32> {
+        let [width, height] = layers.size();
+        layers
+            .layers()
+            .iter()
+            .flat_map(|layer| {
+                (0..height).flat_map(move |y| (0..width).map(move |x| layer.canvas[(x, y)]))
+            })
+            .collect()
+    }
+
+    /// Действие: закрасить пиксель (x, y) активного слоя.
+    fn paint(history: &mut History, layers: &mut LayerStack, x: usize, y: usize) {
+        history.record(layers);
+        layers.active_mut().canvas[(x, y)] = Color32::RED;
+        history.end_action(layers);
+    }
+
+    #[test]
+    fn undo_and_redo_restore_states() {
+        let mut history = History::default();
+        let mut layers = stack();
+        let mut states = vec![pixels(&layers)];
+        paint(&mut history, &mut layers, 1, 1);
+        states.push(pixels(&layers));
+        paint(&mut history, &mut layers, 2, 3);
+        states.push(pixels(&layers));
+
+        assert!(history.undo(&mut layers));
+        assert_eq!(pixels(&layers), states[1]);
+        assert!(history.undo(&mut layers));
+        assert_eq!(pixels(&layers), states[0]);
+        assert!(!history.undo(&mut layers));
+
+        assert!(history.redo(&mut layers));
+        assert_eq!(pixels(&layers), states[1]);
+        assert!(history.redo(&mut layers));
+        assert_eq!(pixels(&layers), states[2]);
+        assert!(!history.redo(&mut layers));
+    }
+
+    #[test]
+    fn one_action_is_one_step() {
+        let mut history = History::default();
+        let mut layers = stack();
+        let before = pixels(&layers);
+        history.record(&layers);
+        layers.active_mut().canvas[(0, 0)] = Color32::RED;
+        history.record(&layers);
+        layers.active_mut().canvas[(3, 3)] = Color32::RED;
+        history.end_action(&layers);
+
+        assert!(history.undo(&mut layers));
+        assert_eq!(pixels(&layers), before);
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn new_action_clears_redo() {
+        let mut history = History::default();
+        let mut layers = stack();
+        paint(&mut history, &mut layers, 0, 0);
+        history.undo(&mut layers);
+        assert!(history.can_redo());
+        paint(&mut history, &mut layers, 1, 0);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn finished_action_keeps_only_changed_region() {
+        let mut history = History::default();
+        let mut layers = stack();
+        history.record(&layers);
+        layers.active_mut().canvas[(1, 2)] = Color32::RED;
+        layers.active_mut().canvas[(2, 3)] = Color32::RED;
+        history.end_action(&layers);
+
+        let Some(Snapshot::Regions { regions, .. }) = history.undo_snapshots().last() else {
+            panic!("снимок не сжат");
+        };
+        assert_eq!(regions.len(), 1);
+        let (layer, rect, region) = &regions[0];
+        assert_eq!((*layer, *rect), (0, PixelRect::new(1, 2, 2, 2)));
+        assert_eq!(region[(0, 0)], Color32::WHITE);
+    }
+
+    #[test]
+    fn layer_properties_are_restored() {
+        let mut history = History::default();
+        let mut layers = stack();
+        history.record(&layers);
+        layers.active_mut().opacity = 0.25;
+        layers.active_mut().visible = false;
+        history.end_action(&layers);
+
+        history.undo(&mut layers);
+        assert_eq!(layers.active().opacity, 1.0);
+        assert!(layers.active().visible);
+        history.redo(&mut layers);
+        assert_eq!(layers.active().opacity, 0.25);
+        assert!(!layers.active().visible);
+    }
+
+    #[test]
+    fn resized_layers_keep_full_snapshot() {
+        let mut history = History::default();
+        let mut layers = stack();
+        history.record(&layers);
+        layers.active_mut().canvas = Canvas::new(2, 6);
+        history.end_action(&layers);
+        assert!(matches!(
+            history.undo_snapshots().last(),
+            Some(Snapshot::Full(_))
+        ));
+
+        history.undo(&mut layers);
+        assert_eq!(layers.size(), [4, 4]);
+        history.redo(&mut layers);
+        assert_eq!(layers.size(), [2, 6]);
+    }
+
+    #[test]
+    fn number_of_steps_is_limited() {
+        let mut history = History::default();
+        let mut layers = stack();
+        for i in 0..History::LIMIT + 10 {
+            paint(&mut history, &mut layers, i % 4, i / 4 % 4);
+        }
+        assert_eq!(history.undo_snapshots().count(), History::LIMIT);
+    }
+
+    #[test]
+    fn pending_action_keeps_full_snapshot() {
+        let mut history = History::default();
+        let mut layers = stack();
+        for i in 0..History::LIMIT {
+            paint(&mut history, &mut layers, i % 4, i / 4 % 4);
+        }
+        let before = pixels(&layers);
+        history.record(&layers);
+        layers.active_mut().canvas.clear(Color32::BLUE);
+        // снимок незавершённого действия - полная копия слоёв
+        assert!(matches!(
+            history.undo_snapshots().last(),
+            Some(Snapshot::Full(_))
+        ));
+        history.end_action(&layers);
+
+        assert_eq!(history.undo_snapshots().count(), History::LIMIT);
+        history.undo(&mut layers);
+        assert_eq!(pixels(&layers), before);
+    }
+}
@@ -0,0 +1,448 @@
+use std::path::PathBuf;
+
+use egui::{Key, KeyboardShortcut, Modifiers};
+
+use super::{ColorsApp, Instrument};
+
+/// Действие, которое можно вызвать сочетанием клавиш.
+#[derive(PartialEq, Clone, Copy)]
+pub enum Action {
+    Instrument(Instrument),
+    Undo,
+    Redo,
+    ZoomIn,
+    ZoomOut,
+    ZoomFit,
+    Zoom100,
+    Save,
+    Load,
+    ToggleConnectivity,
+    SwapColors,
+    Shortcuts,
+}
+
+impl Action {
+    /// Все действия в порядке вывода в окне подсказки.
+    fn all() -> Vec<Action> {
+        let mut actions: Vec<Action> = Instrument::ALL
+            .into_iter()
+            .map(Action::Instrument)
+            .collect();
+        actions.extend([
+            Action::Undo,
+            Action::Redo,
+            Action::ZoomIn,
+            Action::ZoomOut,
+            Action::ZoomFit,
+            Action::Zoom100,
+            Action::Save,
+            Action::Load,
+            Action::ToggleConnectivity,
+            Action::SwapColors,
+            Action::Shortcuts,
+        ]);
+        actions
+    }
+
+    /// Имя действия в файле настроек.
+    fn config_name(&self) -> &'static str {
+        match self {
+            Action::Instrument(instrument) => match instrument {
+                Instrument::Pencil => "pencil",
+                Instrument::Bucket => "bucket",
+                Instrument::ImageBucket => "image_bucket",
+                Instrument::Border => "border",
+                Instrument::SharpLine => "sharp_line",
+                Instrument::SmoothLine => "smooth_line",
+                Instrument::Triangle => "triangle",
+                Instrument::TexturedTriangle => "textured_triangle",
+                Instrument::Polygon => "polygon",
+                Instrument::Circle => "circle",
+                Instrument::Ellipse => "ellipse",
+                Instrument::Curve => "curve",
+                Instrument::Rectangle => "rectangle",
+                Instrument::Eyedropper => "eyedropper",
+                Instrument::Select => "select",
+            },
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::ZoomIn => "zoom_in",
+            Action::ZoomOut => "zoom_out",
+            Action::ZoomFit => "zoom_fit",
+            Action::Zoom100 => "zoom_100",
+            Action::Save => "save",
+            Action::Load => "load",
+            Action::ToggleConnectivity => "toggle_connectivity",
+            Action::SwapColors => "swap_colors",
+            Action::Shortcuts => "shortcuts",
+        }
+    }
+
+    pub fn get_name(&self) -> String {
+        match self {
+            Action::Instrument(instrument) => format!("инструмент: {}", instrument.get_name()),
+            Action::Undo => String::from("отменить"),
+            Action::Redo => String::from("повторить"),
+            Action::ZoomIn => String::from("увеличить"),
+            Action::ZoomOut => String::from("уменьшить"),
+            Action::ZoomFit => String::from("вписать холст"),
+            Action::Zoom100 => String::from("масштаб 100%"),
+            Action::Save => String::from("сохранить холст"),
+            Action::Load => String::from("загрузить холст"),
+            Action::ToggleConnectivity => String::from("переключить связность"),
+            Action::SwapColors => String::from("поменять цвета местами"),
+            Action::Shortcuts => String::from("горячие клавиши"),
+        }
+    }
+}
+
+/// Разбор сочетания вида "Ctrl+Shift+Z" (Ctrl и Cmd означают Command).
+fn parse_shortcut(text: &str) -> Option<KeyboardShortcut> {
+    let mut modifiers = Modifiers::NONE;
+    let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+    // "+" - сама клавиша плюс, а не разделитель
+    if text.trim().ends_with('+') && parts.len() >= 2 && parts[parts.len() - 2].is_empty() {
+        parts.truncate(parts.len() - 2);
+        parts.push("Plus");
+    }
+    let (key, modifier_names) = parts.split_last()?;
+
+    for name in modifier_names {
+        match name.to_lowercase().as_str() {
+            "ctrl" | "cmd" | "command" => modifiers |= Modifiers::COMMAND,
+            "shift" => modifiers |= Modifiers::SHIFT,
+            "alt" => modifiers |= Modifiers::ALT,
+            _ => return None,
+        }
+    }
+    Some(KeyboardShortcut::new(modifiers, Key::from_name(key)?))
+}
+
+/// Запись сочетания в формате, который понимает parse_shortcut.
+fn format_shortcut(shortcut: &KeyboardShortcut) -> String {
+    let mut text = String::new();
+    if shortcut.modifiers.command {
+        text += "Ctrl+";
+    }
+    if shortcut.modifiers.alt {
+        text += "Alt+";
+    }
+    if shortcut.modifiers.shift {
+        text += "Shift+";
+    }
+    text + shortcut.logical_key.name()
+}
+
+/// Привязка действий к сочетаниям клавиш.
+/// Одному действию может соответствовать несколько сочетаний.
+pub struct Keymap {
+    bindings: Vec<(Action, KeyboardShortcut)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let key = |key| KeyboardShortcut::new(Modifiers::NONE, key);
+        let shift = |key| KeyboardShortcut::new(Modifiers::SHIFT, key);
+        let ctrl = |key| KeyboardShortcut::new(Modifiers::COMMAND, key);
+        let ctrl_shift = |key| KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, key);
+
+        let mut keymap = Self {
+            bindings: vec![
+                (Action::Instrument(Instrument::Pencil), key(Key::P)),
+                (Action::Instrument(Instrument::Bucket), key(Key::B)),
+                (Action::Instrument(Instrument::ImageBucket), shift(Key::B)),
+                (Action::Instrument(Instrument::Border), key(Key::O)),
+                (Action::Instrument(Instrument::SharpLine), key(Key::L)),
+                (Action::Instrument(Instrument::SmoothLine), shift(Key::L)),
+                (Action::Instrument(Instrument::Triangle), key(Key::T)),
+                (
+                    Action::Instrument(Instrument::TexturedTriangle),
+                    shift(Key::T),
+                ),
+                (Action::Instrument(Instrument::Polygon), key(Key::G)),
+                (Action::Instrument(Instrument::Circle), key(Key::C)),
+                (Action::Instrument(Instrument::Ellipse), key(Key::E)),
+                (Action::Instrument(Instrument::Curve), key(Key::U)),
+                (Action::Instrument(Instrument::Rectangle), key(Key::R)),
+                (Action::Instrument(Instrument::Eyedropper), key(Key::I)),
+                (Action::Instrument(Instrument::Select), key(Key::M)),
+                (Action::Undo, ctrl(Key::Z)),
+                (Action::Redo, ctrl_shift(Key::Z)),
+                (Action::Redo, ctrl(Key::Y)),
+                (Action::ZoomIn, key(Key::Plus)),
+                (Action::ZoomIn, key(Key::Equals)),
+                (Action::ZoomOut, key(Key::Minus)),
+                (Action::ZoomFit, ctrl(Key::Num0)),
+                (Action::Zoom100, ctrl(Key::Num1)),
+                (Action::Save, ctrl(Key::S)),
+                (Action::Load, ctrl(Key::O)),
+                (Action::ToggleConnectivity, key(Key::K)),
+                (Action::SwapColors, key(Key::X)),
+                (Action::Shortcuts, key(Key::F1)),
+            ],
+        };
+        keymap.sort();
+        keymap
+    }
+}
+
+impl Keymap {
+    /// Файл с настройками горячих клавиш
+    fn config_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("raster-algorithms").join("keymap.txt"))
+    }
+
+    /// Загрузить привязки из файла настроек. Действия, не указанные в файле,
+    /// получают привязки по умолчанию. Если файла нет, он создаётся
+    /// с привязками по умолчанию, чтобы его было удобно редактировать.
+    pub fn load() -> Self {
+        let mut keymap = Self::default();
+        let Some(path) = Self::config_path() else {
+            return keymap;
+        };
+
+        let Ok(text) = std::fs::read_to_string(&path) else {
+            let saved = path
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|_| std::fs::write(&path, keymap.to_config()));
+            if let Err(error) = saved {
+                eprintln!("не удалось сохранить горячие клавиши {path:?}: {error}");
+            }
+            return keymap;
+        };
+
+        let mut overridden: Vec<Action> = Vec::new();
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let parsed = line.split_once('=').and_then(|(name, shortcut)| {
+                let action = Action::all()
+                    .into_iter()
+                    .find(|action| action.config_name() == name.trim())?;
+                // пустая правая часть - снять все привязки действия
+                let shortcut = match shortcut.trim() {
+                    "" => None,
+                    text => Some(parse_shortcut(text)?),
+                };
+                Some((action, shortcut))
+            });
+            let Some((action, shortcut)) = parsed else {
+                eprintln!(
+                    "{path:?}:{}: не удалось разобрать \"{line}\"",
+                    line_number + 1
+                );
+                continue;
+            };
+
+            // первое упоминание действия заменяет привязки по умолчанию
+            if !overridden.contains(&action) {
+                overridden.push(action);
+                keymap.bindings.retain(|(bound, _)| *bound != action);
+            }
+            keymap
+                .bindings
+                .extend(shortcut.map(|shortcut| (action, shortcut)));
+        }
+        keymap.sort();
+        keymap
+    }
+
+    /// Сочетания с большим числом модификаторов проверяются первыми,
+    /// чтобы Shift+B не срабатывало как B.
+    fn sort(&mut self) {
+        let specificity = |shortcut: &KeyboardShortcut| {
+            let m = shortcut.modifiers;
+            m.command as u8 + m.shift as u8 + m.alt as u8
+        };
+        self.bindings
+            .sort_by_key(|(_, shortcut)| std::cmp::Reverse(specificity(shortcut)));
+    }
+
+    /// Содержимое файла настроек.
+    fn to_config(&self) -> String {
+        let mut text = String::from(
+            "# Горячие клавиши: действие = сочетание (например, Ctrl+Shift+Z).\n\
+             # Действие можно указать несколько раз; пустое сочетание снимает привязку.\n",
+        );
+        for action in Action::all() {
+            let shortcuts = self.shortcuts(action);
+            if shortcuts.is_empty() {
+                text += &format!("{} =\n", action.config_name());
+            }
+            for shortcut in shortcuts {
+                text += &format!(
+                    "{} = {}\n",
+                    action.config_name(),
+                    format_shortcut(&shortcut)
+                );
+            }
+        }
+        text
+    }
+
+    /// Сочетания, привязанные к действию.
+    pub fn shortcuts(&self, action: Action) -> Vec<KeyboardShortcut> {
+        self.bindings
+            .iter()
+            .filter(|(bound, _)| *bound == action)
+            .map(|(_, shortcut)| *shortcut)
+            .collect()
+    }
+
+    /// Действия, сочетания которых нажаты в этом кадре (нажатия поглощаются).
+    fn pressed(&self, ctx: &egui::Context) -> Vec<Action> {
+        ctx.input_mut(|input| {
+            self.bindings
+                .iter()
+                .filter(|(_, shortcut)| input.consume_shortcut(shortcut))
+                .map(|(action, _)| *action)
+                .collect()
+        })
+    }
+}
+
+// =============== Горячие клавиши ===============
+
+impl ColorsApp {
+    /// Выполняет действия, сочетания клавиш которых нажаты.
+    pub(super) fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+
+        for action in self.keymap.pressed(ctx) {
+            self.run_action(ctx, action);
+        }
+    }
+
+    /// Выполнить действие (по сочетанию клавиш или из меню).
+    pub(super) fn run_action(&mut self, ctx: &egui::Context, action: Action) {
+        match action {
+            Action::Instrument(instrument) => self.switch_instrument(instrument),
+            Action::Undo => self.undo(ctx),
+            Action::Redo => self.redo(ctx),
+            Action::ZoomIn => self.viewport.zoom_in(),
+            Action::ZoomOut => self.viewport.zoom_out(),
+            Action::ZoomFit => self.viewport.fit(),
+            Action::Zoom100 => self.viewport.set_zoom(1.0),
+            Action::Save => self.save_canvas(),
+            Action::Load => self.load_canvas(ctx),
+            Action::ToggleConnectivity => {
+                self.connectivity = match self.connectivity {
+                    super::canvas::Connectivity::FOUR => super::canvas::Connectivity::EIGHT,
+                    super::canvas::Connectivity::EIGHT => super::canvas::Connectivity::FOUR,
+                };
+            }
+            Action::SwapColors => self.swap_colors(),
+            Action::Shortcuts => self.show_shortcuts = !self.show_shortcuts,
+        }
+    }
+
+    /// Подпись для кнопки меню: первое сочетание клавиш действия.
+    pub(super) fn shortcut_text(&self, ctx: &egui::Context, action: Action) -> String {
+        self.keymap
+            .shortcuts(action)
+            .first()
+            .map(|shortcut| ctx.format_shortcut(shortcut))
+            .unwrap_or_default()
+    }
+
+    /// Отображает окно со списком горячих клавиш.
+    pub(super) fn show_shortcuts_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_shortcuts;
+        egui::Window::new("Shortcuts")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("shortcuts_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for action in Action::all() {
+                            let shortcuts: Vec<String> = self
+                                .keymap
+                                .shortcuts(action)
+                                .iter()
+                                .map(|shortcut| ctx.format_shortcut(shortcut))
+                                .collect();
+                            ui.label(action.get_name());
+                            ui.label(shortcuts.join(", "));
+                            ui.end_row();
+                        }
+                    });
+
+                ui.separator();
+                match Keymap::config_path() {
+                    Some(path) => ui.label(format!("настройки: {}", path.display())),
+                    None => ui.label("файл настроек недоступен"),
+                };
+            });
+        self.show_shortcuts = open;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shortcuts_are_parsed() {
+        let ctrl_shift_z = KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z);
+        assert_eq!(parse_shortcut("Ctrl+Shift+Z"), Some(ctrl_shift_z));
+        assert_eq!(parse_shortcut(" cmd + shift + Z "), Some(ctrl_shift_z));
+        assert_eq!(
+            parse_shortcut("+"),
+            Some(KeyboardShortcut::new(Modifiers::NONE, Key::Plus))
+        );
+        assert_eq!(
+            parse_shortcut("Ctrl++"),
+            Some(KeyboardShortcut::new(Modifiers::COMMAND, Key::Plus))
+        );
+        assert_eq!(parse_shortcut("Hyper+Z"), None);
+        assert_eq!(parse_shortcut("Ctrl+NoSuchKey"), None);
+    }
+
+    #[test]
+    fn formatted_shortcuts_parse_back() {
+        let keymap = Keymap::default();
+        for (_, shortcut) in &keymap.bindings {
+            assert_eq!(parse_shortcut(&format_shortcut(shortcut)), Some(*shortcut));
+        }
+    }
+
+    #[test]
+    fn config_lists_every_binding() {
+        let keymap = Keymap::default();
+        let config = keymap.to_config();
+        for action in Action::all() {
+            for shortcut in keymap.shortcuts(action) {
+                let line = format!("{} = {}", action.config_name(), format_shortcut(&shortcut));
+                assert!(config.lines().any(|l| l == line), "нет строки {line}");
+            }
+        }
+    }
+
+    #[test]
+    fn action_names_are_unique() {
+        let actions = Action::all();
+        for (i, a) in actions.iter().enumerate() {
+            for b in &actions[i + 1..] {
+                assert_ne!(a.config_name(), b.config_name());
+            }
+        }
+    }
+
+    #[test]
+    fn shortcuts_with_more_modifiers_come_first() {
+        let keymap = Keymap::default();
+        let count = |shortcut: &KeyboardShortcut| {
+            let m = shortcut.modifiers;
+            m.command as u8 + m.shift as u8 + m.alt as u8
+        };
+        let counts: Vec<u8> = keymap.bindings.iter().map(|(_, s)| count(s)).collect();
+        assert!(counts.windows(2).all(|w| w[0] >= w[1]));
+    }
+}
@@ -0,0 +1,446 @@
+use egui::Color32;
+
+use super::ColorsApp;
+use super::canvas::{BlendMode, Canvas, PixelRect};
+
+/// Слой изображения.
+#[derive(Clone)]
+pub struct Layer {
+    pub name: String,
+    pub canvas: Canvas,
+    pub visible: bool,
+    /// Непрозрачность в [0, 1]
+    pub opacity: f32,
+    /// Запрет рисования на слое
+    pub locked: bool,
+    pub blend_mode: BlendMode,
+}
+
+impl Layer {
+    pub fn new(name: impl Into<String>, canvas: Canvas) -> Self {
+        Self {
+            name: name.into(),
+            canvas,
+            visible: true,
+            opacity: 1.0,
+            locked: false,
+            blend_mode: BlendMode::Normal,
+        }
+    }
+}
+
+/// Стопка слоёв одинакового размера, слои перечисляются снизу вверх.
+/// Всегда содержит хотя бы один слой.
+#[derive(Clone)]
+pub struct LayerStack {
+    layers: Vec<Layer>,
+    active: usize,
+    /// Номер для имени следующего нового слоя
+    next_number: usize,
+}
+
+impl Default for LayerStack {
+    fn default() -> Self {
+        Self::new(Canvas::default())
+    }
+}
+
+impl LayerStack {
+    /// Стопка из одного слоя с фоном canvas.
+    pub fn new(canvas: Canvas) -> Self {
+        Self {
+            layers: vec![Layer::new("Background", canvas)],
+            active: 0,
+            next_number: 1,
+        }
+    }
+
+    /// Стопка из готовых слоёв (снизу вверх), active - номер активного слоя.
+    /// Возвращает None для пустого списка.
+    pub fn from_layers(layers: Vec<Layer>, active: usize) -> Option<Self> {
+        if layers.is_empty() {
+            return None;
+        }
+        Some(Self {
+            active: active.min(layers.len() - 1),
+            next_number: layers.len(),
+            layers,
+        })
+    }
+
+    /// Размеры слоёв вида [ширина, высота].
+    pub fn size(&self) -> [usize; 2] {
+        self.layers[0].canvas.size()
+    }
+
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    pub fn layers_mut(&mut self) -> &mut [Layer] {
+        &mut self.layers
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    pub fn set_active(&mut self, index: usize) {
+        self.active = index.min(self.layers.len() - 1);
+    }
+
+    pub fn active(&self) -> &Layer {
+        &self.layers[self.active]
+    }
+
+    pub fn active_mut(&mut self) -> &mut Layer {
+        &mut self.layers[self.active]
+    }
+
+    /// Копия стопки без пикселей: только свойства слоёв и номер активного слоя.
+    pub fn properties(&self) -> LayerStack {
+        let layers = self
+            .layers
+            .iter()
+            .map(|layer| Layer {
+                name: layer.name.clone(),
+                canvas: Canvas::default(),
+                visible: layer.visible,
+                opacity: layer.opacity,
+                locked: layer.locked,
+                blend_mode: layer.blend_mode,
+            })
+            .collect();
+        Self {
+            layers,
+            active: self.active,
+            next_number: self.next_number,
+        }
+    }
+
+    /// Установить свойства слоёв и номер активного слоя из стопки properties
+    /// с тем же числом слоёв, не меняя пиксели.
+    pub fn set_properties(&mut self, properties: &LayerStack) {
+        for (layer, source) in self.layers.iter_mut().zip(&properties.layers) {
+            layer.name.clone_from(&source.name);
+            layer.visible = source.visible;
+            layer.opacity = source.opacity;
+            layer.locked = source.locked;
+            layer.blend_mode = source.blend_mode;
+        }
+        self.set_active(properties.active);
+        self.next_number = properties.next_number;
+    }
+
+    /// Добавить прозрачный слой над активным и сделать его активным.
+    pub fn add(&mut self) {
+        let [width, height] = self.size();
+        let layer = Layer::new(
+            format!("Layer {}", self.next_number),
+            Canvas::new_filled(width, height, Color32::TRANSPARENT),
+        );
+        self.next_number += 1;
+        self.active += 1;
+        self.layers.insert(self.active, layer);
+    }
+
+    /// Удалить активный слой (последний оставшийся слой не удаляется).
+    pub fn remove_active(&mut self) {
+        if self.layers.len() > 1 {
+            self.layers.remove(self.active);
+            self.active = self.active.saturating_sub(1);
+        }
+    }
+
+    /// Переместить активный слой на одну позицию вверх (up) или вниз.
+    pub fn move_active(&mut self, up: bool) {
+        let target = if up {
+            self.active + 1
+        } else {
+            self.active.wrapping_sub(1)
+        };
+        if target < self.layers.len() {
+            self.layers.swap(self.active, target);
+            self.active = target;
+        }
+    }
+
+    /// Слить активный слой с нижележащим с учётом его режима и непрозрачности.
+    pub fn merge_down(&mut self) {
+        if self.active == 0 {
+            return;
+        }
+        let top = self.layers.remove(self.active);
+        self.active -= 1;
+        if top.visible {
+            self.layers[self.active]
+                .canvas
+                .blend(&top.canvas, top.opacity, top.blend_mode);
+        }
+    }
+
+    /// Итоговое изображение: видимые слои, наложенные снизу вверх.
+    pub fn composite(&self) -> Canvas {
+        self.composite_with(None)
+    }
+
+    /// Итоговое изображение, в котором активный слой заменён на active_canvas
+    /// (для предпросмотра незавершённых построений).
+    pub fn composite_with(&self, active_canvas: Option<&Canvas>) -> Canvas {
+        let [width, height] = self.size();
+        let mut result = Canvas::new_filled(width, height, Color32::TRANSPARENT);
+        for (i, layer) in self.layers.iter().enumerate() {
+            if !layer.visible {
+                continue;
+            }
+            let canvas = match active_canvas {
+                Some(canvas) if i == self.active => canvas,
+                _ => &layer.canvas,
+            };
+            result.blend(canvas, layer.opacity, layer.blend_mode);
+        }
+        result
+    }
+
+    /// Заменить холст каждого слоя результатом map(номер слоя, холст),
+    /// например, при изменении размеров. Все новые холсты должны иметь одинаковый размер.
+    pub fn map_canvases(&mut self, mut map: impl FnMut(usize, &Canvas) -> Canvas) {
+        for (i, layer) in self.layers.iter_mut().enumerate() {
+            layer.canvas = map(i, &layer.canvas);
+        }
+    }
+
+    /// Пересчитать итоговое изображение composite только в области rect.
+    pub fn composite_rect(&self, composite: &mut Canvas, rect: PixelRect) {
+        composite.fill_region(rect, Color32::TRANSPARENT);
+        for layer in self.layers.iter().filter(|layer| layer.visible) {
+            composite.blend_rect(&layer.canvas, layer.opacity, layer.blend_mode, rect);
+        }
+    }
+
+    /// Забрать изменённые области всех слоёв (их объединение).
+    pub fn take_dirty(&mut self) -> Option<PixelRect> {
+        self.layers
+            .iter_mut()
+            .filter_map(|layer| layer.canvas.take_dirty())
+            .reduce(PixelRect::union)
+    }
+}
+
+// =============== Панель слоёв ===============
+
+impl ColorsApp {
+    /// Отображает список слоёв (верхний слой сверху), свойства активного слоя
+    /// и кнопки добавления, удаления, перемещения и слияния.
+    pub(super) fn show_layers_panel(&mut self, ui: &mut egui::Ui) {
+        let ctx = ui.ctx().clone();
+        ui.heading("Layers");
+
+        ui.horizontal(|ui| {
+            let count = self.layers.layers().len();
+            let active = self.layers.active_index();
+            if ui.button("+").on_hover_text("новый слой").clicked() {
+                self.edit_layers(&ctx, LayerStack::add);
+            }
+            if ui
+                .add_enabled(count > 1, egui::Button::new("−"))
+                .on_hover_text("удалить слой")
+                .clicked()
+            {
+                self.edit_layers(&ctx, LayerStack::remove_active);
+            }
+            if ui
+                .add_enabled(active + 1 < count, egui::Button::new("⏶"))
+                .on_hover_text("поднять слой")
+                .clicked()
+            {
+                self.edit_layers(&ctx, |layers| layers.move_active(true));
+            }
+            if ui
+                .add_enabled(active > 0, egui::Button::new("⏷"))
+                .on_hover_text("опустить слой")
+                .clicked()
+            {
+                self.edit_layers(&ctx, |layers| layers.move_active(false));
+            }
+            if ui
+                .add_enabled(active > 0, egui::Button::new("Merge Down"))
+                .clicked()
+            {
+                self.edit_layers(&ctx, LayerStack::merge_down);
+            }
+        });
+
+        ui.separator();
+
+        for i in (0..self.layers.layers().len()).rev() {
+            let is_active = i == self.layers.active_index();
+            ui.horizontal(|ui| {
+                let mut visible = self.layers.layers()[i].visible;
+                if ui
+                    .checkbox(&mut visible, "")
+                    .on_hover_text("видимость")
+                    .changed()
+                {
+                    self.edit_layer(i, |layer| layer.visible = visible);
+                }
+                let mut locked = self.layers.layers()[i].locked;
+                if ui
+                    .toggle_value(&mut locked, "🔒")
+                    .on_hover_text("запретить рисование")
+                    .changed()
+                {
+                    self.edit_layer(i, |layer| layer.locked = locked);
+                }
+                if ui
+                    .selectable_label(is_active, &self.layers.layers()[i].name)
+                    .clicked()
+                {
+                    self.layers.set_active(i);
+                }
+            });
+        }
+
+        ui.separator();
+
+        let active = self.layers.active_index();
+        ui.horizontal(|ui| {
+            ui.label("имя:");
+            // имя меняется в истории одним действием, когда поле теряет фокус
+            let id = ui.make_persistent_id("layer_name");
+            if !ui.memory(|memory| memory.has_focus(id)) {
+                self.layer_name = (active, self.layers.active().name.clone());
+            }
+            let response = ui.add(egui::TextEdit::singleline(&mut self.layer_name.1).id(id));
+            // слой мог смениться, пока поле было в фокусе
+            let (index, name) = self.layer_name.clone();
+            if response.lost_focus()
+                && self
+                    .layers
+                    .layers()
+                    .get(index)
+                    .is_some_and(|layer| layer.name != name)
+            {
+                self.history.end_action(&self.layers);
+                self.edit_layer(index, |layer| layer.name = name);
+                self.history.end_action(&self.layers);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("непрозрачность:");
+            let mut opacity = self.layers.active().opacity;
+            if ui.add(egui::Slider::new(&mut opacity, 0.0..=1.0)).changed() {
+                self.edit_layer(active, |layer| layer.opacity = opacity);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("режим:");
+            let mut blend_mode = self.layers.active().blend_mode;
+            egui::ComboBox::from_id_salt("blend_mode_combo_box")
+                .selected_text(blend_mode.get_name())
+                .show_ui(ui, |ui| {
+                    for mode in BlendMode::ALL {
+                        ui.selectable_value(&mut blend_mode, mode, mode.get_name());
+                    }
+                });
+            if blend_mode != self.layers.active().blend_mode {
+                self.edit_layer(active, |layer| layer.blend_mode = blend_mode);
+            }
+        });
+    }
+
+    /// Изменить свойства слоя index (имя, видимость, запрет рисования, непрозрачность,
+    /// режим наложения),
+    /// сохранив прежнее состояние в истории. Изменения до отпускания кнопки мыши
+    /// (перетаскивание ползунка) отменяются как одно действие.
+    fn edit_layer(&mut self, index: usize, edit: impl FnOnce(&mut Layer)) {
+        self.history.record(&self.layers);
+        edit(&mut self.layers.layers_mut()[index]);
+        self.needs_redraw = true;
+    }
+
+    /// Изменить стопку слоёв, сохранив прежнее состояние в истории.
+    pub(super) fn edit_layers(&mut self, ctx: &egui::Context, edit: impl FnOnce(&mut LayerStack)) {
+        // изменение слоёв - отдельное действие в истории
+        self.history.end_action(&self.layers);
+        self.history.record(&self.layers);
+        edit(&mut self.layers);
+        self.history.end_action(&self.layers);
+        self.cancel_pending();
+        self.needs_redraw = true;
+        ctx.request_repaint();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Стопка из белого фона 2x2 и слоя над ним.
+    fn two_layers(top: Color32) -> LayerStack {
+        let mut stack = LayerStack::new(Canvas::new(2, 2));
+        stack.add();
+        stack.active_mut().canvas.clear(top);
+        stack
+    }
+
+    #[test]
+    fn new_layer_is_transparent_and_active() {
+        let mut stack = LayerStack::new(Canvas::new(3, 2));
+        stack.add();
+        assert_eq!(stack.layers().len(), 2);
+        assert_eq!(stack.active_index(), 1);
+        assert_eq!(stack.active().name, "Layer 1");
+        assert_eq!(stack.active().canvas.size(), [3, 2]);
+        assert_eq!(stack.active().canvas[(2, 1)], Color32::TRANSPARENT);
+        assert_eq!(stack.composite()[(0, 0)], Color32::WHITE);
+    }
+
+    #[test]
+    fn last_layer_is_not_removed() {
+        let mut stack = two_layers(Color32::RED);
+        stack.remove_active();
+        assert_eq!(stack.layers().len(), 1);
+        assert_eq!(stack.active_index(), 0);
+        stack.remove_active();
+        assert_eq!(stack.layers().len(), 1);
+    }
+
+    #[test]
+    fn moving_layers_changes_order() {
+        let mut stack = two_layers(Color32::RED);
+        assert_eq!(stack.composite()[(0, 0)], Color32::RED);
+        stack.move_active(false);
+        assert_eq!(stack.active_index(), 0);
+        assert_eq!(stack.composite()[(0, 0)], Color32::WHITE);
+        // нижний слой не опускается ниже
+        stack.move_active(false);
+        assert_eq!(stack.active_index(), 0);
+        stack.move_active(true);
+        assert_eq!(stack.active_index(), 1);
+        assert_eq!(stack.composite()[(0, 0)], Color32::RED);
+    }
+
+    #[test]
+    fn hidden_and_translucent_layers() {
+        let mut stack = two_layers(Color32::BLACK);
+        stack.active_mut().visible = false;
+        assert_eq!(stack.composite()[(0, 0)], Color32::WHITE);
+
+        stack.active_mut().visible = true;
+        stack.active_mut().opacity = 0.5;
+        let gray = stack.composite()[(0, 0)];
+        assert!((gray.r() as i32 - 128).abs() <= 1);
+    }
+
+    #[test]
+    fn merge_down_matches_composite() {
+        let mut stack = two_layers(Color32::from_gray(100));
+        stack.active_mut().blend_mode = BlendMode::Multiply;
+        stack.active_mut().opacity = 0.7;
+        let expected = stack.composite();
+        stack.merge_down();
+        assert_eq!(stack.layers().len(), 1);
+        assert_eq!(stack.composite()[(1, 1)], expected[(1, 1)]);
+    }
+}
@@ -0,0 +1,573 @@
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+use egui::Color32;
+
+use super::{ColorsApp, Instrument};
+
+/// Набор сохранённых цветов.
+pub struct Palette {
+    pub name: String,
+    pub colors: Vec<Color32>,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            name: String::from("Untitled"),
+            colors: vec![
+                Color32::BLACK,
+                Color32::WHITE,
+                Color32::GRAY,
+                Color32::RED,
+                Color32::GREEN,
+                Color32::BLUE,
+                Color32::YELLOW,
+                Color32::from_rgb(0, 255, 255),
+                Color32::from_rgb(255, 0, 255),
+            ],
+        }
+    }
+}
+
+fn invalid_data(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+impl Palette {
+    /// Загрузить палитру из файла .gpl (GIMP) или .ase (Adobe).
+    pub fn load(path: &Path) -> Result<Self> {
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        match extension(path).as_str() {
+            "gpl" => Self::from_gpl(&std::fs::read_to_string(path)?, name),
+            "ase" => Self::from_ase(&std::fs::read(path)?, name),
+            _ => Err(Error::new(
+                ErrorKind::Unsupported,
+                "неизвестный формат палитры",
+            )),
+        }
+    }
+
+    /// Сохранить палитру в файл .gpl (GIMP) или .ase (Adobe).
+    pub fn save(&self, path: &Path) -> Result<()> {
+        match extension(path).as_str() {
+            "gpl" => std::fs::write(path, self.to_gpl()),
+            "ase" => std::fs::write(path, self.to_ase()),
+            _ => Err(Error::new(
+                ErrorKind::Unsupported,
+                "неизвестный формат палитры",
+            )),
+        }
+    }
+
+    /// Разбор палитры GIMP: заголовок "GIMP Palette", необязательные
+    /// строки "Name:" и "Columns:", комментарии "#" и строки "R G B [имя]".
+    fn from_gpl(text: &str, name: String) -> Result<Self> {
+        let mut lines = text.lines();
+        if lines.next().map(str::trim) != Some("GIMP Palette") {
+            return Err(invalid_data("нет заголовка GIMP Palette"));
+        }
+
+        let mut palette = Self {
+            name,
+            colors: Vec::new(),
+        };
+        for line in lines {
+            let line = line.trim();
+            if let Some(name) = line.strip_prefix("Name:") {
+                palette.name = name.trim().to_string();
+                continue;
+            }
+            if line.is_empty() || line.starts_with('#') || line.starts_with("Columns:") {
+                continue;
+            }
+
+            let channels: Vec<u8> = line
+                .split_whitespace()
+                .take(3)
+                .map(|value| value.parse::<u8>())
+                .collect::<std::result::Result<_, _>>()
+                .map_err(|_| invalid_data("некорректная строка цвета"))?;
+            if let [r, g, b] = channels[..] {
+                palette.colors.push(Color32::from_rgb(r, g, b));
+            } else {
+                return Err(invalid_data("некорректная строка цвета"));
+            }
+        }
+        Ok(palette)
+    }
+
+    fn to_gpl(&self) -> String {
+        let mut text = format!("GIMP Palette\nName: {}\nColumns: 8\n#\n", self.name);
+        for color in &self.colors {
+            let [r, g, b, _] = color.to_srgba_unmultiplied();
+            text += &format!("{r:3} {g:3} {b:3}\t#{r:02X}{g:02X}{b:02X}\n");
+        }
+        text
+    }
+
+    /// Разбор Adobe Swatch Exchange: сигнатура "ASEF", версия, число блоков;
+    /// блок - тип (u16), длина (u32) и данные, все числа big-endian.
+    /// Поддерживаются цветовые модели RGB, Gray, CMYK и LAB, группы пропускаются.
+    fn from_ase(data: &[u8], name: String) -> Result<Self> {
+        let mut reader = AseReader { data, pos: 0 };
+        if reader.bytes(4)? != b"ASEF" {
+            return Err(invalid_data("нет сигнатуры ASEF"));
+        }
+        let _version = (reader.u16()?, reader.u16()?);
+        let block_count = reader.u32()?;
+
+        let mut palette = Self {
+            name,
+            colors: Vec::new(),
+        };
+        for _ in 0..block_count {
+            let block_type = reader.u16()?;
+            let length = reader.u32()? as usize;
+            let block = reader.bytes(length)?;
+            if block_type == ASE_COLOR_ENTRY
+                && let Some(color) = parse_ase_color(block)?
+            {
+                palette.colors.push(color);
+            }
+        }
+        Ok(palette)
+    }
+
+    fn to_ase(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"ASEF");
+        data.extend_from_slice(&1u16.to_be_bytes());
+        data.extend_from_slice(&0u16.to_be_bytes());
+        data.extend_from_slice(&(self.colors.len() as u32).to_be_bytes());
+
+        for color in &self.colors {
+            let [r, g, b, _] = color.to_srgba_unmultiplied();
+            // имя в UTF-16 с завершающим нулём
+            let name: Vec<u16> = format!("#{r:02X}{g:02X}{b:02X}")
+                .encode_utf16()
+                .chain([0])
+                .collect();
+
+            let mut block = Vec::new();
+            block.extend_from_slice(&(name.len() as u16).to_be_bytes());
+            for unit in name {
+                block.extend_from_slice(&unit.to_be_bytes());
+            }
+            block.extend_from_slice(b"RGB ");
+            for channel in [r, g, b] {
+                block.extend_from_slice(&(channel as f32 / 255.0).to_be_bytes());
+            }
+            block.extend_from_slice(&ASE_NORMAL_COLOR.to_be_bytes());
+
+            data.extend_from_slice(&ASE_COLOR_ENTRY.to_be_bytes());
+            data.extend_from_slice(&(block.len() as u32).to_be_bytes());
+            data.extend_from_slice(&block);
+        }
+        data
+    }
+}
+
+/// Тип блока ASE с цветом
+const ASE_COLOR_ENTRY: u16 = 0x0001;
+/// Тип цвета ASE: обычный (не global и не spot)
+const ASE_NORMAL_COLOR: u16 = 2;
+
+/// Последовательное чтение big-endian чисел из файла ASE.
+struct AseReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> AseReader<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8]> {
+        let end = self.pos + count;
+        let bytes = self
+            .data
+            .get(self.pos..end)
+            .ok_or_else(|| invalid_data("неожиданный конец файла"))?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_be_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32> {
+        Ok(f32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+}
+
+/// Цвет из блока ASE (None для неизвестной цветовой модели).
+fn parse_ase_color(block: &[u8]) -> Result<Option<Color32>> {
+    let mut reader = AseReader {
+        data: block,
+        pos: 0,
+    };
+    let name_length = reader.u16()? as usize;
+    reader.bytes(name_length * 2)?;
+    let model = reader.bytes(4)?;
+
+    let to_u8 = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let color = match model {
+        b"RGB " => {
+            let (r, g, b) = (reader.f32()?, reader.f32()?, reader.f32()?);
+            Color32::from_rgb(to_u8(r), to_u8(g), to_u8(b))
+        }
+        b"Gray" => {
+            let gray = to_u8(reader.f32()?);
+            Color32::from_rgb(gray, gray, gray)
+        }
+        b"CMYK" => {
+            let (c, m, y, k) = (reader.f32()?, reader.f32()?, reader.f32()?, reader.f32()?);
+            Color32::from_rgb(
+                to_u8((1.0 - c) * (1.0 - k)),
+                to_u8((1.0 - m) * (1.0 - k)),
+                to_u8((1.0 - y) * (1.0 - k)),
+            )
+        }
+        b"LAB " => {
+            let (l, a, b) = (reader.f32()?, reader.f32()?, reader.f32()?);
+            let [r, g, b] = lab_to_srgb(l * 100.0, a, b);
+            Color32::from_rgb(to_u8(r), to_u8(g), to_u8(b))
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(color))
+}
+
+/// Перевод CIE L*a*b* (белая точка D50) в sRGB, каналы в [0, 1].
+fn lab_to_srgb(l: f32, a: f32, b: f32) -> [f32; 3] {
+    let fy = (l + 16.0) / 116.0;
+    let fx = fy + a / 500.0;
+    let fz = fy - b / 200.0;
+    let inverse = |t: f32| {
+        if t > 6.0 / 29.0 {
+            t.powi(3)
+        } else {
+            3.0 * (6.0f32 / 29.0).powi(2) * (t - 4.0 / 29.0)
+        }
+    };
+    let (x, y, z) = (inverse(fx) * 0.9642, inverse(fy), inverse(fz) * 0.8249);
+
+    // XYZ (D50) -> линейный sRGB с хроматической адаптацией Брэдфорда
+    let linear = [
+        3.133856 * x - 1.616867 * y - 0.490615 * z,
+        -0.978768 * x + 1.916142 * y + 0.033454 * z,
+        0.071945 * x - 0.228991 * y + 1.405243 * z,
+    ];
+    linear.map(|c| {
+        if c <= 0.0031308 {
+            12.92 * c
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        }
+    })
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+// =============== Пипетка, история цветов и палитра ===============
+
+impl ColorsApp {
+    /// Число цветов в полосе недавних цветов
+    const RECENT_COLORS: usize = 16;
+    /// Размер образца цвета в панели
+    const SWATCH_SIZE: f32 = 16.0;
+
+    /// Обрабатывает пипетку: левая кнопка - основной цвет, правая - дополнительный.
+    pub(super) fn handle_eyedropper(&mut self, canvas_rect: egui::Rect, response: &egui::Response) {
+        let secondary = response.secondary_clicked();
+        if (response.clicked() || response.dragged() || secondary)
+            && let Some(pointer_pos) = response.hover_pos()
+            && let Some(pos) = self.coord_screen_to_canvas(pointer_pos, canvas_rect)
+        {
+            let color = self.composite.sample_color(pos, self.eyedropper_size);
+            if secondary {
+                self.secondary_color = color;
+            } else {
+                self.cur_color = color;
+            }
+            if !response.dragged() {
+                self.remember_color(color);
+            }
+        }
+    }
+
+    /// Добавить цвет в начало полосы недавних цветов.
+    pub(super) fn remember_color(&mut self, color: Color32) {
+        self.recent_colors.retain(|&recent| recent != color);
+        self.recent_colors.insert(0, color);
+        self.recent_colors.truncate(Self::RECENT_COLORS);
+    }
+
+    /// Поменять местами основной и дополнительный цвета.
+    pub(super) fn swap_colors(&mut self) {
+        std::mem::swap(&mut self.cur_color, &mut self.secondary_color);
+    }
+
+    /// Образец цвета, по щелчку выбирается основным (левая кнопка)
+    /// или дополнительным (правая кнопка) цветом.
+    fn color_swatch(&mut self, ui: &mut egui::Ui, color: Color32) -> egui::Response {
+        let (rect, response) =
+            ui.allocate_exact_size(egui::Vec2::splat(Self::SWATCH_SIZE), egui::Sense::click());
+        egui::color_picker::show_color_at(ui.painter(), color, rect);
+        ui.painter().rect_stroke(
+            rect,
+            0.0,
+            ui.visuals().widgets.noninteractive.bg_stroke,
+            egui::StrokeKind::Inside,
+        );
+
+        if response.clicked() {
+            self.cur_color = color;
+        }
+        if response.secondary_clicked() {
+            self.secondary_color = color;
+        }
+        let [r, g, b, a] = color.to_srgba_unmultiplied();
+        response.on_hover_text(format!("#{r:02X}{g:02X}{b:02X}{a:02X}"))
+    }
+
+    /// Отображает основной и дополнительный цвета, недавние цвета и палитру.
+    pub(super) fn show_palette_panel(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("основной:");
+            egui::color_picker::color_edit_button_srgba(
+                ui,
+                &mut self.cur_color,
+                egui::color_picker::Alpha::OnlyBlend,
+            );
+            ui.label("доп.:");
+            egui::color_picker::color_edit_button_srgba(
+                ui,
+                &mut self.secondary_color,
+                egui::color_picker::Alpha::OnlyBlend,
+            );
+            if ui
+                .button("⇄")
+                .on_hover_text("поменять местами (X)")
+                .clicked()
+            {
+                self.swap_colors();
+            }
+        });
+
+        ui.label("недавние:");
+        let recent = self.recent_colors.clone();
+        ui.horizontal_wrapped(|ui| {
+            ui.spacing_mut().item_spacing = egui::Vec2::splat(2.0);
+            for color in recent {
+                self.color_swatch(ui, color);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label(format!("палитра: {}", self.palette.name));
+            if ui.button("Load").clicked() {
+                self.load_palette();
+            }
+            if ui.button("Save").clicked() {
+                self.save_palette();
+            }
+        });
+        let colors = self.palette.colors.clone();
+        let mut removed = None;
+        ui.horizontal_wrapped(|ui| {
+            ui.spacing_mut().item_spacing = egui::Vec2::splat(2.0);
+            for (i, color) in colors.into_iter().enumerate() {
+                let response = self.color_swatch(ui, color);
+                if response.middle_clicked() {
+                    removed = Some(i);
+                }
+            }
+            if ui
+                .small_button("+")
+                .on_hover_text("добавить основной цвет,\nсредняя кнопка мыши - удалить цвет")
+                .clicked()
+                && !self.palette.colors.contains(&self.cur_color)
+            {
+                self.palette.colors.push(self.cur_color);
+            }
+        });
+        if let Some(i) = removed {
+            self.palette.colors.remove(i);
+        }
+
+        if self.cur_instrument == Instrument::Eyedropper {
+            ui.horizontal(|ui| {
+                ui.label("пипетка:");
+                egui::ComboBox::from_id_salt("eyedropper_size_combo_box")
+                    .selected_text(format!("{0} x {0}", self.eyedropper_size))
+                    .show_ui(ui, |ui| {
+                        for size in [1, 3, 5, 9] {
+                            ui.selectable_value(
+                                &mut self.eyedropper_size,
+                                size,
+                                format!("{size} x {size}"),
+                            );
+                        }
+                    });
+            });
+        }
+    }
+
+    /// Загрузить палитру из файловой системы
+    fn load_palette(&mut self) {
+        let path = rfd::FileDialog::new()
+            .add_filter("Palettes", &["gpl", "ase"])
+            .pick_file();
+
+        if let Some(path) = path {
+            match Palette::load(&path) {
+                Ok(palette) => self.palette = palette,
+                Err(error) => eprintln!("не удалось загрузить палитру {path:?}: {error}"),
+            }
+        }
+    }
+
+    /// Сохранить палитру в файловую систему
+    fn save_palette(&mut self) {
+        let path = rfd::FileDialog::new()
+            .add_filter("GIMP Palette", &["gpl"])
+            .add_filter("Adobe Swatch Exchange", &["ase"])
+            .set_file_name(format!("{}.gpl", self.palette.name))
+            .save_file();
+
+        if let Some(path) = path
+            && let Err(error) = self.palette.save(&path)
+        {
+            eprintln!("не удалось сохранить палитру {path:?}: {error}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn palette() -> Palette {
+        Palette {
+            name: String::from("Test"),
+            colors: vec![
+                Color32::BLACK,
+                Color32::from_rgb(255, 128, 7),
+                Color32::from_rgb(12, 200, 99),
+            ],
+        }
+    }
+
+    /// Блок ASE с цветом модели model и каналами channels.
+    fn ase_block(model: &[u8; 4], channels: &[f32]) -> Vec<u8> {
+        let mut block = Vec::new();
+        block.extend_from_slice(&1u16.to_be_bytes());
+        block.extend_from_slice(&0u16.to_be_bytes());
+        block.extend_from_slice(model);
+        for channel in channels {
+            block.extend_from_slice(&channel.to_be_bytes());
+        }
+        block.extend_from_slice(&ASE_NORMAL_COLOR.to_be_bytes());
+
+        let mut data = ASE_COLOR_ENTRY.to_be_bytes().to_vec();
+        data.extend_from_slice(&(block.len() as u32).to_be_bytes());
+        data.extend_from_slice(&block);
+        data
+    }
+
+    #[test]
+    fn gpl_round_trip() {
+        let original = palette();
+        let loaded = Palette::from_gpl(&original.to_gpl(), String::new()).unwrap();
+        assert_eq!(loaded.name, original.name);
+        assert_eq!(loaded.colors, original.colors);
+    }
+
+    #[test]
+    fn gpl_comments_and_color_names() {
+        let text =
+            "GIMP Palette\nColumns: 4\n# комментарий\n\n  0 10 20\tDark\n255 255 255 White\n";
+        let loaded = Palette::from_gpl(text, String::from("file")).unwrap();
+        assert_eq!(loaded.name, "file");
+        assert_eq!(
+            loaded.colors,
+            [Color32::from_rgb(0, 10, 20), Color32::WHITE]
+        );
+    }
+
+    #[test]
+    fn gpl_errors() {
+        assert!(Palette::from_gpl("0 0 0\n", String::new()).is_err());
+        assert!(Palette::from_gpl("GIMP Palette\n0 0\n", String::new()).is_err());
+        assert!(Palette::from_gpl("GIMP Palette\n0 0 300\n", String::new()).is_err());
+    }
+
+    #[test]
+    fn ase_round_trip() {
+        let original = palette();
+        let loaded = Palette::from_ase(&original.to_ase(), String::from("file")).unwrap();
+        assert_eq!(loaded.name, "file");
+        assert_eq!(loaded.colors, original.colors);
+    }
+
+    #[test]
+    fn ase_color_models() {
+        let blocks = [
+            ase_block(b"Gray", &[0.5]),
+            ase_block(b"CMYK", &[0.0, 1.0, 1.0, 0.0]),
+            ase_block(b"LAB ", &[1.0, 0.0, 0.0]),
+            ase_block(b"XYZ ", &[0.1, 0.2, 0.3]),
+        ];
+        let mut data = b"ASEF".to_vec();
+        data.extend_from_slice(&[0, 1, 0, 0]);
+        // начало группы (без данных) пропускается
+        data.extend_from_slice(&(blocks.len() as u32 + 1).to_be_bytes());
+        data.extend_from_slice(&0xC001u16.to_be_bytes());
+        data.extend_from_slice(&0u32.to_be_bytes());
+        for block in &blocks {
+            data.extend_from_slice(block);
+        }
+
+        let loaded = Palette::from_ase(&data, String::new()).unwrap();
+        assert_eq!(loaded.colors.len(), 3);
+        assert_eq!(loaded.colors[0], Color32::from_rgb(128, 128, 128));
+        assert_eq!(loaded.colors[1], Color32::RED);
+        // белый в L*a*b*
+        let [r, g, b, _] = loaded.colors[2].to_array();
+        assert!(r >= 253 && g >= 253 && b >= 253);
+    }
+
+    #[test]
+    fn ase_errors() {
+        assert!(Palette::from_ase(b"ASEX\0\x01\0\0\0\0\0\0", String::new()).is_err());
+        let data = palette().to_ase();
+        assert!(Palette::from_ase(&data[..data.len() - 3], String::new()).is_err());
+    }
+
+    #[test]
+    fn save_and_load_by_extension() {
+        let directory = std::env::temp_dir().join(format!("palette-test-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let original = palette();
+        for file in ["Test.gpl", "Test.ASE"] {
+            let path = directory.join(file);
+            original.save(&path).unwrap();
+            let loaded = Palette::load(&path).unwrap();
+            assert_eq!(loaded.name, "Test");
+            assert_eq!(loaded.colors, original.colors);
+        }
+        assert!(original.save(&directory.join("Test.txt")).is_err());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,465 @@
+use egui::{Color32, Pos2, Shape, Stroke, Vec2};
+
+use super::canvas::{
+    Canvas, CurveKind, CurveMethod, Fill, FillRule, PixelRect, TextureSampler, flatten_curve,
+};
+use super::{ColorsApp, Instrument};
+
+impl Instrument {
+    /// Строит ли инструмент фигуру по точкам (и есть ли у него предпросмотр результата).
+    fn has_preview(&self) -> bool {
+        !matches!(
+            self,
+            Instrument::Pencil
+                | Instrument::Bucket
+                | Instrument::ImageBucket
+                | Instrument::Border
+                | Instrument::Eyedropper
+                | Instrument::Select
+        )
+    }
+}
+
+// =============== Незавершённые построения ===============
+
+impl ColorsApp {
+    /// Рисует на холсте результат текущего инструмента по поставленным точкам.
+    /// last - точка, завершающая построение (очередной щелчок или отпускание кнопки);
+    /// offset - сдвиг всех точек (для рисования на холсте, вырезанном из изображения);
+    /// Возвращает false, если для построения не хватает точек.
+    pub(super) fn render_pending(
+        &self,
+        canvas: &mut Canvas,
+        last: Option<Pos2>,
+        offset: Vec2,
+    ) -> bool {
+        let color = self.cur_color;
+        let mut points: Vec<Pos2> = self.points.iter().map(|&p| p + offset).collect();
+        points.extend(last.map(|p| p + offset));
+
+        match self.cur_instrument {
+            Instrument::SharpLine | Instrument::SmoothLine => {
+                let smooth = self.cur_instrument == Instrument::SmoothLine;
+                if self.polyline_mode {
+                    if points.len() < 2 {
+                        return false;
+                    }
+                    canvas.draw_polyline(&points, color, smooth);
+                } else {
+                    let [pos1, pos2] = points[..] else {
+                        return false;
+                    };
+                    if smooth {
+                        canvas.draw_smooth_line_simple(pos1, pos2, color);
+                    } else {
+                        canvas.draw_sharp_line(pos1, pos2, color);
+                    }
+                }
+            }
+            Instrument::Triangle => {
+                let [pos1, pos2, pos3] = points[..] else {
+                    return false;
+                };
+                let (color1, color2) = (self.colors[0], self.colors[1]);
+                canvas.draw_gradient_triangle(pos1, pos2, pos3, color1, color2, color);
+            }
+            Instrument::TexturedTriangle => {
+                let (&[pos1, pos2, pos3], Some(texture)) = (&points[..], &self.loaded_texture)
+                else {
+                    return false;
+                };
+                canvas.draw_textured_triangle(
+                    [pos1, pos2, pos3],
+                    self.texture_uvs,
+                    texture,
+                    self.texture_sampler,
+                );
+            }
+            Instrument::Polygon => {
+                let mut contours: Vec<Vec<Pos2>> = self
+                    .contours
+                    .iter()
+                    .map(|contour| contour.iter().map(|&p| p + offset).collect())
+                    .collect();
+                let mut colors = self.contour_colors.clone();
+                if points.len() >= 3 {
+                    let mut contour_colors = self.colors.clone();
+                    contour_colors.resize(points.len(), color);
+                    contours.push(points);
+                    colors.push(contour_colors);
+                }
+                if contours.is_empty() {
+                    return false;
+                }
+
+                if self.triangulate_polygon {
+                    // первый контур внешний, остальные - дыры
+                    if !self.gouraud_polygon {
+                        colors = contours.iter().map(|c| vec![color; c.len()]).collect();
+                    }
+                    canvas.draw_gradient_polygon(&contours, &colors);
+                } else if self.gouraud_polygon {
+                    canvas.fill_gradient_polygon(&contours, &colors, self.fill_rule);
+                } else {
+                    canvas.fill_polygon(&contours, self.fill_rule, color);
+                }
+            }
+            Instrument::Circle | Instrument::Ellipse => {
+                let [start, end] = points[..] else {
+                    return false;
+                };
+                let is_circle = self.cur_instrument == Instrument::Circle;
+                let (center, radii, angle) = if is_circle {
+                    (start, egui::Vec2::splat(start.distance(end)), 0.0)
+                } else {
+                    (
+                        start.lerp(end, 0.5),
+                        (end - start).abs() / 2.0,
+                        self.ellipse_angle.to_radians(),
+                    )
+                };
+
+                let smooth = self.conic_smooth;
+                match (is_circle, self.conic_filled) {
+                    (true, true) => canvas.fill_circle(center, radii.x, color),
+                    (false, true) => canvas.fill_ellipse(center, radii, angle, color),
+                    (true, false) if !smooth => canvas.draw_circle(center, radii.x, color),
+                    (false, false) if !smooth => canvas.draw_ellipse(center, radii, angle, color),
+                    _ => {}
+                }
+                // сглаженный контур поверх заливки смягчает её край
+                if smooth {
+                    canvas.draw_smooth_ellipse(center, radii, angle, color);
+                }
+            }
+            Instrument::Rectangle => {
+                let [start, end] = points[..] else {
+                    return false;
+                };
+                let rect = Self::drag_rect(start, end, self.modifiers.shift);
+                if self.rect_filled {
+                    canvas.fill_rect(rect, self.rect_corner_radius, self.rect_fill);
+                }
+                if self.rect_stroke {
+                    canvas.draw_rect(rect, self.rect_corner_radius, self.rect_stroke_width, color);
+                }
+            }
+            Instrument::Curve => {
+                // кривая завершается по Enter, курсор в неё не входит
+                points.truncate(self.points.len());
+                if points.len() < self.curve_kind.control_points().unwrap_or(2) {
+                    return false;
+                }
+                canvas.draw_curve(
+                    self.curve_kind,
+                    &points,
+                    color,
+                    self.curve_method,
+                    self.curve_smooth,
+                );
+            }
+            Instrument::Pencil
+            | Instrument::Bucket
+            | Instrument::ImageBucket
+            | Instrument::Border
+            | Instrument::Eyedropper
+            | Instrument::Select => {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Нарисовать незавершённое построение на холсте и сбросить поставленные точки.
+    /// last - точка, завершающая построение;
+    /// Возвращает false, если для построения не хватает точек.
+    pub(super) fn commit_pending(&mut self, ctx: &egui::Context, last: Option<Pos2>) -> bool {
+        if !self.pending_complete(last) {
+            return false;
+        }
+
+        // canvas_mut сохраняет в истории состояние до построения,
+        // холст слоя на время рисования забирается без копирования
+        let mut canvas = std::mem::take(self.canvas_mut(ctx));
+        self.render_pending(&mut canvas, last, Vec2::ZERO);
+        self.layers.active_mut().canvas = canvas;
+        self.cancel_pending();
+        true
+    }
+
+    /// Хватает ли точек, чтобы завершить построение в точке last.
+    fn pending_complete(&self, last: Option<Pos2>) -> bool {
+        let count = self.points.len() + usize::from(last.is_some());
+        match self.cur_instrument {
+            Instrument::SharpLine | Instrument::SmoothLine if self.polyline_mode => count >= 2,
+            Instrument::SharpLine
+            | Instrument::SmoothLine
+            | Instrument::Circle
+            | Instrument::Ellipse
+            | Instrument::Rectangle => count == 2,
+            Instrument::Triangle => count == 3,
+            Instrument::TexturedTriangle => count == 3 && self.loaded_texture.is_some(),
+            Instrument::Polygon => !self.contours.is_empty() || count >= 3,
+            // кривая завершается по Enter, курсор в неё не входит
+            Instrument::Curve => self.points.len() >= self.curve_kind.control_points().unwrap_or(2),
+            Instrument::Pencil
+            | Instrument::Bucket
+            | Instrument::ImageBucket
+            | Instrument::Border
+            | Instrument::Eyedropper
+            | Instrument::Select => false,
+        }
+    }
+
+    /// Область холста, которую может затронуть построение, завершённое в точке last
+    /// (None, если строить нечего).
+    fn pending_bounds(&self, last: Option<Pos2>) -> Option<PixelRect> {
+        if !self.pending_complete(last) {
+            return None;
+        }
+        let mut points: Vec<Pos2> = self.contours.iter().flatten().copied().collect();
+        points.extend(&self.points);
+        points.extend(last);
+
+        let rect = match (self.cur_instrument, &points[..]) {
+            (Instrument::Circle, &[center, end]) => {
+                egui::Rect::from_center_size(center, Vec2::splat(2.0 * center.distance(end)))
+            }
+            (Instrument::Ellipse, &[start, end]) => {
+                // повёрнутый эллипс лежит в круге радиуса большей полуоси
+                let radius = (end - start).abs().max_elem() / 2.0;
+                egui::Rect::from_center_size(start.lerp(end, 0.5), Vec2::splat(2.0 * radius))
+            }
+            (Instrument::Rectangle, &[start, end]) => {
+                Self::drag_rect(start, end, self.modifiers.shift)
+            }
+            (Instrument::Curve, _) => egui::Rect::from_points(&flatten_curve(
+                self.curve_kind,
+                &self.points,
+                self.curve_method,
+            )),
+            _ => egui::Rect::from_points(&points),
+        };
+        if !rect.is_finite() || rect.is_negative() {
+            return None;
+        }
+
+        // запас на сглаживание и округление координат
+        let margin = ColorsApp::GHOST_MARGIN;
+        let [width, height] = self.layers.size().map(|side| side as f32);
+        let left = (rect.left() - margin).floor().clamp(0.0, width) as usize;
+        let top = (rect.top() - margin).floor().clamp(0.0, height) as usize;
+        let right = (rect.right() + margin).ceil().clamp(0.0, width) as usize;
+        let bottom = (rect.bottom() + margin).ceil().clamp(0.0, height) as usize;
+        (left < right && top < bottom)
+            .then(|| PixelRect::new(left, top, right - left, bottom - top))
+    }
+
+    /// Прямоугольник, заданный перетаскиванием от start до end.
+    /// Прямоугольник захватывает пиксели под обеими точками,
+    /// square - сделать его квадратом по большей стороне.
+    fn drag_rect(start: Pos2, end: Pos2, square: bool) -> egui::Rect {
+        let start = start.floor();
+        let mut end = end.floor();
+        if square {
+            let side = (end.x - start.x).abs().max((end.y - start.y).abs());
+            end.x = start.x + side * (end.x - start.x).signum();
+            end.y = start.y + side * (end.y - start.y).signum();
+        }
+
+        let rect = egui::Rect::from_two_pos(start, end);
+        egui::Rect::from_min_max(rect.min, rect.max + egui::Vec2::splat(1.0))
+    }
+
+    /// Отменить незавершённое построение.
+    pub(super) fn cancel_pending(&mut self) {
+        self.points.clear();
+        self.colors.clear();
+        self.contours.clear();
+        self.contour_colors.clear();
+        self.dragged_point = None;
+    }
+}
+
+// =============== Предпросмотр поверх холста ===============
+
+/// Всё, от чего зависит предпросмотр построения: поставленные точки, точка
+/// под курсором, цвет и настройки инструмента.
+#[derive(PartialEq)]
+pub(super) struct GhostState {
+    instrument: Instrument,
+    points: Vec<Pos2>,
+    colors: Vec<Color32>,
+    contours: usize,
+    last: Option<Pos2>,
+    color: Color32,
+    /// Shift (квадрат вместо прямоугольника)
+    square: bool,
+    polyline_mode: bool,
+    texture: (TextureSampler, [Pos2; 3]),
+    /// Правило заливки, заливка по Гуро и триангуляция многоугольника
+    polygon: (FillRule, bool, bool),
+    /// Заливка, сглаживание и угол поворота окружностей и эллипсов
+    conic: (bool, bool, f32),
+    curve: (CurveKind, CurveMethod, bool),
+    /// Радиус скругления, контур, толщина контура, заливка и её вид
+    rect: (f32, bool, f32, bool, Fill),
+}
+
+impl ColorsApp {
+    /// Непрозрачность предпросмотра результата построения.
+    const GHOST_ALPHA: u8 = 150;
+
+    /// Запас (в пикселях холста) вокруг точек построения для предпросмотра.
+    const GHOST_MARGIN: f32 = 2.0;
+
+    /// Обновить текстуру с предпросмотром результата: построение, завершённое
+    /// в точке под курсором, на прозрачном фоне. Рисуется только область,
+    /// которую затрагивает построение, а не всё изображение.
+    pub(super) fn update_ghost(
+        &mut self,
+        ctx: &egui::Context,
+        canvas_rect: egui::Rect,
+        hover_pos: Option<Pos2>,
+    ) {
+        let last = hover_pos
+            .and_then(|pos| self.coord_screen_to_canvas(pos, canvas_rect))
+            .map(|pos| pos.floor());
+
+        // перестраивать предпросмотр только при изменении построения или настроек
+        let state = Some(GhostState {
+            instrument: self.cur_instrument,
+            points: self.points.clone(),
+            colors: self.colors.clone(),
+            contours: self.contours.len(),
+            last,
+            color: self.cur_color,
+            square: self.modifiers.shift,
+            polyline_mode: self.polyline_mode,
+            texture: (self.texture_sampler, self.texture_uvs),
+            polygon: (
+                self.fill_rule,
+                self.gouraud_polygon,
+                self.triangulate_polygon,
+            ),
+            conic: (self.conic_filled, self.conic_smooth, self.ellipse_angle),
+            curve: (self.curve_kind, self.curve_method, self.curve_smooth),
+            rect: (
+                self.rect_corner_radius,
+                self.rect_stroke,
+                self.rect_stroke_width,
+                self.rect_filled,
+                self.rect_fill,
+            ),
+        });
+        if state == self.ghost_state && !self.image_changed() {
+            return;
+        }
+        self.ghost_state = state;
+        self.ghost_texture = None;
+
+        if !self.cur_instrument.has_preview() {
+            return;
+        }
+        let Some(rect) = self.pending_bounds(last) else {
+            return;
+        };
+        let mut ghost = Canvas::new_filled(rect.width, rect.height, Color32::TRANSPARENT);
+        let offset = -Vec2::new(rect.x as f32, rect.y as f32);
+        if self.render_pending(&mut ghost, last, offset) {
+            let texture = ctx.load_texture(
+                "ghost",
+                ghost.to_color_image(),
+                egui::TextureOptions::NEAREST,
+            );
+            self.ghost_texture = Some((texture, rect));
+        }
+    }
+
+    /// Рисует поверх холста предпросмотр результата, контур построения
+    /// и поставленные точки с их цветами.
+    pub(super) fn draw_overlay(
+        &self,
+        painter: &egui::Painter,
+        canvas_rect: egui::Rect,
+        hover_pos: Option<Pos2>,
+    ) {
+        if let Some((ghost, rect)) = &self.ghost_texture {
+            let corner = |x: usize, y: usize| {
+                self.coord_canvas_to_screen(Pos2::new(x as f32, y as f32), canvas_rect)
+            };
+            painter.image(
+                ghost.id(),
+                egui::Rect::from_min_max(
+                    corner(rect.x, rect.y),
+                    corner(rect.x + rect.width, rect.y + rect.height),
+                ),
+                egui::Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)),
+                Color32::from_white_alpha(ColorsApp::GHOST_ALPHA),
+            );
+        }
+
+        let to_screen = |points: &[Pos2]| -> Vec<Pos2> {
+            points
+                .iter()
+                .map(|&p| self.coord_canvas_to_screen(p, canvas_rect))
+                .collect()
+        };
+        let outline = Stroke::new(1.0, Color32::GRAY);
+
+        // контур построения
+        let mut path = to_screen(&self.points);
+        match self.cur_instrument {
+            Instrument::Curve => {
+                // сама кривая видна в предпросмотре результата
+                painter.add(Shape::line(path, outline));
+            }
+            Instrument::Circle => {
+                if let (Some(&center), Some(hover_pos)) = (path.first(), hover_pos) {
+                    painter.circle_stroke(center, center.distance(hover_pos), outline);
+                }
+            }
+            Instrument::Ellipse | Instrument::Rectangle => {
+                if let (Some(&start), Some(hover_pos)) = (path.first(), hover_pos) {
+                    painter.rect_stroke(
+                        egui::Rect::from_two_pos(start, hover_pos),
+                        0.0,
+                        outline,
+                        egui::StrokeKind::Middle,
+                    );
+                }
+            }
+            Instrument::Polygon | Instrument::Triangle | Instrument::TexturedTriangle => {
+                for contour in &self.contours {
+                    painter.add(Shape::closed_line(to_screen(contour), outline));
+                }
+                path.extend(hover_pos);
+                painter.add(Shape::closed_line(path, outline));
+            }
+            _ => {
+                path.extend(hover_pos);
+                painter.add(Shape::line(path, outline));
+            }
+        }
+
+        // поставленные точки
+        let contour_points = self
+            .contours
+            .iter()
+            .flatten()
+            .zip(self.contour_colors.iter().flatten());
+        let points = self
+            .points
+            .iter()
+            .enumerate()
+            .map(|(i, point)| (point, self.colors.get(i).unwrap_or(&self.cur_color)));
+        for (&point, &color) in contour_points.chain(points) {
+            painter.circle(
+                self.coord_canvas_to_screen(point, canvas_rect),
+                ColorsApp::HANDLE_RADIUS,
+                color,
+                Stroke::new(1.0, Color32::BLACK),
+            );
+        }
+    }
+}
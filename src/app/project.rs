@@ -0,0 +1,696 @@
+use std::fs::File;
+use std::io::{self, Cursor, Read, Write};
+use std::path::Path;
+
+use egui::{Color32, Pos2};
+use serde::{Deserialize, Serialize};
+use zip::write::SimpleFileOptions;
+
+use super::canvas::{
+    self, BlendMode, Canvas, CurveKind, CurveMethod, Fill, FillRule, PixelRect, TextureSampler,
+};
+use super::history::{History, Snapshot};
+use super::layers::{Layer, LayerStack};
+use super::{ColorsApp, Instrument};
+
+/// Расширение файлов проекта
+pub const EXTENSION: &str = "rproj";
+/// Идентификатор формата в манифесте
+const FORMAT_NAME: &str = "raster-algorithms-project";
+/// Текущая версия формата. Файлы более новых версий не открываются,
+/// более старые версии читаются (отсутствующие поля получают значения по умолчанию).
+/// Версия 2: история хранится изменёнными областями, сохраняются настройки инструментов.
+const FORMAT_VERSION: u32 = 2;
+
+/// Манифест проекта - файл manifest.json в zip-архиве.
+///
+/// Состав архива:
+/// - manifest.json - этот манифест;
+/// - layers/<номер>.png - слои снизу вверх (RGBA без премультипликации);
+/// - bucket.png - картинка для заливки, если она загружена;
+/// - history/undo/<шаг>/..., history/redo/<шаг>/... - снимки истории: картинки всех слоёв
+///   (<номер слоя>.png) или только изменённых областей (region<номер>.png);
+///   в версии 1 - всегда картинки всех слоёв.
+///
+/// Цвета в манифесте записываются как [r, g, b, a] с премультиплицированной альфой,
+/// координаты точек - в пикселях холста. Неизвестные поля при чтении игнорируются,
+/// поэтому новые возможности добавляются новыми необязательными полями.
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    format: String,
+    version: u32,
+    width: usize,
+    height: usize,
+    #[serde(flatten)]
+    layers: StackManifest,
+    #[serde(default)]
+    state: StateManifest,
+    #[serde(default)]
+    history: HistoryManifest,
+}
+
+/// Описание стопки слоёв.
+#[derive(Serialize, Deserialize)]
+struct StackManifest {
+    layers: Vec<LayerManifest>,
+    #[serde(default)]
+    active_layer: usize,
+}
+
+/// Свойства слоя и путь к его картинке в архиве
+/// (у свойств слоёв в снимках истории картинки нет).
+#[derive(Serialize, Deserialize)]
+struct LayerManifest {
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+    #[serde(default = "default_true")]
+    visible: bool,
+    #[serde(default = "default_opacity")]
+    opacity: f32,
+    #[serde(default)]
+    locked: bool,
+    #[serde(default)]
+    blend_mode: BlendMode,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_opacity() -> f32 {
+    1.0
+}
+
+/// Состояние приложения, относящееся к документу.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct StateManifest {
+    cur_color: Option<Color32>,
+    secondary_color: Option<Color32>,
+    instrument: Instrument,
+    connectivity: canvas::Connectivity,
+    /// Путь к картинке для заливки в архиве
+    bucket_image: Option<String>,
+    /// Незавершённые построения
+    points: Vec<Pos2>,
+    colors: Vec<Color32>,
+    contours: Vec<Vec<Pos2>>,
+    contour_colors: Vec<Vec<Color32>>,
+    texture_uvs: Option<[Pos2; 3]>,
+    /// Настройки, с которыми строятся незавершённые построения (с версии 2)
+    tools: Option<ToolsManifest>,
+}
+
+/// Настройки инструментов.
+#[derive(Serialize, Deserialize)]
+struct ToolsManifest {
+    polyline_mode: bool,
+    texture_sampler: TextureSampler,
+    fill_rule: FillRule,
+    gouraud_polygon: bool,
+    triangulate_polygon: bool,
+    conic_filled: bool,
+    conic_smooth: bool,
+    ellipse_angle: f32,
+    curve_kind: CurveKind,
+    curve_method: CurveMethod,
+    curve_smooth: bool,
+    rect_corner_radius: f32,
+    rect_stroke: bool,
+    rect_stroke_width: f32,
+    rect_filled: bool,
+    rect_fill: Fill,
+}
+
+/// История, шаги от старых к новым (для повтора последний отменённый - в конце).
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct HistoryManifest {
+    /// Полные состояния слоёв (версия 1)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    undo: Vec<StackManifest>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    redo: Vec<StackManifest>,
+    /// Снимки истории (с версии 2)
+    undo_steps: Vec<StepManifest>,
+    redo_steps: Vec<StepManifest>,
+}
+
+/// Снимок истории: полное состояние слоёв или свойства слоёв
+/// и прежнее содержимое изменённых областей.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum StepManifest {
+    Full(StackManifest),
+    Regions {
+        properties: StackManifest,
+        regions: Vec<RegionManifest>,
+    },
+}
+
+/// Область слоя layer с левым верхним углом (x, y) и путь к её картинке в архиве.
+#[derive(Serialize, Deserialize)]
+struct RegionManifest {
+    layer: usize,
+    x: usize,
+    y: usize,
+    file: String,
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Записать холст в PNG.
+fn encode_png(canvas: &Canvas) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    canvas
+        .to_rgba_image()
+        .write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Png)
+        .map_err(io::Error::other)?;
+    Ok(bytes)
+}
+
+/// Прочитать холст из PNG.
+fn decode_png(bytes: &[u8]) -> io::Result<Canvas> {
+    let image = image::load_from_memory_with_format(bytes, image::ImageFormat::Png)
+        .map_err(|error| invalid_data(error.to_string()))?;
+    Ok(Canvas::from(&image))
+}
+
+/// Запись проекта в zip-архив.
+struct ProjectWriter {
+    zip: zip::ZipWriter<File>,
+    options: SimpleFileOptions,
+}
+
+impl ProjectWriter {
+    fn write_file(&mut self, name: &str, bytes: &[u8]) -> io::Result<()> {
+        self.zip.start_file(name, self.options)?;
+        self.zip.write_all(bytes)
+    }
+
+    /// Записать картинки слоёв в каталог dir и вернуть их описание.
+    /// Без dir записываются только свойства слоёв.
+    fn write_stack(&mut self, stack: &LayerStack, dir: Option<&str>) -> io::Result<StackManifest> {
+        let mut layers = Vec::new();
+        for (i, layer) in stack.layers().iter().enumerate() {
+            let file = match dir {
+                Some(dir) => {
+                    let file = format!("{dir}/{i}.png");
+                    self.write_file(&file, &encode_png(&layer.canvas)?)?;
+                    Some(file)
+                }
+                None => None,
+            };
+            layers.push(LayerManifest {
+                name: layer.name.clone(),
+                file,
+                visible: layer.visible,
+                opacity: layer.opacity,
+                locked: layer.locked,
+                blend_mode: layer.blend_mode,
+            });
+        }
+        Ok(StackManifest {
+            layers,
+            active_layer: stack.active_index(),
+        })
+    }
+
+    /// Записать снимок истории в каталог dir и вернуть его описание.
+    fn write_step(&mut self, snapshot: &Snapshot, dir: &str) -> io::Result<StepManifest> {
+        Ok(match snapshot {
+            Snapshot::Full(stack) => StepManifest::Full(self.write_stack(stack, Some(dir))?),
+            Snapshot::Regions {
+                properties,
+                regions,
+            } => {
+                let mut manifests = Vec::new();
+                for (i, (layer, rect, pixels)) in regions.iter().enumerate() {
+                    let file = format!("{dir}/region{i}.png");
+                    self.write_file(&file, &encode_png(pixels)?)?;
+                    manifests.push(RegionManifest {
+                        layer: *layer,
+                        x: rect.x,
+                        y: rect.y,
+                        file,
+                    });
+                }
+                StepManifest::Regions {
+                    properties: self.write_stack(properties, None)?,
+                    regions: manifests,
+                }
+            }
+        })
+    }
+}
+
+/// Чтение проекта из zip-архива.
+struct ProjectReader {
+    zip: zip::ZipArchive<File>,
+}
+
+impl ProjectReader {
+    fn read_file(&mut self, name: &str) -> io::Result<Vec<u8>> {
+        let mut file = self.zip.by_name(name)?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Прочитать стопку слоёв. Все слои должны иметь размер size
+    /// (без size - одинаковый размер, например, в снимках истории до изменения размеров).
+    fn read_stack(
+        &mut self,
+        manifest: &StackManifest,
+        mut size: Option<[usize; 2]>,
+    ) -> io::Result<LayerStack> {
+        let mut layers = Vec::new();
+        for layer in &manifest.layers {
+            let file = layer
+                .file
+                .as_deref()
+                .ok_or_else(|| invalid_data(format!("нет картинки слоя {}", layer.name)))?;
+            let canvas = decode_png(&self.read_file(file)?)?;
+            if *size.get_or_insert(canvas.size()) != canvas.size() {
+                return Err(invalid_data(format!(
+                    "размер слоя {file} не совпадает с размером проекта"
+                )));
+            }
+            layers.push(Layer {
+                name: layer.name.clone(),
+                canvas,
+                visible: layer.visible,
+                opacity: layer.opacity.clamp(0.0, 1.0),
+                locked: layer.locked,
+                blend_mode: layer.blend_mode,
+            });
+        }
+        LayerStack::from_layers(layers, manifest.active_layer)
+            .ok_or_else(|| invalid_data("в проекте нет слоёв"))
+    }
+
+    /// Прочитать свойства слоёв (без картинок).
+    fn read_properties(manifest: &StackManifest) -> io::Result<LayerStack> {
+        let layers = manifest
+            .layers
+            .iter()
+            .map(|layer| Layer {
+                name: layer.name.clone(),
+                canvas: Canvas::default(),
+                visible: layer.visible,
+                opacity: layer.opacity.clamp(0.0, 1.0),
+                locked: layer.locked,
+                blend_mode: layer.blend_mode,
+            })
+            .collect();
+        LayerStack::from_layers(layers, manifest.active_layer)
+            .ok_or_else(|| invalid_data("в снимке истории нет слоёв"))
+    }
+
+    /// Прочитать снимок истории.
+    fn read_step(&mut self, manifest: &StepManifest) -> io::Result<Snapshot> {
+        Ok(match manifest {
+            StepManifest::Full(stack) => Snapshot::Full(self.read_stack(stack, None)?),
+            StepManifest::Regions {
+                properties,
+                regions,
+            } => {
+                let mut pixels = Vec::new();
+                for region in regions {
+                    let canvas = decode_png(&self.read_file(&region.file)?)?;
+                    let [width, height] = canvas.size();
+                    let rect = PixelRect::new(region.x, region.y, width, height);
+                    pixels.push((region.layer, rect, canvas));
+                }
+                Snapshot::Regions {
+                    properties: Self::read_properties(properties)?,
+                    regions: pixels,
+                }
+            }
+        })
+    }
+}
+
+// =============== Сохранение и открытие проектов ===============
+
+impl ColorsApp {
+    /// Сохранить проект в файл path.
+    fn write_project(&self, path: &Path) -> io::Result<()> {
+        let mut writer = ProjectWriter {
+            zip: zip::ZipWriter::new(File::create(path)?),
+            options: SimpleFileOptions::default()
+                .compression_method(zip::CompressionMethod::Deflated),
+        };
+
+        let layers = writer.write_stack(&self.layers, Some("layers"))?;
+
+        let mut history = HistoryManifest::default();
+        for (step, snapshot) in self.history.undo_snapshots().enumerate() {
+            let dir = format!("history/undo/{step}");
+            history.undo_steps.push(writer.write_step(snapshot, &dir)?);
+        }
+        for (step, snapshot) in self.history.redo_snapshots().iter().enumerate() {
+            let dir = format!("history/redo/{step}");
+            history.redo_steps.push(writer.write_step(snapshot, &dir)?);
+        }
+
+        let bucket_image = match &self.loaded_image {
+            Some(image) => {
+                let [width, height] = image.size;
+                let canvas = Canvas::from_pixels(width, height, image.pixels.clone());
+                writer.write_file("bucket.png", &encode_png(&canvas)?)?;
+                Some(String::from("bucket.png"))
+            }
+            None => None,
+        };
+
+        let [width, height] = self.layers.size();
+        let manifest = Manifest {
+            format: String::from(FORMAT_NAME),
+            version: FORMAT_VERSION,
+            width,
+            height,
+            layers,
+            state: StateManifest {
+                cur_color: Some(self.cur_color),
+                secondary_color: Some(self.secondary_color),
+                instrument: self.cur_instrument,
+                connectivity: self.connectivity,
+                bucket_image,
+                points: self.points.clone(),
+                colors: self.colors.clone(),
+                contours: self.contours.clone(),
+                contour_colors: self.contour_colors.clone(),
+                texture_uvs: Some(self.texture_uvs),
+                tools: Some(ToolsManifest {
+                    polyline_mode: self.polyline_mode,
+                    texture_sampler: self.texture_sampler,
+                    fill_rule: self.fill_rule,
+                    gouraud_polygon: self.gouraud_polygon,
+                    triangulate_polygon: self.triangulate_polygon,
+                    conic_filled: self.conic_filled,
+                    conic_smooth: self.conic_smooth,
+                    ellipse_angle: self.ellipse_angle,
+                    curve_kind: self.curve_kind,
+                    curve_method: self.curve_method,
+                    curve_smooth: self.curve_smooth,
+                    rect_corner_radius: self.rect_corner_radius,
+                    rect_stroke: self.rect_stroke,
+                    rect_stroke_width: self.rect_stroke_width,
+                    rect_filled: self.rect_filled,
+                    rect_fill: self.rect_fill,
+                }),
+            },
+            history,
+        };
+        let json = serde_json::to_vec_pretty(&manifest).map_err(io::Error::other)?;
+        writer.write_file("manifest.json", &json)?;
+
+        writer.zip.finish()?;
+        Ok(())
+    }
+
+    /// Открыть проект из файла path. Текущий документ заменяется только
+    /// после успешного чтения всего файла.
+    fn read_project(&mut self, path: &Path) -> io::Result<()> {
+        let mut reader = ProjectReader {
+            zip: zip::ZipArchive::new(File::open(path)?)?,
+        };
+
+        let manifest: Manifest = serde_json::from_slice(&reader.read_file("manifest.json")?)
+            .map_err(|error| invalid_data(error.to_string()))?;
+        if manifest.format != FORMAT_NAME {
+            return Err(invalid_data("файл не является проектом"));
+        }
+        if manifest.version > FORMAT_VERSION {
+            return Err(invalid_data(format!(
+                "проект сохранён в более новой версии формата ({})",
+                manifest.version
+            )));
+        }
+
+        let size = [manifest.width, manifest.height];
+        let layers = reader.read_stack(&manifest.layers, Some(size))?;
+        let history = if manifest.version >= 2 {
+            let mut undo = Vec::new();
+            for step in &manifest.history.undo_steps {
+                undo.push(reader.read_step(step)?);
+            }
+            let mut redo = Vec::new();
+            for step in &manifest.history.redo_steps {
+                redo.push(reader.read_step(step)?);
+            }
+            History::from_snapshots(undo, redo)
+        } else {
+            // версия 1 хранит полные состояния слоёв
+            let mut undo = Vec::new();
+            for stack in &manifest.history.undo {
+                undo.push(reader.read_stack(stack, None)?);
+            }
+            let mut redo = Vec::new();
+            for stack in &manifest.history.redo {
+                redo.push(reader.read_stack(stack, None)?);
+            }
+            History::from_states(undo, redo, &layers)
+        };
+
+        let state = manifest.state;
+        let bucket_image = match &state.bucket_image {
+            Some(file) => Some(
+                image::load_from_memory(&reader.read_file(file)?)
+                    .map_err(|error| invalid_data(error.to_string()))?,
+            ),
+            None => None,
+        };
+
+        // весь файл прочитан - можно заменять документ
+        self.layers = layers;
+        self.history = history;
+        self.cur_color = state.cur_color.unwrap_or(self.cur_color);
+        self.secondary_color = state.secondary_color.unwrap_or(self.secondary_color);
+        self.cur_instrument = state.instrument;
+        self.connectivity = state.connectivity;
+        self.points = state.points;
+        self.colors = state.colors;
+        self.contours = state.contours;
+        self.contour_colors = state.contour_colors;
+        self.texture_uvs = state.texture_uvs.unwrap_or(self.texture_uvs);
+        if let Some(tools) = state.tools {
+            self.polyline_mode = tools.polyline_mode;
+            self.texture_sampler = tools.texture_sampler;
+            self.fill_rule = tools.fill_rule;
+            self.gouraud_polygon = tools.gouraud_polygon;
+            self.triangulate_polygon = tools.triangulate_polygon;
+            self.conic_filled = tools.conic_filled;
+            self.conic_smooth = tools.conic_smooth;
+            self.ellipse_angle = tools.ellipse_angle;
+            self.curve_kind = tools.curve_kind;
+            self.curve_method = tools.curve_method;
+            self.curve_smooth = tools.curve_smooth;
+            self.rect_corner_radius = tools.rect_corner_radius;
+            self.rect_stroke = tools.rect_stroke;
+            self.rect_stroke_width = tools.rect_stroke_width;
+            self.rect_filled = tools.rect_filled;
+            self.rect_fill = tools.rect_fill;
+        }
+        self.dragged_point = None;
+        match bucket_image {
+            Some(image) => self.set_bucket_image(&image),
+            None => {
+                self.loaded_image = None;
+                self.loaded_texture = None;
+            }
+        }
+        self.bucket_image_path = None;
+        self.ghost_state = None;
+        self.viewport.fit();
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    /// Сохранить проект в файловую систему
+    pub(super) fn save_project(&mut self) {
+        let path = rfd::FileDialog::new()
+            .add_filter("Project", &[EXTENSION])
+            .set_file_name(format!("project.{EXTENSION}"))
+            .save_file();
+
+        if let Some(path) = path
+            && let Err(error) = self.write_project(&path)
+        {
+            eprintln!("не удалось сохранить проект {path:?}: {error}");
+        }
+    }
+
+    /// Открыть проект из файловой системы
+    pub(super) fn open_project(&mut self, ctx: &egui::Context) {
+        let path = rfd::FileDialog::new()
+            .add_filter("Project", &[EXTENSION])
+            .pick_file();
+
+        if let Some(path) = path {
+            match self.read_project(&path) {
+                Ok(()) => ctx.request_repaint(),
+                Err(error) => eprintln!("не удалось открыть проект {path:?}: {error}"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Временный файл проекта, удаляемый по окончании теста.
+    struct TempFile(std::path::PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let file = format!(
+                "raster-algorithms-{name}-{}.{EXTENSION}",
+                std::process::id()
+            );
+            Self(std::env::temp_dir().join(file))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    /// Пиксели всех слоёв.
+    fn pixels(layers: &LayerStack) -> Vec<Color32> {
+        let [width, height] = layers.size();
+        layers
+            .layers()
+            .iter()
+            .flat_map(|layer| {
+                (0..height).flat_map(move |y| (0..width).map(move |x| layer.canvas[(x, y)]))
+            })
+            .collect()
+    }
+
+    /// Действие: закрасить пиксель (x, y) активного слоя цветом color.
+    fn paint(app: &mut ColorsApp, x: usize, y: usize, color: Color32) {
+        app.history.record(&app.layers);
+        app.layers.active_mut().canvas[(x, y)] = color;
+        app.history.end_action(&app.layers);
+    }
+
+    #[test]
+    fn project_round_trip() {
+        let mut app = ColorsApp {
+            layers: LayerStack::new(Canvas::new(6, 4)),
+            ..Default::default()
+        };
+        paint(&mut app, 1, 1, Color32::RED);
+        app.history.record(&app.layers);
+        app.layers.add();
+        app.layers.active_mut().opacity = 0.5;
+        app.layers.active_mut().blend_mode = BlendMode::Screen;
+        app.history.end_action(&app.layers);
+        paint(
+            &mut app,
+            2,
+            3,
+            Color32::from_rgba_unmultiplied(0, 0, 255, 100),
+        );
+        paint(&mut app, 5, 0, Color32::GREEN);
+        app.history.undo(&mut app.layers);
+
+        app.cur_color = Color32::from_rgb(1, 2, 3);
+        app.cur_instrument = Instrument::Curve;
+        app.points = vec![Pos2::new(1.0, 2.0), Pos2::new(3.5, 0.5)];
+        app.curve_kind = CurveKind::CatmullRom;
+        app.fill_rule = FillRule::NonZero;
+        app.polyline_mode = true;
+        app.rect_corner_radius = 7.0;
+
+        let file = TempFile::new("round-trip");
+        app.write_project(&file.0).unwrap();
+        let mut loaded = ColorsApp::default();
+        loaded.read_project(&file.0).unwrap();
+
+        assert_eq!(pixels(&loaded.layers), pixels(&app.layers));
+        assert_eq!(loaded.layers.active_index(), 1);
+        assert_eq!(loaded.layers.active().opacity, 0.5);
+        assert!(loaded.layers.active().blend_mode == BlendMode::Screen);
+        assert_eq!(loaded.cur_color, app.cur_color);
+        assert!(loaded.cur_instrument == Instrument::Curve);
+        assert_eq!(loaded.points, app.points);
+        assert!(loaded.curve_kind == CurveKind::CatmullRom);
+        assert!(loaded.fill_rule == FillRule::NonZero);
+        assert!(loaded.polyline_mode);
+        assert_eq!(loaded.rect_corner_radius, 7.0);
+
+        // история переносится целиком: и отмена, и повтор
+        assert_eq!(loaded.history.undo_snapshots().count(), 3);
+        assert!(loaded.history.redo(&mut loaded.layers));
+        assert_eq!(loaded.layers.active().canvas[(5, 0)], Color32::GREEN);
+        while loaded.history.undo(&mut loaded.layers) {}
+        assert_eq!(loaded.layers.layers().len(), 1);
+        assert_eq!(pixels(&loaded.layers), vec![Color32::WHITE; 24]);
+    }
+
+    #[test]
+    fn version_1_project_with_resized_history() {
+        let file = TempFile::new("version-1");
+        let mut writer = ProjectWriter {
+            zip: zip::ZipWriter::new(File::create(&file.0).unwrap()),
+            options: SimpleFileOptions::default(),
+        };
+        let current = LayerStack::new(Canvas::new(3, 2));
+        let older = LayerStack::new(Canvas::new_filled(5, 5, Color32::BLUE));
+        let layers = writer.write_stack(&current, Some("layers")).unwrap();
+        let undo = vec![writer.write_stack(&older, Some("history/undo/0")).unwrap()];
+        let manifest = Manifest {
+            format: String::from(FORMAT_NAME),
+            version: 1,
+            width: 3,
+            height: 2,
+            layers,
+            state: StateManifest::default(),
+            history: HistoryManifest {
+                undo,
+                ..Default::default()
+            },
+        };
+        writer
+            .write_file("manifest.json", &serde_json::to_vec(&manifest).unwrap())
+            .unwrap();
+        writer.zip.finish().unwrap();
+
+        let mut app = ColorsApp::default();
+        app.read_project(&file.0).unwrap();
+        assert_eq!(app.layers.size(), [3, 2]);
+        assert!(app.history.undo(&mut app.layers));
+        assert_eq!(app.layers.size(), [5, 5]);
+        assert_eq!(app.layers.active().canvas[(4, 4)], Color32::BLUE);
+    }
+
+    #[test]
+    fn newer_version_is_rejected() {
+        let file = TempFile::new("newer");
+        let mut writer = ProjectWriter {
+            zip: zip::ZipWriter::new(File::create(&file.0).unwrap()),
+            options: SimpleFileOptions::default(),
+        };
+        let manifest = format!(
+            r#"{{"format": "{FORMAT_NAME}", "version": {}, "width": 1, "height": 1, "layers": []}}"#,
+            FORMAT_VERSION + 1
+        );
+        writer
+            .write_file("manifest.json", manifest.as_bytes())
+            .unwrap();
+        writer.zip.finish().unwrap();
+
+        let mut app = ColorsApp::default();
+        let error = app.read_project(&file.0).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,167 @@
+use egui::Color32;
+
+use super::ColorsApp;
+use super::canvas::{Canvas, Dithering, FixedPalette};
+
+#[derive(PartialEq, Clone, Copy)]
+/// Откуда берётся палитра для квантования.
+pub enum PaletteSource {
+    Fixed(FixedPalette),
+    /// Палитра с панели слева
+    User,
+    /// Палитра, построенная медианным сечением цветов изображения
+    MedianCut,
+    /// Палитра, построенная кластеризацией k-средних
+    KMeans,
+}
+
+impl PaletteSource {
+    pub const ALL: [PaletteSource; 6] = [
+        PaletteSource::Fixed(FixedPalette::Web),
+        PaletteSource::Fixed(FixedPalette::Cga),
+        PaletteSource::Fixed(FixedPalette::Ega),
+        PaletteSource::User,
+        PaletteSource::MedianCut,
+        PaletteSource::KMeans,
+    ];
+
+    pub fn get_name(&self) -> String {
+        match self {
+            PaletteSource::Fixed(palette) => palette.get_name(),
+            PaletteSource::User => String::from("пользовательская"),
+            PaletteSource::MedianCut => String::from("медианное сечение"),
+            PaletteSource::KMeans => String::from("k-средних"),
+        }
+    }
+
+    /// Строится ли палитра по изображению.
+    fn is_generated(&self) -> bool {
+        matches!(self, PaletteSource::MedianCut | PaletteSource::KMeans)
+    }
+}
+
+/// Состояние окна квантования.
+pub struct QuantizeDialog {
+    source: PaletteSource,
+    /// Число цветов построенной палитры
+    colors: usize,
+    /// Наибольшее число итераций k-средних
+    iterations: usize,
+    dithering: Dithering,
+    /// Змеевидный обход строк при диффузии ошибки
+    serpentine: bool,
+}
+
+impl Default for QuantizeDialog {
+    fn default() -> Self {
+        Self {
+            source: PaletteSource::Fixed(FixedPalette::default()),
+            colors: 16,
+            iterations: 10,
+            dithering: Dithering::default(),
+            serpentine: true,
+        }
+    }
+}
+
+impl QuantizeDialog {
+    /// Палитра для холста canvas; user - пользовательская палитра.
+    fn palette(&self, canvas: &Canvas, user: &[Color32]) -> Vec<Color32> {
+        match self.source {
+            PaletteSource::Fixed(palette) => palette.colors(),
+            PaletteSource::User => user.to_vec(),
+            PaletteSource::MedianCut => canvas.median_cut_palette(self.colors),
+            PaletteSource::KMeans => canvas.kmeans_palette(self.colors, self.iterations),
+        }
+    }
+}
+
+// =============== Квантование ===============
+
+impl ColorsApp {
+    /// Отображает окно квантования активного слоя (или выделения на нём) по палитре
+    /// (Adjustments -> Quantize). Построенная палитра вычисляется по всему слою.
+    pub(super) fn show_quantize_window(&mut self, ctx: &egui::Context) {
+        let Some(mut dialog) = self.quantize_dialog.take() else {
+            return;
+        };
+        let user_palette = self.palette.colors.clone();
+
+        let mut open = true;
+        let mut apply = false;
+        egui::Window::new("Quantize")
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("палитра:");
+                    egui::ComboBox::from_id_salt("quantize_palette_combo_box")
+                        .selected_text(dialog.source.get_name())
+                        .show_ui(ui, |ui| {
+                            for source in PaletteSource::ALL {
+                                ui.selectable_value(&mut dialog.source, source, source.get_name());
+                            }
+                        });
+                });
+                if dialog.source.is_generated() {
+                    ui.horizontal(|ui| {
+                        ui.label("цветов:");
+                        ui.add(egui::Slider::new(&mut dialog.colors, 2..=256));
+                    });
+                }
+                if dialog.source == PaletteSource::KMeans {
+                    ui.horizontal(|ui| {
+                        ui.label("итераций:");
+                        ui.add(egui::Slider::new(&mut dialog.iterations, 1..=50));
+                    });
+                }
+                let empty = dialog.source == PaletteSource::User && user_palette.is_empty();
+                if empty {
+                    ui.label("пользовательская палитра пуста");
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("сглаживание:");
+                    egui::ComboBox::from_id_salt("quantize_dithering_combo_box")
+                        .selected_text(dialog.dithering.get_name())
+                        .show_ui(ui, |ui| {
+                            for dithering in Dithering::ALL {
+                                ui.selectable_value(
+                                    &mut dialog.dithering,
+                                    dithering,
+                                    dithering.get_name(),
+                                );
+                            }
+                        });
+                });
+                if dialog.dithering.is_error_diffusion() {
+                    ui.checkbox(&mut dialog.serpentine, "змеевидный обход")
+                        .on_hover_text("нечётные строки обходятся справа налево");
+                }
+
+                apply = ui.add_enabled(!empty, egui::Button::new("Apply")).clicked();
+            });
+
+        if apply {
+            // палитра строится только по цветам изменяемой области
+            let canvas = &self.layers.active().canvas;
+            let palette = match self.selection() {
+                Some(selection) => dialog.palette(&canvas.crop(selection), &user_palette),
+                None => dialog.palette(canvas, &user_palette),
+            };
+            self.map_active_layer(ctx, |canvas| {
+                canvas.quantize(&palette, dialog.dithering, dialog.serpentine)
+            });
+
+            #[cfg(debug_assertions)]
+            println!(
+                "квантование: палитра {}, сглаживание {}",
+                dialog.source.get_name(),
+                dialog.dithering.get_name()
+            );
+        } else if open {
+            self.quantize_dialog = Some(dialog);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use egui::Color32;
+
+use super::ColorsApp;
+use super::canvas::{Anchor, Resampling};
+
+#[derive(PartialEq, Clone, Copy)]
+/// Что меняет окно изменения размеров.
+pub enum ResizeKind {
+    /// Масштабирование изображения
+    Image,
+    /// Размер холста без масштабирования
+    Canvas,
+}
+
+/// Состояние окна изменения размеров.
+pub struct ResizeDialog {
+    kind: ResizeKind,
+    width: usize,
+    height: usize,
+    /// Сохранять соотношение сторон (только для масштабирования)
+    keep_aspect: bool,
+    resampling: Resampling,
+    anchor: Anchor,
+    /// Цвет новых областей нижнего слоя
+    fill: Color32,
+}
+
+// =============== Изменение размеров изображения и холста ===============
+
+impl ColorsApp {
+    /// Наибольшая сторона изображения, которую можно задать в окне
+    pub(super) const MAX_IMAGE_SIDE: usize = 16384;
+
+    /// Открыть окно изменения размеров.
+    pub(super) fn open_resize_dialog(&mut self, kind: ResizeKind) {
+        let [width, height] = self.layers.size();
+        self.resize_dialog = Some(ResizeDialog {
+            kind,
+            width,
+            height,
+            keep_aspect: true,
+            resampling: Resampling::default(),
+            anchor: Anchor::default(),
+            fill: Color32::TRANSPARENT,
+        });
+    }
+
+    /// Отображает окно изменения размеров (Image -> Resize / Canvas Size).
+    pub(super) fn show_resize_window(&mut self, ctx: &egui::Context) {
+        let Some(mut dialog) = self.resize_dialog.take() else {
+            return;
+        };
+        let [old_width, old_height] = self.layers.size();
+        let title = match dialog.kind {
+            ResizeKind::Image => "Resize",
+            ResizeKind::Canvas => "Canvas Size",
+        };
+
+        let mut open = true;
+        let mut apply = false;
+        egui::Window::new(title)
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label(format!("сейчас: {old_width} x {old_height}"));
+
+                let range = 1..=Self::MAX_IMAGE_SIDE;
+                ui.horizontal(|ui| {
+                    ui.label("Width:");
+                    if ui
+                        .add(egui::DragValue::new(&mut dialog.width).range(range.clone()))
+                        .changed()
+                        && dialog.keep_aspect
+                        && dialog.kind == ResizeKind::Image
+                    {
+                        dialog.height = (dialog.width as f32 * old_height as f32
+                            / old_width.max(1) as f32)
+                            .round()
+                            .max(1.0) as usize;
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Height:");
+                    if ui
+                        .add(egui::DragValue::new(&mut dialog.height).range(range))
+                        .changed()
+                        && dialog.keep_aspect
+                        && dialog.kind == ResizeKind::Image
+                    {
+                        dialog.width = (dialog.height as f32 * old_width as f32
+                            / old_height.max(1) as f32)
+                            .round()
+                            .max(1.0) as usize;
+                    }
+                });
+
+                match dialog.kind {
+                    ResizeKind::Image => {
+                        ui.checkbox(&mut dialog.keep_aspect, "сохранять пропорции");
+                        ui.horizontal(|ui| {
+                            ui.label("фильтр:");
+                            egui::ComboBox::from_id_salt("resampling_combo_box")
+                                .selected_text(dialog.resampling.get_name())
+                                .show_ui(ui, |ui| {
+                                    for filter in Resampling::ALL {
+                                        ui.selectable_value(
+                                            &mut dialog.resampling,
+                                            filter,
+                                            filter.get_name(),
+                                        );
+                                    }
+                                });
+                        });
+                    }
+                    ResizeKind::Canvas => {
+                        ui.label("положение изображения:");
+                        egui::Grid::new("anchor_grid").show(ui, |ui| {
+                            for (i, anchor) in Anchor::ALL.into_iter().enumerate() {
+                                ui.selectable_value(&mut dialog.anchor, anchor, anchor.get_arrow())
+                                    .on_hover_text(anchor.get_name());
+                                if i % 3 == 2 {
+                                    ui.end_row();
+                                }
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("заливка:");
+                            ui.color_edit_button_srgba(&mut dialog.fill);
+                        });
+                    }
+                }
+
+                ui.horizontal(|ui| {
+                    apply = ui.button("Apply").clicked();
+                });
+            });
+
+        if apply {
+            self.apply_resize(ctx, &dialog);
+        } else if open {
+            self.resize_dialog = Some(dialog);
+        }
+    }
+
+    /// Изменить размеры всех слоёв.
+    fn apply_resize(&mut self, ctx: &egui::Context, dialog: &ResizeDialog) {
+        let (width, height) = (dialog.width.max(1), dialog.height.max(1));
+        if [width, height] == self.layers.size() && dialog.kind == ResizeKind::Image {
+            return;
+        }
+
+        self.edit_layers(ctx, |layers| {
+            layers.map_canvases(|i, canvas| match dialog.kind {
+                ResizeKind::Image => canvas.resize(width, height, dialog.resampling),
+                ResizeKind::Canvas => {
+                    // цветом заливается только фон, верхние слои дополняются прозрачностью
+                    let fill = if i == 0 {
+                        dialog.fill
+                    } else {
+                        Color32::TRANSPARENT
+                    };
+                    canvas.resize_canvas(width, height, dialog.anchor, fill)
+                }
+            })
+        });
+        self.deselect();
+        self.viewport.fit();
+
+        #[cfg(debug_assertions)]
+        println!("новый размер изображения {width} x {height}");
+    }
+}
@@ -0,0 +1,156 @@
+use egui::{Color32, Pos2, Rect, Shape, Stroke};
+
+use super::ColorsApp;
+use super::canvas::{Canvas, PixelRect};
+
+// =============== Прямоугольное выделение ===============
+
+impl ColorsApp {
+    /// Длина штриха рамки выделения в пикселях экрана
+    const SELECTION_DASH: f32 = 4.0;
+
+    /// Текущее выделение, обрезанное по границам холста.
+    pub(super) fn selection(&self) -> Option<PixelRect> {
+        let [width, height] = self.layers.size();
+        self.selection
+            .and_then(|selection| selection.intersect(PixelRect::new(0, 0, width, height)))
+    }
+
+    /// Выделить весь холст.
+    pub(super) fn select_all(&mut self) {
+        let [width, height] = self.layers.size();
+        self.selection = Some(PixelRect::new(0, 0, width, height));
+    }
+
+    /// Снять выделение.
+    pub(super) fn deselect(&mut self) {
+        self.selection = None;
+        self.selection_anchor = None;
+    }
+
+    /// Пиксель холста под точкой экрана (точки за пределами холста
+    /// прижимаются к его краю, чтобы выделение можно было дотянуть до границы).
+    fn clamped_pixel(&self, screen_pos: Pos2, canvas_rect: Rect) -> Option<[usize; 2]> {
+        let [width, height] = self.layers.size();
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let pos = self.coord_screen_to_canvas(
+            screen_pos.clamp(canvas_rect.min, canvas_rect.max),
+            canvas_rect,
+        )?;
+        Some([
+            (pos.x.max(0.0) as usize).min(width - 1),
+            (pos.y.max(0.0) as usize).min(height - 1),
+        ])
+    }
+
+    /// Обрабатывает прямоугольное выделение: перетаскивание выделяет область,
+    /// щелчок или Escape снимают выделение.
+    pub(super) fn handle_select(&mut self, canvas_rect: Rect, response: &egui::Response) {
+        if response.ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.deselect();
+            return;
+        }
+
+        let pixel = response
+            .interact_pointer_pos()
+            .and_then(|pointer_pos| self.clamped_pixel(pointer_pos, canvas_rect));
+
+        if response.drag_started() {
+            self.selection_anchor = pixel;
+        }
+        if response.dragged()
+            && let (Some(anchor), Some(pixel)) = (self.selection_anchor, pixel)
+        {
+            self.selection = Some(PixelRect::from_corners(anchor, pixel));
+        } else if response.drag_stopped() {
+            self.selection_anchor = None;
+
+            #[cfg(debug_assertions)]
+            println!("выделена область {:?}", self.selection);
+        } else if response.clicked() {
+            self.deselect();
+        }
+    }
+
+    /// Рисует рамку выделения пунктиром поверх холста.
+    pub(super) fn draw_selection(&self, painter: &egui::Painter, canvas_rect: Rect) {
+        let Some(selection) = self.selection() else {
+            return;
+        };
+
+        let min = self.coord_canvas_to_screen(
+            Pos2::new(selection.x as f32, selection.y as f32),
+            canvas_rect,
+        );
+        let max = self.coord_canvas_to_screen(
+            Pos2::new(
+                (selection.x + selection.width) as f32,
+                (selection.y + selection.height) as f32,
+            ),
+            canvas_rect,
+        );
+        let rect = Rect::from_min_max(min, max);
+        let outline = [
+            rect.left_top(),
+            rect.right_top(),
+            rect.right_bottom(),
+            rect.left_bottom(),
+            rect.left_top(),
+        ];
+
+        // светлая подложка и тёмный пунктир видны на любом фоне
+        painter.add(Shape::line(
+            outline.to_vec(),
+            Stroke::new(1.0, Color32::WHITE),
+        ));
+        painter.extend(Shape::dashed_line(
+            &outline,
+            Stroke::new(1.0, Color32::BLACK),
+            Self::SELECTION_DASH,
+            Self::SELECTION_DASH,
+        ));
+    }
+
+    /// Заменить активный слой результатом map, а при наличии выделения -
+    /// только пиксели внутри выделения. Закреплённый слой не изменяется.
+    pub(super) fn map_active_layer(
+        &mut self,
+        ctx: &egui::Context,
+        map: impl FnOnce(&Canvas) -> Canvas,
+    ) {
+        if self.layers.active().locked {
+            return;
+        }
+        let result = self.mapped_active_layer(map);
+        self.edit_layers(ctx, |layers| layers.active_mut().canvas = result);
+    }
+
+    /// Холст активного слоя после map (при наличии выделения изменяется
+    /// только область выделения); сам слой не изменяется.
+    pub(super) fn mapped_active_layer(&self, map: impl FnOnce(&Canvas) -> Canvas) -> Canvas {
+        let canvas = &self.layers.active().canvas;
+        let result = map(canvas);
+        match self.selection() {
+            Some(selection) => {
+                let mut mapped = canvas.clone();
+                mapped.copy_region(&result, selection);
+                mapped
+            }
+            None => result,
+        }
+    }
+
+    /// Обрезать все слои по выделению.
+    pub(super) fn crop_to_selection(&mut self, ctx: &egui::Context) {
+        let Some(selection) = self.selection() else {
+            return;
+        };
+        self.edit_layers(ctx, |layers| {
+            layers.map_canvases(|_, canvas| canvas.crop(selection));
+        });
+        self.deselect();
+        self.viewport.fit();
+    }
+}
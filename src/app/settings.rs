@@ -0,0 +1,163 @@
+use std::path::PathBuf;
+
+use egui::Color32;
+use serde::{Deserialize, Serialize};
+
+use super::layers::LayerStack;
+use super::{ColorsApp, Instrument, canvas};
+
+/// Настройки, сохраняемые между запусками приложения.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    cur_color: Color32,
+    secondary_color: Color32,
+    recent_colors: Vec<Color32>,
+    instrument: Instrument,
+    connectivity: canvas::Connectivity,
+    canvas_size: [usize; 2],
+    show_pixel_grid: bool,
+    /// Последний открытый или сохранённый холст
+    canvas_path: Option<PathBuf>,
+    /// Последняя картинка для заливки
+    bucket_image_path: Option<PathBuf>,
+    /// Открывать при запуске последний холст и картинку для заливки
+    reopen_last: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            cur_color: Color32::BLACK,
+            secondary_color: Color32::WHITE,
+            recent_colors: Vec::new(),
+            instrument: Instrument::default(),
+            connectivity: canvas::Connectivity::default(),
+            canvas_size: [ColorsApp::INIT_CANVAS_WIDTH, ColorsApp::INIT_CANVAS_HEIGHT],
+            show_pixel_grid: false,
+            canvas_path: None,
+            bucket_image_path: None,
+            reopen_last: false,
+        }
+    }
+}
+
+// =============== Сохранение и восстановление настроек ===============
+
+impl ColorsApp {
+    /// Текущие настройки приложения.
+    pub(super) fn settings(&self) -> Settings {
+        Settings {
+            cur_color: self.cur_color,
+            secondary_color: self.secondary_color,
+            recent_colors: self.recent_colors.clone(),
+            instrument: self.cur_instrument,
+            connectivity: self.connectivity,
+            canvas_size: self.layers.size(),
+            show_pixel_grid: self.show_pixel_grid,
+            canvas_path: self.canvas_path.clone(),
+            bucket_image_path: self.bucket_image_path.clone(),
+            reopen_last: self.reopen_last,
+        }
+    }
+
+    /// Применить сохранённые настройки при запуске.
+    /// Если включено reopen_last, заново открываются последние холст и картинка для заливки.
+    pub(super) fn apply_settings(&mut self, settings: Settings) {
+        self.cur_color = settings.cur_color;
+        self.secondary_color = settings.secondary_color;
+        self.recent_colors = settings.recent_colors;
+        self.cur_instrument = settings.instrument;
+        self.connectivity = settings.connectivity;
+        self.show_pixel_grid = settings.show_pixel_grid;
+        self.reopen_last = settings.reopen_last;
+
+        let [width, height] = settings.canvas_size;
+        if width > 0 && height > 0 {
+            self.layers = LayerStack::new(canvas::Canvas::new(width, height));
+        }
+
+        if self.reopen_last {
+            if let Some(path) = &settings.canvas_path
+                && let Some(canvas) = Self::read_canvas(path)
+            {
+                self.layers = LayerStack::new(canvas);
+            }
+            if let Some(path) = &settings.bucket_image_path {
+                self.open_bucket_image(path);
+            }
+        }
+        self.canvas_path = settings.canvas_path;
+        self.bucket_image_path = settings.bucket_image_path;
+        self.needs_redraw = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// Хранилище eframe в памяти.
+    #[derive(Default)]
+    struct MemoryStorage(HashMap<String, String>);
+
+    impl eframe::Storage for MemoryStorage {
+        fn get_string(&self, key: &str) -> Option<String> {
+            self.0.get(key).cloned()
+        }
+
+        fn set_string(&mut self, key: &str, value: String) {
+            self.0.insert(key.to_owned(), value);
+        }
+
+        fn flush(&mut self) {}
+    }
+
+    #[test]
+    fn settings_survive_restart() {
+        let app = ColorsApp {
+            cur_color: Color32::from_rgba_unmultiplied(10, 20, 30, 128),
+            secondary_color: Color32::RED,
+            recent_colors: vec![Color32::GREEN, Color32::BLUE],
+            cur_instrument: Instrument::Ellipse,
+            connectivity: canvas::Connectivity::FOUR,
+            show_pixel_grid: true,
+            layers: LayerStack::new(canvas::Canvas::new(37, 21)),
+            ..Default::default()
+        };
+
+        let mut storage = MemoryStorage::default();
+        eframe::set_value(&mut storage, eframe::APP_KEY, &app.settings());
+        let settings: Settings = eframe::get_value(&storage, eframe::APP_KEY).unwrap();
+
+        let mut restored = ColorsApp::default();
+        restored.apply_settings(settings);
+        assert_eq!(restored.cur_color, app.cur_color);
+        assert_eq!(restored.secondary_color, Color32::RED);
+        assert_eq!(restored.recent_colors, app.recent_colors);
+        assert!(restored.cur_instrument == Instrument::Ellipse);
+        assert!(restored.connectivity == canvas::Connectivity::FOUR);
+        assert!(restored.show_pixel_grid);
+        assert_eq!(restored.layers.size(), [37, 21]);
+    }
+
+    #[test]
+    fn missing_fields_get_defaults() {
+        let mut storage = MemoryStorage::default();
+        eframe::Storage::set_string(
+            &mut storage,
+            eframe::APP_KEY,
+            String::from("(show_pixel_grid: true)"),
+        );
+        let settings: Settings = eframe::get_value(&storage, eframe::APP_KEY).unwrap();
+        assert!(settings.show_pixel_grid);
+        assert_eq!(settings.cur_color, Color32::BLACK);
+        assert_eq!(
+            settings.canvas_size,
+            [ColorsApp::INIT_CANVAS_WIDTH, ColorsApp::INIT_CANVAS_HEIGHT]
+        );
+        assert!(!settings.reopen_last);
+    }
+}
@@ -0,0 +1,211 @@
+use super::ColorsApp;
+use super::canvas::{Affine, Canvas, Resampling};
+
+#[derive(PartialEq, Clone, Copy)]
+/// Преобразование без потерь из меню Image.
+pub enum Transform {
+    /// Поворот на 90 градусов по часовой стрелке
+    Rotate90,
+    Rotate180,
+    /// Поворот на 90 градусов против часовой стрелки
+    Rotate270,
+    FlipHorizontal,
+    FlipVertical,
+}
+
+impl Transform {
+    pub const ALL: [Transform; 5] = [
+        Transform::Rotate90,
+        Transform::Rotate180,
+        Transform::Rotate270,
+        Transform::FlipHorizontal,
+        Transform::FlipVertical,
+    ];
+
+    /// Надпись пункта меню.
+    pub fn get_label(&self) -> &'static str {
+        match self {
+            Transform::Rotate90 => "Rotate 90° CW",
+            Transform::Rotate180 => "Rotate 180°",
+            Transform::Rotate270 => "Rotate 90° CCW",
+            Transform::FlipHorizontal => "Flip Horizontal",
+            Transform::FlipVertical => "Flip Vertical",
+        }
+    }
+
+    fn apply(&self, canvas: &Canvas) -> Canvas {
+        match self {
+            Transform::Rotate90 => canvas.rotate_90(),
+            Transform::Rotate180 => canvas.rotate_180(),
+            Transform::Rotate270 => canvas.rotate_270(),
+            Transform::FlipHorizontal => canvas.flip_horizontal(),
+            Transform::FlipVertical => canvas.flip_vertical(),
+        }
+    }
+}
+
+/// Состояние окна произвольного преобразования.
+pub struct TransformDialog {
+    /// Угол поворота в градусах (по часовой стрелке)
+    angle: f32,
+    /// Масштаб по осям в процентах
+    scale: [f32; 2],
+    /// Коэффициенты сдвига по осям
+    shear: [f32; 2],
+    resampling: Resampling,
+    /// Увеличить холст до размеров результата (при преобразовании всего изображения)
+    expand: bool,
+}
+
+impl Default for TransformDialog {
+    fn default() -> Self {
+        Self {
+            angle: 0.0,
+            scale: [100.0, 100.0],
+            shear: [0.0, 0.0],
+            resampling: Resampling::Bilinear,
+            expand: true,
+        }
+    }
+}
+
+impl TransformDialog {
+    /// Итоговое преобразование: сдвиг, затем масштаб, затем поворот.
+    fn affine(&self) -> Affine {
+        Affine::shear(self.shear[0], self.shear[1])
+            .then(Affine::scale(self.scale[0] / 100.0, self.scale[1] / 100.0))
+            .then(Affine::rotation(self.angle))
+    }
+}
+
+// =============== Геометрические преобразования изображения ===============
+
+impl ColorsApp {
+    /// Применить map к выделенной области активного слоя,
+    /// а без выделения - ко всем слоям целиком.
+    fn transform_image(&mut self, ctx: &egui::Context, map: impl Fn(&Canvas) -> Canvas) {
+        match self.selection() {
+            Some(selection) => {
+                if self.layers.active().locked {
+                    return;
+                }
+                let mut placed = None;
+                self.edit_layers(ctx, |layers| {
+                    placed = layers.active_mut().canvas.map_region(selection, &map);
+                });
+                self.selection = placed;
+            }
+            None => {
+                self.edit_layers(ctx, |layers| layers.map_canvases(|_, canvas| map(canvas)));
+                self.viewport.fit();
+            }
+        }
+    }
+
+    /// Выполнить преобразование без потерь из меню Image.
+    pub(super) fn apply_transform(&mut self, ctx: &egui::Context, transform: Transform) {
+        self.transform_image(ctx, |canvas| transform.apply(canvas));
+
+        #[cfg(debug_assertions)]
+        println!("выполнено преобразование {}", transform.get_label());
+    }
+
+    /// Отображает окно поворота, масштабирования и сдвига (Image -> Transform).
+    pub(super) fn show_transform_window(&mut self, ctx: &egui::Context) {
+        let Some(mut dialog) = self.transform_dialog.take() else {
+            return;
+        };
+        let selection = self.selection();
+        let has_selection = selection.is_some();
+        let source_size = selection.map_or(self.layers.size(), |rect| [rect.width, rect.height]);
+
+        let mut open = true;
+        let mut apply = false;
+        egui::Window::new("Transform")
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                if has_selection {
+                    ui.label("применяется к выделению на активном слое");
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("поворот:");
+                    ui.add(egui::Slider::new(&mut dialog.angle, -180.0..=180.0).suffix("°"));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("масштаб:");
+                    for scale in &mut dialog.scale {
+                        ui.add(egui::DragValue::new(scale).range(1.0..=1000.0).suffix("%"));
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("сдвиг:");
+                    ui.add(
+                        egui::DragValue::new(&mut dialog.shear[0])
+                            .speed(0.01)
+                            .range(-5.0..=5.0)
+                            .prefix("x "),
+                    );
+                    ui.add(
+                        egui::DragValue::new(&mut dialog.shear[1])
+                            .speed(0.01)
+                            .range(-5.0..=5.0)
+                            .prefix("y "),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    ui.label("интерполяция:");
+                    egui::ComboBox::from_id_salt("transform_resampling_combo_box")
+                        .selected_text(dialog.resampling.get_name())
+                        .show_ui(ui, |ui| {
+                            for filter in Resampling::ALL {
+                                ui.selectable_value(
+                                    &mut dialog.resampling,
+                                    filter,
+                                    filter.get_name(),
+                                );
+                            }
+                        });
+                });
+                if !has_selection {
+                    ui.checkbox(&mut dialog.expand, "расширить холст")
+                        .on_hover_text("иначе углы результата обрезаются по старому размеру");
+                }
+
+                // выделение преобразуется целиком, оно может выйти за свою прежнюю рамку
+                let affine = dialog.affine();
+                let expand = dialog.expand || has_selection;
+                let [width, height] = Canvas::transformed_size(source_size, affine, expand);
+                let singular = affine.inverse().is_none();
+                let too_large = width.max(height) > ColorsApp::MAX_IMAGE_SIDE;
+                if singular {
+                    ui.label("преобразование вырождено: изображение сжимается в линию");
+                } else if too_large {
+                    ui.label(format!(
+                        "результат {width} x {height} больше {} по стороне",
+                        ColorsApp::MAX_IMAGE_SIDE
+                    ));
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.button("Reset").clicked() {
+                        dialog = TransformDialog::default();
+                    }
+                    apply = ui
+                        .add_enabled(!singular && !too_large, egui::Button::new("Apply"))
+                        .clicked();
+                });
+            });
+
+        if apply {
+            let affine = dialog.affine();
+            let expand = dialog.expand || has_selection;
+            let resampling = dialog.resampling;
+            self.transform_image(ctx, |canvas| canvas.transform(affine, resampling, expand));
+        } else if open {
+            self.transform_dialog = Some(dialog);
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use egui::{Pos2, Rect, Vec2};
+
+use super::ColorsApp;
+
+/// Область просмотра холста: масштаб и сдвиг относительно центра панели.
+pub struct Viewport {
+    /// Масштаб (пикселей экрана на пиксель холста), None - вписать холст в панель
+    zoom: Option<f32>,
+    /// Сдвиг центра холста относительно центра панели (в пикселях экрана)
+    offset: Vec2,
+    /// Панель, в которой выводится холст
+    area: Rect,
+    /// Размеры холста
+    canvas_size: [usize; 2],
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self {
+            zoom: None,
+            offset: Vec2::ZERO,
+            area: Rect::ZERO,
+            canvas_size: [0, 0],
+        }
+    }
+}
+
+impl Viewport {
+    pub const MIN_ZOOM: f32 = 0.05;
+    pub const MAX_ZOOM: f32 = 128.0;
+    /// Целочисленные масштабы для быстрого выбора
+    pub const PRESETS: [f32; 7] = [1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0];
+
+    /// Обновить размеры панели и холста (вызывается каждый кадр).
+    pub fn set_bounds(&mut self, area: Rect, canvas_size: [usize; 2]) {
+        self.area = area;
+        self.canvas_size = canvas_size;
+    }
+
+    /// Масштаб, при котором холст целиком вписывается в панель.
+    fn fit_zoom(&self) -> f32 {
+        let width = self.canvas_size[0].max(1) as f32;
+        let height = self.canvas_size[1].max(1) as f32;
+        let zoom = (self.area.width() / width).min(self.area.height() / height);
+        if zoom.is_finite() && zoom > 0.0 {
+            zoom
+        } else {
+            1.0
+        }
+    }
+
+    /// Текущий масштаб.
+    pub fn zoom(&self) -> f32 {
+        self.zoom.unwrap_or_else(|| self.fit_zoom())
+    }
+
+    /// Вписан ли холст в панель.
+    pub fn is_fit(&self) -> bool {
+        self.zoom.is_none()
+    }
+
+    /// Панель, в которой выводится холст.
+    pub fn area(&self) -> Rect {
+        self.area
+    }
+
+    /// Прямоугольник на экране, в котором выводится холст.
+    /// Левый верхний угол выравнивается по пикселям экрана.
+    pub fn canvas_rect(&self) -> Rect {
+        let size = Vec2::new(self.canvas_size[0] as f32, self.canvas_size[1] as f32) * self.zoom();
+        let min = self.area.center() + self.offset - size / 2.0;
+        Rect::from_min_size(min.round(), size)
+    }
+
+    /// Вписать холст в панель.
+    pub fn fit(&mut self) {
+        self.zoom = None;
+        self.offset = Vec2::ZERO;
+    }
+
+    /// Установить масштаб относительно центра панели.
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom_around(zoom, self.area.center());
+    }
+
+    /// Увеличить масштаб до следующей степени двойки.
+    pub fn zoom_in(&mut self) {
+        let step = (self.zoom().log2() + 1e-3).floor() + 1.0;
+        self.set_zoom(step.exp2());
+    }
+
+    /// Уменьшить масштаб до предыдущей степени двойки.
+    pub fn zoom_out(&mut self) {
+        let step = (self.zoom().log2() - 1e-3).ceil() - 1.0;
+        self.set_zoom(step.exp2());
+    }
+
+    /// Установить масштаб, сохраняя на месте точку холста под anchor.
+    /// zoom - новый масштаб;
+    /// anchor - неподвижная точка экрана;
+    pub fn zoom_around(&mut self, zoom: f32, anchor: Pos2) {
+        let old_zoom = self.zoom();
+        let new_zoom = zoom.clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+
+        // вектор от центра холста до anchor масштабируется вместе с холстом
+        let to_anchor = anchor - (self.area.center() + self.offset);
+        self.offset += to_anchor - to_anchor * (new_zoom / old_zoom);
+        self.zoom = Some(new_zoom);
+    }
+
+    /// Сдвинуть холст на delta пикселей экрана.
+    pub fn pan(&mut self, delta: Vec2) {
+        // вписанный холст после сдвига перестаёт подстраиваться под панель
+        self.zoom = Some(self.zoom());
+        self.offset += delta;
+    }
+}
+
+// =============== Масштабирование и перемещение холста ===============
+
+impl ColorsApp {
+    /// Чувствительность масштабирования к прокрутке колеса мыши
+    const WHEEL_ZOOM_SPEED: f32 = 1.0 / 200.0;
+    /// Минимальный масштаб, при котором выводится сетка пикселей
+    const GRID_MIN_ZOOM: f32 = 4.0;
+    /// Размер клетки шахматной доски под прозрачными пикселями
+    const CHECKER_SIZE: f32 = 8.0;
+
+    /// Обрабатывает масштабирование колесом мыши и перемещение холста
+    /// средней кнопкой мыши или левой кнопкой с зажатым пробелом.
+    /// Возвращает true, если сейчас идёт перемещение и рисование нужно пропустить.
+    pub(super) fn handle_viewport(&mut self, response: &egui::Response) -> bool {
+        let ctx = &response.ctx;
+        self.viewport.set_bounds(response.rect, self.layers.size());
+
+        // масштабирование вокруг курсора
+        if let Some(hover_pos) = response.hover_pos() {
+            let (scroll, zoom_delta) = ctx.input(|i| (i.smooth_scroll_delta.y, i.zoom_delta()));
+            let factor = zoom_delta * (scroll * Self::WHEEL_ZOOM_SPEED).exp();
+            if factor != 1.0 {
+                let zoom = self.viewport.zoom() * factor;
+                self.viewport.zoom_around(zoom, hover_pos);
+            }
+        }
+
+        // перемещение
+        let space_down = !ctx.wants_keyboard_input() && ctx.input(|i| i.key_down(egui::Key::Space));
+        let middle_down = ctx.input(|i| i.pointer.middle_down());
+        if response.dragged_by(egui::PointerButton::Middle)
+            || (space_down && response.dragged_by(egui::PointerButton::Primary))
+        {
+            self.viewport.pan(response.drag_delta());
+        }
+
+        if space_down || middle_down {
+            if response.hovered() {
+                ctx.set_cursor_icon(if response.dragged() {
+                    egui::CursorIcon::Grabbing
+                } else {
+                    egui::CursorIcon::Grab
+                });
+            }
+            return true;
+        }
+        false
+    }
+
+    /// Рисует под холстом шахматную доску, видимую сквозь прозрачные пиксели.
+    /// Размер клетки постоянен на экране и не зависит от масштаба.
+    pub(super) fn draw_checkerboard(
+        &mut self,
+        ctx: &egui::Context,
+        painter: &egui::Painter,
+        canvas_rect: Rect,
+    ) {
+        let texture = self.checker_texture.get_or_insert_with(|| {
+            let light = egui::Color32::from_gray(255);
+            let dark = egui::Color32::from_gray(204);
+            ctx.load_texture(
+                "checkerboard",
+                egui::ColorImage::new([2, 2], vec![light, dark, dark, light]),
+                egui::TextureOptions::NEAREST_REPEAT,
+            )
+        });
+
+        // одно повторение текстуры - две клетки
+        let repeats = canvas_rect.size() / (2.0 * Self::CHECKER_SIZE);
+        painter.image(
+            texture.id(),
+            canvas_rect,
+            Rect::from_min_max(Pos2::ZERO, repeats.to_pos2()),
+            egui::Color32::WHITE,
+        );
+    }
+
+    /// Рисует поверх холста границы пикселей (только видимую часть).
+    pub(super) fn draw_pixel_grid(&self, painter: &egui::Painter, canvas_rect: Rect) {
+        let zoom = self.viewport.zoom();
+        if !self.show_pixel_grid || zoom < Self::GRID_MIN_ZOOM {
+            return;
+        }
+
+        let visible = painter.clip_rect().intersect(canvas_rect);
+        if !visible.is_positive() {
+            return;
+        }
+        let stroke = egui::Stroke::new(
+            1.0 / painter.ctx().pixels_per_point(),
+            egui::Color32::from_rgba_unmultiplied(128, 128, 128, 160),
+        );
+
+        let first_column = ((visible.left() - canvas_rect.left()) / zoom).floor() as i32;
+        let last_column = ((visible.right() - canvas_rect.left()) / zoom).ceil() as i32;
+        for column in first_column..=last_column {
+            let x = canvas_rect.left() + column as f32 * zoom;
+            painter.vline(x, visible.y_range(), stroke);
+        }
+
+        let first_row = ((visible.top() - canvas_rect.top()) / zoom).floor() as i32;
+        let last_row = ((visible.bottom() - canvas_rect.top()) / zoom).ceil() as i32;
+        for row in first_row..=last_row {
+            let y = canvas_rect.top() + row as f32 * zoom;
+            painter.hline(visible.x_range(), y, stroke);
+        }
+    }
+
+    /// Найти пиксель под курсором по положению холста в прошлом кадре.
+    /// Вызывается до вывода панелей, чтобы нижняя панель показывала пиксель этого кадра.
+    pub(super) fn update_hovered_pixel(&mut self, ctx: &egui::Context) {
+        let canvas_rect = self.viewport.canvas_rect();
+        // цвет пикселя берётся из итогового изображения
+        let [width, height] = self.composite.size();
+        self.hovered_pixel = ctx
+            .pointer_hover_pos()
+            // курсор над панелью холста, а не над меню или окном поверх неё
+            .filter(|&pos| {
+                self.viewport.area().contains(pos)
+                    && ctx
+                        .layer_id_at(pos)
+                        .is_none_or(|layer| layer.order == egui::Order::Background)
+            })
+            .and_then(|pos| self.coord_screen_to_canvas(pos, canvas_rect))
+            .map(|pos| [pos.x as usize, pos.y as usize])
+            .filter(|&[x, y]| x < width && y < height);
+    }
+
+    /// Отображает координаты и цвет пикселя под курсором.
+    pub(super) fn show_pixel_readout(&self, ui: &mut egui::Ui) {
+        let Some([x, y]) = self.hovered_pixel else {
+            ui.label("пиксель: -");
+            return;
+        };
+
+        let color = self.composite[(x, y)];
+        let [r, g, b, a] = color.to_srgba_unmultiplied();
+        ui.label(format!("пиксель: ({x}, {y})"));
+        egui::color_picker::show_color(ui, color, egui::Vec2::splat(ui.spacing().interact_size.y));
+        ui.label(format!(
+            "RGBA({r}, {g}, {b}, {a}) #{r:02X}{g:02X}{b:02X}{a:02X}"
+        ));
+    }
+
+    /// Отображает элементы управления масштабом.
+    pub(super) fn show_zoom_controls(&mut self, ui: &mut egui::Ui) {
+        ui.label("масштаб:");
+        egui::ComboBox::from_id_salt("zoom_combo_box")
+            .selected_text(format!("{:.0}%", self.viewport.zoom() * 100.0))
+            .show_ui(ui, |ui| {
+                for zoom in Viewport::PRESETS {
+                    if ui
+                        .selectable_label(self.viewport.zoom() == zoom, format!("{zoom:.0}x"))
+                        .clicked()
+                    {
+                        self.viewport.set_zoom(zoom);
+                    }
+                }
+            });
+
+        if ui.selectable_label(self.viewport.is_fit(), "Fit").clicked() {
+            self.viewport.fit();
+        }
+        if ui.button("100%").clicked() {
+            self.viewport.set_zoom(1.0);
+        }
+        ui.checkbox(&mut self.show_pixel_grid, "сетка")
+            .on_hover_text(format!(
+                "границы пикселей при масштабе от {:.0}x",
+                Self::GRID_MIN_ZOOM
+            ));
+    }
+}